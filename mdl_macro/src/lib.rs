@@ -145,6 +145,8 @@ pub fn data_source(_input: proc_macro::TokenStream) -> proc_macro::TokenStream {
             Postgres,
             #[serde(alias = "duckdb")]
             DuckDB,
+            #[serde(alias = "sqlite")]
+            SQLite,
         }
     };
     proc_macro::TokenStream::from(expanded)
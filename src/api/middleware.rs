@@ -0,0 +1,32 @@
+//! 中间件 - 为每个请求分配关联 ID
+
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use uuid::Uuid;
+
+use crate::error::with_correlation_id;
+
+/// 携带关联 ID 的请求头，请求中已有时沿用，并在响应中返回
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// 调用方传入的关联 ID 的最大长度
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// 在请求范围内设置关联 ID，错误响应体中的 `correlation_id` 取自这里
+pub async fn request_id(request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let mut response = with_correlation_id(id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
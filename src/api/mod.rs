@@ -1,10 +1,21 @@
 //! API 层 - HTTP 路由和处理器
 
+pub mod middleware;
+pub mod server;
+pub mod state;
 pub mod v3;
 
+pub use server::{serve, shutdown_signal};
+pub use state::AppState;
+
+use axum::extract::DefaultBodyLimit;
 use axum::Router;
 
 /// 创建主 API 路由
-pub fn router() -> Router {
-    Router::new().nest("/", v3::router())
+pub fn router(state: AppState) -> Router {
+    let body_limit = state.settings().server.body_limit;
+    Router::new()
+        .nest("/", v3::router(state))
+        .layer(DefaultBodyLimit::max(body_limit))
+        .layer(axum::middleware::from_fn(middleware::request_id))
 }
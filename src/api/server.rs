@@ -0,0 +1,157 @@
+//! 服务运行 - 监听请求并在收到关闭信号后优雅退出
+//!
+//! 收到信号后不再接受新连接，等待进行中的请求完成；超过配置的等待时间后
+//! 取消仍在执行的查询（数据库支持时在服务端取消），最后关闭连接池。
+
+use std::future::{Future, IntoFuture};
+use std::time::Duration;
+
+use axum::Router;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::api::AppState;
+use crate::error::Result;
+
+/// 取消查询后等待请求返回的最长时间
+const CANCEL_GRACE: Duration = Duration::from_secs(5);
+
+/// 运行服务直到 `shutdown` 完成且进行中的请求处理完毕
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    state: AppState,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<()> {
+    let stopping = CancellationToken::new();
+    let trigger = stopping.clone();
+    tokio::spawn(async move {
+        shutdown.await;
+        info!("Shutdown signal received, draining in-flight requests");
+        trigger.cancel();
+    });
+
+    let graceful = stopping.clone();
+    let server = axum::serve(listener, app)
+        .with_graceful_shutdown(async move { graceful.cancelled().await })
+        .into_future();
+    tokio::pin!(server);
+
+    let deadline = state.settings().server.shutdown_timeout();
+    let result = tokio::select! {
+        result = &mut server => result,
+        _ = async {
+            stopping.cancelled().await;
+            tokio::time::sleep(deadline).await;
+        } => {
+            let cancelled = state.queries().cancel_all();
+            warn!("Shutdown deadline exceeded, cancelled {cancelled} running queries");
+            match tokio::time::timeout(CANCEL_GRACE, &mut server).await {
+                Ok(result) => result,
+                Err(_) => {
+                    warn!("Requests still running after cancellation, exiting anyway");
+                    Ok(())
+                }
+            }
+        }
+    };
+
+    state.close();
+    info!("Server stopped");
+    result.map_err(Into::into)
+}
+
+/// 等待 SIGINT（Ctrl+C）或 SIGTERM
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("failed to listen for Ctrl+C: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("failed to listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use axum::extract::State;
+    use axum::routing::get;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::oneshot;
+
+    /// 模拟一个直到被取消或 `delay` 后才结束的查询
+    async fn slow(State(state): State<AppState>) -> &'static str {
+        let running = state.queries().register();
+        tokio::select! {
+            _ = running.token().cancelled_owned() => "cancelled",
+            _ = tokio::time::sleep(Duration::from_millis(200)) => "finished",
+        }
+    }
+
+    /// 发起请求，在查询开始执行后触发关闭，返回响应和服务的退出结果
+    async fn shutdown_during_request(shutdown_timeout_secs: u64) -> (String, Result<()>) {
+        let mut settings = Settings::default();
+        settings.server.shutdown_timeout_secs = shutdown_timeout_secs;
+        let state = AppState::from_settings(settings);
+        let app = Router::new()
+            .route("/slow", get(slow))
+            .with_state(state.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = oneshot::channel::<()>();
+        let server = tokio::spawn(serve(listener, app, state.clone(), async {
+            let _ = rx.await;
+        }));
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /slow HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        while state.queries().is_empty() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        tx.send(()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        (response, server.await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_in_flight_requests() {
+        let (response, result) = shutdown_during_request(30).await;
+        assert!(response.ends_with("finished"), "{response}");
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_cancels_queries_after_deadline() {
+        let (response, result) = shutdown_during_request(0).await;
+        assert!(response.ends_with("cancelled"), "{response}");
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,240 @@
+//! 应用状态 - 在所有请求之间共享的资源
+
+use std::sync::Arc;
+
+use crate::config::Settings;
+#[cfg(feature = "datafusion")]
+use crate::connector::DataFusionSessions;
+#[cfg(feature = "duckdb")]
+use crate::connector::DuckDbDatabases;
+#[cfg(any(feature = "datafusion", feature = "duckdb", feature = "sqlite"))]
+use crate::connector::FileAccess;
+#[cfg(feature = "mysql")]
+use crate::connector::MySqlPools;
+#[cfg(feature = "sqlite")]
+use crate::connector::SqliteFactory;
+use crate::connector::{ConnectorFactory, ConnectorRegistry, PostgresPools, RunningQueries};
+use crate::mdl::manifest::DataSource;
+
+/// 应用状态，克隆开销很小
+#[derive(Clone)]
+pub struct AppState {
+    settings: Arc<Settings>,
+    postgres: Arc<PostgresPools>,
+    #[cfg(feature = "mysql")]
+    mysql: Arc<MySqlPools>,
+    #[cfg(feature = "duckdb")]
+    duckdb: Arc<DuckDbDatabases>,
+    #[cfg(feature = "datafusion")]
+    datafusion: Arc<DataFusionSessions>,
+    connectors: Arc<ConnectorRegistry>,
+    queries: Arc<RunningQueries>,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::from_settings(Settings::default())
+    }
+}
+
+impl AppState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 按配置创建连接池等共享资源，并注册启用的内置连接器
+    pub fn from_settings(settings: Settings) -> Self {
+        let postgres = Arc::new(
+            PostgresPools::new(settings.database.pool_size)
+                .with_connect_timeout(settings.database.connect_timeout())
+                .with_max_pools(settings.database.max_pools)
+                .with_idle_timeout(settings.database.pool_idle_timeout()),
+        );
+        #[cfg(feature = "mysql")]
+        let mysql = Arc::new(
+            MySqlPools::new(settings.database.pool_size)
+                .with_file_access(FileAccess::from_config(&settings.files))
+                .with_connect_timeout(settings.database.connect_timeout())
+                .with_max_pools(settings.database.max_pools)
+                .with_idle_timeout(settings.database.pool_idle_timeout()),
+        );
+        #[cfg(feature = "duckdb")]
+        let duckdb = Arc::new(
+            DuckDbDatabases::new()
+                .with_file_access(FileAccess::from_config(&settings.files))
+                .with_max_databases(settings.database.max_pools)
+                .with_idle_timeout(settings.database.pool_idle_timeout()),
+        );
+        #[cfg(feature = "datafusion")]
+        let datafusion = Arc::new(
+            DataFusionSessions::new()
+                .with_file_access(FileAccess::from_config(&settings.files))
+                .with_max_sessions(settings.database.max_pools)
+                .with_idle_timeout(settings.database.pool_idle_timeout()),
+        );
+
+        let mut connectors = ConnectorRegistry::new();
+        connectors.register("postgres", DataSource::Postgres, postgres.clone());
+        #[cfg(feature = "mysql")]
+        connectors.register("mysql", DataSource::MySQL, mysql.clone());
+        #[cfg(feature = "duckdb")]
+        connectors.register("duckdb", DataSource::DuckDB, duckdb.clone());
+        #[cfg(feature = "datafusion")]
+        connectors.register("datafusion", DataSource::Datafusion, datafusion.clone());
+        #[cfg(feature = "sqlite")]
+        connectors.register(
+            "sqlite",
+            DataSource::SQLite,
+            SqliteFactory::new()
+                .with_busy_timeout(settings.database.connect_timeout())
+                .with_file_access(FileAccess::from_config(&settings.files)),
+        );
+
+        Self {
+            settings: Arc::new(settings),
+            postgres,
+            #[cfg(feature = "mysql")]
+            mysql,
+            #[cfg(feature = "duckdb")]
+            duckdb,
+            #[cfg(feature = "datafusion")]
+            datafusion,
+            connectors: Arc::new(connectors),
+            queries: Arc::default(),
+        }
+    }
+
+    /// 注册连接器，已有同名连接器时替换；用于下游 crate 提供的插件连接器
+    ///
+    /// 已克隆出的状态不受影响，应在创建路由前调用
+    pub fn with_connector(
+        mut self,
+        name: &str,
+        dialect: DataSource,
+        factory: impl ConnectorFactory + 'static,
+    ) -> Self {
+        Arc::make_mut(&mut self.connectors).register(name, dialect, factory);
+        self
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Postgres 连接池
+    pub fn postgres(&self) -> &PostgresPools {
+        &self.postgres
+    }
+
+    /// MySQL 连接池
+    #[cfg(feature = "mysql")]
+    pub fn mysql(&self) -> &MySqlPools {
+        &self.mysql
+    }
+
+    /// 已打开的 DuckDB 数据库
+    #[cfg(feature = "duckdb")]
+    pub fn duckdb(&self) -> &DuckDbDatabases {
+        &self.duckdb
+    }
+
+    /// DataFusion 会话
+    #[cfg(feature = "datafusion")]
+    pub fn datafusion(&self) -> &DataFusionSessions {
+        &self.datafusion
+    }
+
+    /// 已注册的连接器
+    pub fn connectors(&self) -> &ConnectorRegistry {
+        &self.connectors
+    }
+
+    /// 正在执行的查询
+    pub fn queries(&self) -> &Arc<RunningQueries> {
+        &self.queries
+    }
+
+    /// 关闭所有连接器持有的连接池等资源，服务退出前调用
+    pub fn close(&self) {
+        self.connectors.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::ConnectorContext;
+    use crate::error::{Error, Result};
+    use crate::mdl::manifest::Manifest;
+    use crate::model::{ConnectionInfo, QueryResponse, SslMode};
+
+    /// 声明的能力与连接器的实际行为一致
+    #[tokio::test]
+    async fn test_capabilities_match_behavior() {
+        let dir = std::env::temp_dir().join(format!("mimir-state-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let csv = dir.join("orders.csv");
+        std::fs::write(&csv, "id\n1\n2\n").unwrap();
+        let mut settings = Settings::default();
+        settings.files.allowed_roots = vec![dir.clone()];
+        let state = AppState::from_settings(settings);
+        let manifest: Manifest =
+            serde_json::from_str(r#"{ "catalog": "wren", "schema": "public" }"#).unwrap();
+        // 接受连接后立即关闭的桩服务，外部数据库的连接总是失败，不依赖本机的网络环境
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let stub = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                drop(stream);
+            }
+        });
+        let base: ConnectionInfo =
+            serde_json::from_value(serde_json::json!({ "host": "127.0.0.1", "port": port }))
+                .unwrap();
+
+        for entry in state.connectors().iter() {
+            let name = entry.name();
+            let capabilities = entry.capabilities();
+            let query = |info: ConnectionInfo, sql: &'static str| {
+                let manifest = &manifest;
+                async move {
+                    let context = ConnectorContext {
+                        connection_info: &info,
+                        manifest,
+                    };
+                    entry.connector(&context)?.query(sql).await
+                }
+            };
+            let result: Result<QueryResponse> = query(base.clone(), "SELECT 1").await;
+            assert_eq!(result.is_ok(), capabilities.embedded, "{name}: {result:?}");
+
+            let tls = ConnectionInfo {
+                ssl_mode: SslMode::Require,
+                ..base.clone()
+            };
+            let rejected = matches!(query(tls, "SELECT 1").await, Err(Error::Validation(_)));
+            if capabilities.embedded {
+                assert!(
+                    !capabilities.tls,
+                    "{name} does not connect over the network"
+                );
+            } else {
+                assert_eq!(rejected, !capabilities.tls, "{name}");
+            }
+
+            let mut files = base.clone();
+            files
+                .files
+                .insert("orders".to_string(), csv.to_string_lossy().into_owned());
+            let result = query(files, "SELECT COUNT(*) FROM orders").await;
+            assert_eq!(
+                result.is_ok(),
+                capabilities.embedded && capabilities.files,
+                "{name}: {result:?}"
+            );
+        }
+
+        stub.abort();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
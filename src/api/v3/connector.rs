@@ -1,54 +1,315 @@
 //! v3 Connector API - 数据源连接器接口
 
-use crate::error::Error;
-use crate::model::{DryPlanRequest, QueryRequest};
+use super::stream::{ResultFormat, ResultStream};
+use crate::api::AppState;
+use crate::connector::{Connector, ConnectorContext, RunningQuery};
+use crate::engine::{Plan, Rewriter, SessionProperties};
+use crate::error::{correlation_id, Error, Result};
+use crate::mdl::manifest::DataSource;
+use crate::mdl::{decode_manifest, AnalyzedMdl};
+use crate::model::{
+    ConnectorResponse, ConnectorsResponse, DryPlanRequest, DryPlanResponse, QueryRequest,
+    QueryResponse,
+};
 use axum::{
-    extract::Path,
-    response::{IntoResponse, Json},
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderValue},
+    response::{IntoResponse, Json, Response},
     routing::{get, post},
     Router,
 };
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// 以该前缀开头的请求头作为会话属性传入，如 `x-wren-user-session_user`
+const SESSION_PROPERTY_HEADER_PREFIX: &str = "x-wren-user-";
+
+/// 查询响应中返回查询 ID 的响应头
+pub const QUERY_ID_HEADER: &str = "x-query-id";
 
 /// 创建 v3 connector 路由
-pub fn router() -> Router {
+pub fn router(state: AppState) -> Router {
     Router::new()
+        .route("/v3/connector", get(connectors))
         .route("/v3/connector/:data_source/query", post(query))
         .route("/v3/connector/:data_source/dry-plan", post(dry_plan))
         .route("/health", get(health))
+        .with_state(state)
 }
 
 /// 查询接口 - 执行 SQL 查询
+///
+/// 默认缓冲完整结果后返回；请求 `stream` 或 `Accept` 为 NDJSON、Arrow IPC 流、
+/// CSV、Parquet 时按该格式流式返回，CSV 和 Parquet 作为文件下载
 /// POST /v3/connector/{data_source}/query
 async fn query(
-    Path(_data_source): Path<String>,
-    Json(_request): Json<QueryRequest>,
-) -> impl IntoResponse {
-    // TODO: 实现查询逻辑
-    let err = Error::Planning("Not implemented yet".to_string());
-    (
-        axum::http::StatusCode::NOT_IMPLEMENTED,
-        Json(serde_json::json!({
-            "error": "Not implemented",
-            "message": err.to_string()
-        })),
-    )
+    State(state): State<AppState>,
+    Path(data_source): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<QueryRequest>,
+) -> Result<Response> {
+    let running = match &request.query_id {
+        Some(id) => state.queries().register_with_id(id)?,
+        None => state.queries().register(),
+    };
+    let query_id = running.id().to_string();
+    let format = ResultFormat::negotiate(&headers, request.stream);
+    if format == ResultFormat::Json {
+        let response = execute(&state, &data_source, &headers, &request, &running).await?;
+        return Ok(([(QUERY_ID_HEADER, query_id)], Json(response)).into_response());
+    }
+    if format == ResultFormat::Csv {
+        request.csv.validate()?;
+    }
+    let body = execute_stream(&state, &data_source, &headers, &request, format, running).await?;
+    let mut response = ([(QUERY_ID_HEADER, query_id.clone())], body).into_response();
+    let response_headers = response.headers_mut();
+    response_headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(format.content_type()),
+    );
+    // CSV、Parquet 作为文件下载，以查询 ID 命名
+    if let Some(extension) = format.file_extension() {
+        let name: String = query_id
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+                _ => '_',
+            })
+            .collect();
+        let disposition = format!("attachment; filename=\"{name}.{extension}\"");
+        if let Ok(disposition) = HeaderValue::from_str(&disposition) {
+            response_headers.insert(header::CONTENT_DISPOSITION, disposition);
+        }
+    }
+    Ok(response)
+}
+
+/// 规划完成、待执行的查询
+struct PreparedQuery {
+    connector: Box<dyn Connector>,
+    sql: String,
+    timeout: Duration,
+    max_rows: Option<u64>,
+}
+
+/// 计算超时和行数上限，规划 SQL 并选择连接器
+///
+/// `name` 为路径中的数据源名称，由连接器注册表确定改写使用的方言和执行查询的连接器
+fn prepare(
+    state: &AppState,
+    name: &str,
+    headers: &HeaderMap,
+    request: &QueryRequest,
+) -> Result<PreparedQuery> {
+    let data_source = state.connectors().dialect(name)?;
+    let limits = &state.settings().database;
+    let timeout = effective_limit(
+        "timeout_secs",
+        request.timeout_secs,
+        Some(limits.query_timeout_secs),
+    )?
+    .map_or(limits.query_timeout(), Duration::from_secs);
+    let max_rows = effective_limit("limit", request.limit, limits.max_rows)?;
+
+    let properties = session_properties(headers, &request.session_properties);
+    let mdl = analyze(data_source, &request.manifest_str)?;
+    // 多取一行，用于判断结果是否被截断
+    let plan = plan(
+        state,
+        data_source,
+        &mdl,
+        &request.sql,
+        properties,
+        max_rows.map(|n| n.saturating_add(1)),
+    )?;
+    let connector = state.connectors().connector(
+        name,
+        &ConnectorContext {
+            connection_info: &request.connection_info,
+            manifest: mdl.manifest(),
+        },
+    )?;
+    Ok(PreparedQuery {
+        connector,
+        sql: plan.sql,
+        timeout,
+        max_rows,
+    })
+}
+
+/// 规划后通过数据源对应的连接器执行，`running` 被取消或请求被丢弃时查询随之取消
+async fn execute(
+    state: &AppState,
+    data_source: &str,
+    headers: &HeaderMap,
+    request: &QueryRequest,
+    running: &RunningQuery,
+) -> Result<QueryResponse> {
+    let query = prepare(state, data_source, headers, request)?;
+    // 超时时丢弃查询，连接器负责在数据库侧取消
+    let response = tokio::select! {
+        response = query.connector.query_with_cancel(&query.sql, running.token()) => response?,
+        _ = tokio::time::sleep(query.timeout) => return Err(timeout_error(query.timeout)),
+    };
+    Ok(match query.max_rows {
+        Some(max_rows) => truncate(response, max_rows),
+        None => response,
+    })
+}
+
+/// 规划后以流的方式执行，查询开始返回结果前的错误仍以错误响应返回
+///
+/// 超时从请求开始计算，覆盖整个结果流；响应体被丢弃时查询随之取消
+async fn execute_stream(
+    state: &AppState,
+    data_source: &str,
+    headers: &HeaderMap,
+    request: &QueryRequest,
+    format: ResultFormat,
+    running: RunningQuery,
+) -> Result<Body> {
+    let query = prepare(state, data_source, headers, request)?;
+    let deadline = Instant::now() + query.timeout;
+    let result = tokio::select! {
+        result = query.connector.query_stream(&query.sql, running.token()) => result?,
+        _ = tokio::time::sleep_until(deadline) => return Err(timeout_error(query.timeout)),
+    };
+    ResultStream {
+        format,
+        columns: result.columns,
+        batches: result.batches,
+        max_rows: query.max_rows,
+        deadline,
+        timeout: query.timeout,
+        csv: request.csv.clone(),
+        running,
+        correlation_id: correlation_id(),
+    }
+    .into_body()
+}
+
+pub(super) fn timeout_error(timeout: Duration) -> Error {
+    Error::Timeout(format!(
+        "query did not finish within {}s",
+        timeout.as_secs()
+    ))
+}
+
+/// 请求和服务端配置的上限取较小值，请求中的值必须大于 0
+fn effective_limit(name: &str, requested: Option<u64>, server: Option<u64>) -> Result<Option<u64>> {
+    if requested == Some(0) {
+        return Err(Error::Validation(format!(
+            "`{name}` must be greater than 0"
+        )));
+    }
+    Ok(match (requested, server) {
+        (Some(requested), Some(server)) => Some(requested.min(server)),
+        (requested, server) => requested.or(server),
+    })
+}
+
+/// 超过 `max_rows` 的结果截断并标记
+fn truncate(mut response: QueryResponse, max_rows: u64) -> QueryResponse {
+    let max_rows = usize::try_from(max_rows).unwrap_or(usize::MAX);
+    if response.data.len() > max_rows {
+        response.data.truncate(max_rows);
+        response.truncated = true;
+    }
+    response
+}
+
+/// 连接器列表 - 已注册的连接器、使用的 SQL 方言和支持的能力
+/// GET /v3/connector
+async fn connectors(State(state): State<AppState>) -> Json<ConnectorsResponse> {
+    let connectors = state
+        .connectors()
+        .iter()
+        .map(|entry| ConnectorResponse {
+            name: entry.name().to_string(),
+            dialect: entry.dialect().to_string(),
+            capabilities: entry.capabilities(),
+        })
+        .collect();
+    Json(ConnectorsResponse { connectors })
 }
 
 /// 规划接口 - SQL 规划（不执行）
+///
+/// 不需要连接数据源；请求 `extended` 时附带规划诊断
 /// POST /v3/connector/{data_source}/dry-plan
 async fn dry_plan(
-    Path(_data_source): Path<String>,
-    Json(_request): Json<DryPlanRequest>,
-) -> impl IntoResponse {
-    // TODO: 实现 SQL 规划逻辑
-    let err = Error::Planning("Not implemented yet".to_string());
-    (
-        axum::http::StatusCode::NOT_IMPLEMENTED,
-        Json(serde_json::json!({
-            "error": "Not implemented",
-            "message": err.to_string()
-        })),
-    )
+    State(state): State<AppState>,
+    Path(data_source): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<DryPlanRequest>,
+) -> Result<Json<DryPlanResponse>> {
+    let data_source = state.connectors().dialect(&data_source)?;
+    let properties = session_properties(&headers, &request.session_properties);
+    let mdl = analyze(data_source, &request.manifest_str)?;
+    let Plan { sql, diagnostics } =
+        plan(&state, data_source, &mdl, &request.sql, properties, None)?;
+    Ok(Json(DryPlanResponse {
+        sql,
+        diagnostics: request.extended.then_some(diagnostics),
+    }))
+}
+
+/// 解码并分析 manifest
+///
+/// manifest 声明了 `data_source` 时必须与路径一致
+fn analyze(data_source: DataSource, manifest_str: &str) -> Result<AnalyzedMdl> {
+    let manifest = decode_manifest(manifest_str)?;
+    if let Some(declared) = manifest.data_source {
+        if declared != data_source {
+            return Err(Error::Validation(format!(
+                "manifest is defined for data source `{declared}`, but the request targets `{data_source}`"
+            )));
+        }
+    }
+    AnalyzedMdl::analyze(Arc::new(manifest))
+}
+
+/// 将 SQL 改写为路径中数据源可执行的 SQL
+fn plan(
+    state: &AppState,
+    data_source: DataSource,
+    mdl: &AnalyzedMdl,
+    sql: &str,
+    properties: SessionProperties,
+    limit: Option<u64>,
+) -> Result<Plan> {
+    let engine = &state.settings().engine;
+    let mut rewriter = Rewriter::new()
+        .with_default_data_source(engine.default_data_source)
+        .with_data_source(data_source)
+        .with_cls_mode(engine.cls_mode)
+        .with_session_properties(properties);
+    if let Some(limit) = limit {
+        rewriter = rewriter.with_limit(limit);
+    }
+    rewriter.plan(mdl, sql)
+}
+
+/// 合并请求头和请求体中的会话属性，同名时请求体优先
+fn session_properties(
+    headers: &HeaderMap,
+    from_request: &HashMap<String, String>,
+) -> SessionProperties {
+    let from_headers = headers.iter().filter_map(|(name, value)| {
+        let property = name.as_str().strip_prefix(SESSION_PROPERTY_HEADER_PREFIX)?;
+        Some((property.to_string(), value.to_str().ok()?.to_string()))
+    });
+    from_headers
+        .chain(
+            from_request
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone())),
+        )
+        .collect()
 }
 
 /// 健康检查
@@ -56,3 +317,286 @@ async fn dry_plan(
 async fn health() -> Json<serde_json::Value> {
     Json(serde_json::json!({ "status": "ok" }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::middleware::REQUEST_ID_HEADER;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    const MANIFEST: &str = r#"{
+        "catalog": "wren",
+        "schema": "public",
+        "dataSource": "POSTGRES",
+        "models": [
+            {
+                "name": "orders",
+                "tableReference": { "table": "orders" },
+                "columns": [{ "name": "o_orderkey", "type": "integer" }]
+            }
+        ]
+    }"#;
+
+    async fn post(uri: &str, body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+        post_with(AppState::new(), uri, body).await
+    }
+
+    async fn post_with(
+        state: AppState,
+        uri: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let request = Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = router(state).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn query_body(sql: &str) -> serde_json::Value {
+        serde_json::json!({
+            "sql": sql,
+            "manifest_str": MANIFEST,
+            "connection_info": {
+                "host": "127.0.0.1",
+                "port": 1,
+                "database": "db",
+                "user": "user",
+                "password": ""
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_dry_plan() {
+        let (status, body) = post(
+            "/v3/connector/postgres/dry-plan",
+            query_body("SELECT * FROM orders"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            body["sql"].as_str().unwrap().contains(r#"FROM "orders""#),
+            "{body}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dry_plan_extended() {
+        let (_, body) = post(
+            "/v3/connector/postgres/dry-plan",
+            query_body("SELECT o_orderkey FROM orders"),
+        )
+        .await;
+        assert!(body.get("diagnostics").is_none(), "{body}");
+
+        let mut request = query_body("SELECT o_orderkey FROM orders");
+        request["extended"] = true.into();
+        let (status, body) = post("/v3/connector/postgres/dry-plan", request).await;
+        assert_eq!(status, StatusCode::OK);
+        let diagnostics = &body["diagnostics"];
+        assert_eq!(diagnostics["models"], serde_json::json!(["orders"]));
+        assert_eq!(
+            diagnostics["columns"],
+            serde_json::json!([{ "model": "orders", "column": "o_orderkey" }])
+        );
+        assert_eq!(
+            diagnostics["row_level_access_controls"],
+            serde_json::json!([])
+        );
+    }
+
+    #[test]
+    fn test_row_and_timeout_limits() {
+        assert_eq!(
+            effective_limit("limit", Some(50), Some(10)).unwrap(),
+            Some(10)
+        );
+        assert_eq!(
+            effective_limit("limit", Some(5), Some(10)).unwrap(),
+            Some(5)
+        );
+        assert_eq!(effective_limit("limit", None, Some(10)).unwrap(), Some(10));
+        assert_eq!(effective_limit("limit", Some(5), None).unwrap(), Some(5));
+        assert_eq!(effective_limit("limit", None, None).unwrap(), None);
+        assert!(effective_limit("limit", Some(0), None).is_err());
+
+        let response = |rows: usize| QueryResponse {
+            data: vec![serde_json::json!([1]); rows],
+            columns: vec![],
+            truncated: false,
+        };
+        let truncated = truncate(response(3), 2);
+        assert_eq!(truncated.data.len(), 2);
+        assert!(truncated.truncated);
+        assert!(!truncate(response(2), 2).truncated);
+    }
+
+    #[tokio::test]
+    async fn test_query_rejects_mismatched_data_source() {
+        let (status, body) = post("/v3/connector/mysql/query", query_body("SELECT 1")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "VALIDATION_ERROR");
+        assert!(
+            body["message"].as_str().unwrap().contains("`postgres`"),
+            "{body}"
+        );
+
+        let (status, _) = post("/v3/connector/oracle/query", query_body("SELECT 1")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_query_reports_connection_failure() {
+        let (status, body) = post(
+            "/v3/connector/postgres/query",
+            query_body("SELECT * FROM orders"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY, "{body}");
+        assert_eq!(body["code"], "DATABASE_ERROR");
+
+        // 查询开始返回结果前失败时，流式请求同样得到错误响应
+        let mut request = query_body("SELECT * FROM orders");
+        request["stream"] = true.into();
+        let (status, body) = post("/v3/connector/postgres/query", request).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY, "{body}");
+        assert_eq!(body["code"], "DATABASE_ERROR");
+    }
+
+    #[tokio::test]
+    async fn test_list_connectors() {
+        let request = Request::get("/v3/connector").body(Body::empty()).unwrap();
+        let response = router(AppState::new()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let postgres = body["connectors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|c| c["name"] == "postgres")
+            .unwrap();
+        assert_eq!(postgres["dialect"], "postgres");
+        assert_eq!(postgres["capabilities"]["streaming"], true);
+        assert_eq!(postgres["capabilities"]["embedded"], false);
+    }
+
+    /// 插件连接器按注册的名称路由，使用声明的方言改写 SQL
+    #[tokio::test]
+    async fn test_query_routes_to_plugin_connector() {
+        struct Echo;
+
+        #[async_trait::async_trait]
+        impl Connector for Echo {
+            async fn query(&self, sql: &str) -> Result<QueryResponse> {
+                Ok(QueryResponse {
+                    data: vec![serde_json::json!([sql])],
+                    columns: vec![],
+                    truncated: false,
+                })
+            }
+
+            fn name(&self) -> &str {
+                "echo"
+            }
+        }
+
+        struct EchoFactory;
+
+        impl crate::connector::ConnectorFactory for EchoFactory {
+            fn connector(&self, _context: &ConnectorContext<'_>) -> Result<Box<dyn Connector>> {
+                Ok(Box::new(Echo))
+            }
+        }
+
+        let state = AppState::new().with_connector("Echo", DataSource::Postgres, EchoFactory);
+
+        let request = Request::post("/v3/connector/echo/query")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                query_body("SELECT o_orderkey FROM orders").to_string(),
+            ))
+            .unwrap();
+        let response = router(state).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(
+            body["data"][0][0]
+                .as_str()
+                .unwrap()
+                .contains(r#"FROM "orders""#),
+            "{body}"
+        );
+    }
+
+    /// DataFusion 直接查询本地文件，不需要外部数据库
+    #[cfg(feature = "datafusion")]
+    #[tokio::test]
+    async fn test_query_datafusion_files() {
+        let dir = std::env::temp_dir().join(format!("mimir-api-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let csv = dir.join("orders.csv");
+        std::fs::write(&csv, "o_orderkey\n1\n2\n").unwrap();
+
+        let mut request = query_body("SELECT COUNT(*) AS n FROM orders");
+        request["manifest_str"] = MANIFEST.replace("POSTGRES", "DATAFUSION").into();
+        request["connection_info"] = serde_json::json!({
+            "files": { "orders": csv.to_string_lossy() }
+        });
+        // 默认不允许读取任何文件
+        let (status, body) = post("/v3/connector/datafusion/query", request.clone()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+
+        let mut settings = crate::config::Settings::default();
+        settings.files.allowed_roots = vec![dir.clone()];
+        let (status, body) = post_with(
+            AppState::from_settings(settings),
+            "/v3/connector/datafusion/query",
+            request,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["data"], serde_json::json!([[2]]));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_error_carries_correlation_id() {
+        let request = Request::post("/v3/connector/postgres/dry-plan")
+            .header("content-type", "application/json")
+            .header(REQUEST_ID_HEADER, "req-42")
+            .body(Body::from(query_body("SELEC 1").to_string()))
+            .unwrap();
+        let response = crate::api::router(AppState::new())
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "req-42");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "PLANNING_ERROR");
+        assert_eq!(body["correlation_id"], "req-42");
+        assert_eq!(body["detail"]["line"], 1, "{body}");
+    }
+
+    #[tokio::test]
+    async fn test_undecodable_manifest_is_mdl_error() {
+        let mut body = query_body("SELECT 1");
+        body["manifest_str"] =
+            serde_json::json!(r#"{"catalog": "wren", "schema": "public", "models": [{}]}"#);
+        let (status, body) = post("/v3/connector/postgres/dry-plan", body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "MDL_ERROR");
+        assert_eq!(body["detail"]["path"], "models[0]", "{body}");
+        assert_eq!(body["detail"]["line"], 1, "{body}");
+    }
+}
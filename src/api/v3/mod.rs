@@ -1,5 +1,14 @@
 //! v3 API 版本
 
 pub mod connector;
+pub mod queries;
+mod stream;
 
-pub use connector::router;
+use axum::Router;
+
+use crate::api::AppState;
+
+/// 创建 v3 路由
+pub fn router(state: AppState) -> Router {
+    connector::router(state.clone()).merge(queries::router(state))
+}
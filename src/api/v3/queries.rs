@@ -0,0 +1,56 @@
+//! v3 Queries API - 管理正在执行的查询
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::delete,
+    Router,
+};
+
+use crate::api::AppState;
+use crate::error::{Error, Result};
+
+/// 创建 v3 queries 路由
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/v3/queries/:id", delete(cancel))
+        .with_state(state)
+}
+
+/// 取消接口 - 取消正在执行的查询
+/// DELETE /v3/queries/{id}
+async fn cancel(State(state): State<AppState>, Path(id): Path<String>) -> Result<StatusCode> {
+    if state.queries().cancel(&id) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(Error::NotFound(format!("query `{id}` is not running")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn delete(state: &AppState, id: &str) -> StatusCode {
+        let request = Request::delete(format!("/v3/queries/{id}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = router(state.clone()).oneshot(request).await.unwrap();
+        response.status()
+    }
+
+    #[tokio::test]
+    async fn test_cancel_query() {
+        let state = AppState::new();
+        let running = state.queries().register_with_id("q-1").unwrap();
+
+        assert_eq!(delete(&state, "q-1").await, StatusCode::NO_CONTENT);
+        assert!(running.token().is_cancelled());
+
+        drop(running);
+        assert_eq!(delete(&state, "q-1").await, StatusCode::NOT_FOUND);
+    }
+}
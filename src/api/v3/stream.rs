@@ -0,0 +1,538 @@
+//! 流式查询结果 - 按协商的格式输出行批次
+//!
+//! 响应体按连接器产出的行批次逐步写出，客户端读取变慢时不再拉取新的批次。
+//! 响应头发出后无法再修改状态码，中途出现的错误以 `ErrorResponse` 的结构写在结果末尾：
+//!
+//! - NDJSON：首行 `{"columns":[...]}`，之后每行一个数组，最后一行为
+//!   `{"truncated":false}` 或 `{"error":{...}}`
+//! - JSON：与缓冲的 `QueryResponse` 结构相同，出错时以 `"error"` 字段代替 `"truncated"`
+//! - Arrow IPC 流：列类型按 `format::arrow` 映射，每个行批次一条 `RecordBatch` 消息；
+//!   截断无法标记，生效的行数上限记录在 schema 元数据的 `max_rows` 中
+//! - CSV：见 `format::csv`，不标记截断
+//! - Parquet：见 `format::parquet`，文件元数据中的 `truncated` 标记截断
+//!
+//! JSON 以外的格式无法携带错误，出错时响应体异常中断，客户端读不到完整的结果
+
+use axum::body::{Body, Bytes};
+use axum::http::{header, HeaderMap};
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use super::connector::timeout_error;
+use crate::connector::{RowBatchStream, RunningQuery};
+use crate::error::{Error, ErrorResponse, Result};
+use crate::format::arrow::{self, IpcStreamWriter};
+use crate::format::csv::CsvWriter;
+use crate::format::parquet::ParquetWriter;
+use crate::model::{ColumnInfo, CsvOptions};
+
+/// NDJSON 的媒体类型
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Arrow IPC 流的媒体类型
+pub const ARROW_STREAM_CONTENT_TYPE: &str = "application/vnd.apache.arrow.stream";
+
+/// CSV 的媒体类型
+pub const CSV_CONTENT_TYPE: &str = "text/csv";
+
+/// Parquet 的媒体类型
+pub const PARQUET_CONTENT_TYPE: &str = "application/vnd.apache.parquet";
+
+/// 查询结果的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultFormat {
+    /// 缓冲完整结果后一次返回
+    Json,
+    /// 分块传输的 JSON
+    JsonStream,
+    /// 每行一个 JSON 值
+    Ndjson,
+    /// Arrow IPC 流
+    Arrow,
+    /// CSV 文件
+    Csv,
+    /// Parquet 文件
+    Parquet,
+}
+
+impl ResultFormat {
+    /// 按 `Accept` 选择 Arrow IPC 流、Parquet、CSV 或 NDJSON，都不接受时请求 `stream` 输出分块 JSON
+    pub fn negotiate(headers: &HeaderMap, stream: bool) -> Self {
+        let accepts = |content_type: &str| {
+            headers
+                .get_all(header::ACCEPT)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .filter_map(|media| media.split(';').next())
+                .any(|media| media.trim().eq_ignore_ascii_case(content_type))
+        };
+        if accepts(ARROW_STREAM_CONTENT_TYPE) {
+            ResultFormat::Arrow
+        } else if accepts(PARQUET_CONTENT_TYPE) {
+            ResultFormat::Parquet
+        } else if accepts(CSV_CONTENT_TYPE) {
+            ResultFormat::Csv
+        } else if accepts(NDJSON_CONTENT_TYPE) {
+            ResultFormat::Ndjson
+        } else if stream {
+            ResultFormat::JsonStream
+        } else {
+            ResultFormat::Json
+        }
+    }
+
+    /// 响应的 `Content-Type`
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ResultFormat::Json | ResultFormat::JsonStream => "application/json",
+            ResultFormat::Ndjson => NDJSON_CONTENT_TYPE,
+            ResultFormat::Arrow => ARROW_STREAM_CONTENT_TYPE,
+            ResultFormat::Csv => CSV_CONTENT_TYPE,
+            ResultFormat::Parquet => PARQUET_CONTENT_TYPE,
+        }
+    }
+
+    /// 作为文件下载的格式对应的扩展名
+    pub fn file_extension(self) -> Option<&'static str> {
+        match self {
+            ResultFormat::Csv => Some("csv"),
+            ResultFormat::Parquet => Some("parquet"),
+            _ => None,
+        }
+    }
+}
+
+/// 结果流中的事件
+enum Event {
+    Rows(Vec<Value>),
+    End { truncated: bool },
+    Error(Error),
+}
+
+/// 对行批次施加行数上限和截止时间，结果流结束前 `running` 保持注册
+struct Limiter {
+    batches: RowBatchStream,
+    remaining: Option<u64>,
+    deadline: Instant,
+    timeout: Duration,
+    truncated: bool,
+    _running: RunningQuery,
+}
+
+fn events(limiter: Limiter) -> impl Stream<Item = Event> {
+    stream::unfold(Some(limiter), |state| async move {
+        let mut state = state?;
+        if state.truncated {
+            return Some((Event::End { truncated: true }, None));
+        }
+        let batch = tokio::select! {
+            batch = state.batches.next() => batch,
+            _ = tokio::time::sleep_until(state.deadline) => {
+                return Some((Event::Error(timeout_error(state.timeout)), None));
+            }
+        };
+        match batch {
+            Some(Ok(mut rows)) => {
+                if let Some(remaining) = state.remaining.as_mut() {
+                    let len = rows.len() as u64;
+                    if len > *remaining {
+                        // 丢弃剩余的批次，连接器随之取消查询
+                        rows.truncate(usize::try_from(*remaining).unwrap_or(usize::MAX));
+                        state.truncated = true;
+                    }
+                    *remaining = remaining.saturating_sub(len);
+                }
+                Some((Event::Rows(rows), Some(state)))
+            }
+            Some(Err(e)) => Some((Event::Error(e), None)),
+            None => Some((Event::End { truncated: false }, None)),
+        }
+    })
+}
+
+/// 待输出的流式查询结果
+pub(super) struct ResultStream {
+    pub format: ResultFormat,
+    pub columns: Vec<ColumnInfo>,
+    pub batches: RowBatchStream,
+    /// 超过上限的行被丢弃并标记截断
+    pub max_rows: Option<u64>,
+    /// 到达截止时间时以超时错误结束，`timeout` 用于错误信息
+    pub deadline: Instant,
+    pub timeout: Duration,
+    pub csv: CsvOptions,
+    /// 结果输出完毕前保持注册
+    pub running: RunningQuery,
+    pub correlation_id: Option<String>,
+}
+
+impl ResultStream {
+    /// 构造流式响应体
+    pub fn into_body(self) -> Result<Body> {
+        let schema = || arrow::schema_with_max_rows(&self.columns, self.max_rows);
+        let writer = match self.format {
+            ResultFormat::Json | ResultFormat::JsonStream => Writer::Json { first_row: true },
+            ResultFormat::Ndjson => Writer::Ndjson,
+            ResultFormat::Arrow => Writer::Arrow(IpcStreamWriter::try_new(schema())?),
+            ResultFormat::Csv => Writer::Csv(CsvWriter::new(self.csv)),
+            ResultFormat::Parquet => Writer::Parquet(ParquetWriter::try_new(schema())?),
+        };
+        let mut encoder = Encoder {
+            writer,
+            correlation_id: self.correlation_id,
+            query_id: self.running.id().to_string(),
+        };
+        let head = encoder.head(&self.columns);
+        let events = events(Limiter {
+            batches: self.batches,
+            remaining: self.max_rows,
+            deadline: self.deadline,
+            timeout: self.timeout,
+            truncated: false,
+            _running: self.running,
+        });
+        let chunks = stream::once(async move { Ok(head) })
+            .chain(events.map(move |event| encoder.encode(event)))
+            .filter(|chunk| {
+                std::future::ready(chunk.as_ref().map_or(true, |chunk| !chunk.is_empty()))
+            });
+        Ok(Body::from_stream(chunks))
+    }
+}
+
+/// 各输出格式的写入器
+enum Writer {
+    Json { first_row: bool },
+    Ndjson,
+    Arrow(IpcStreamWriter),
+    Csv(CsvWriter),
+    Parquet(ParquetWriter),
+}
+
+struct Encoder {
+    writer: Writer,
+    correlation_id: Option<String>,
+    query_id: String,
+}
+
+impl Encoder {
+    fn head(&mut self, columns: &[ColumnInfo]) -> Bytes {
+        #[derive(Serialize)]
+        struct Head<'a> {
+            columns: &'a [ColumnInfo],
+        }
+        let mut buf = match &mut self.writer {
+            Writer::Arrow(writer) => return writer.take().into(),
+            Writer::Csv(writer) => return writer.header(columns).into(),
+            Writer::Parquet(_) => return Bytes::new(),
+            Writer::Json { .. } | Writer::Ndjson => json(&Head { columns }),
+        };
+        if let Writer::Ndjson = self.writer {
+            buf.push(b'\n');
+        } else {
+            // 去掉右花括号，继续写入 `data` 字段
+            buf.pop();
+            buf.extend_from_slice(br#","data":["#);
+        }
+        buf.into()
+    }
+
+    /// JSON 以外的格式出错时返回错误，使响应体异常结束，客户端读不到完整的结果
+    fn encode(&mut self, event: Event) -> Result<Bytes> {
+        let mut buf = Vec::new();
+        match event {
+            Event::Rows(rows) => match &mut self.writer {
+                Writer::Json { first_row } => {
+                    for row in &rows {
+                        if !std::mem::take(first_row) {
+                            buf.push(b',');
+                        }
+                        buf.extend(json(row));
+                    }
+                }
+                Writer::Ndjson => {
+                    for row in &rows {
+                        buf.extend(json(row));
+                        buf.push(b'\n');
+                    }
+                }
+                Writer::Arrow(writer) => buf = writer.write(&rows)?,
+                Writer::Csv(writer) => buf = writer.write(&rows),
+                Writer::Parquet(writer) => buf = writer.write(&rows)?,
+            },
+            Event::End { truncated } => match &mut self.writer {
+                Writer::Arrow(writer) => buf = writer.finish()?,
+                Writer::Csv(_) => {}
+                Writer::Parquet(writer) => buf = writer.finish(truncated)?,
+                Writer::Json { .. } | Writer::Ndjson => {
+                    self.tail(&mut buf, "truncated", &truncated)
+                }
+            },
+            Event::Error(err) => {
+                let response = ErrorResponse::new(&err, self.correlation_id.clone());
+                if err.status_code().is_server_error() {
+                    tracing::error!(
+                        code = response.code,
+                        correlation_id = response.correlation_id,
+                        query_id = self.query_id,
+                        "query stream failed: {}",
+                        response.message
+                    );
+                }
+                match self.writer {
+                    Writer::Json { .. } | Writer::Ndjson => self.tail(&mut buf, "error", &response),
+                    _ => return Err(err),
+                }
+            }
+        }
+        Ok(buf.into())
+    }
+
+    /// 写入 JSON 结果末尾的 `key` 字段
+    fn tail<T: Serialize>(&self, buf: &mut Vec<u8>, key: &str, value: &T) {
+        if let Writer::Ndjson = self.writer {
+            buf.extend(json(&serde_json::json!({ key: value })));
+            buf.push(b'\n');
+        } else {
+            buf.extend_from_slice(b"],");
+            buf.extend(json(&key));
+            buf.push(b':');
+            buf.extend(json(value));
+            buf.push(b'}');
+        }
+    }
+}
+
+fn json<T: Serialize + ?Sized>(value: &T) -> Vec<u8> {
+    serde_json::to_vec(value).expect("query results always serialize to JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::RunningQueries;
+    use crate::model::QueryResponse;
+    use axum::body::to_bytes;
+    use std::sync::Arc;
+
+    fn columns() -> Vec<ColumnInfo> {
+        vec![ColumnInfo {
+            name: "n".to_string(),
+            data_type: "int4".to_string(),
+        }]
+    }
+
+    fn rows(range: std::ops::Range<i64>) -> Vec<Value> {
+        range.map(|n| serde_json::json!([n])).collect()
+    }
+
+    fn result_stream(
+        format: ResultFormat,
+        batches: RowBatchStream,
+        max_rows: Option<u64>,
+        queries: &Arc<RunningQueries>,
+    ) -> ResultStream {
+        ResultStream {
+            format,
+            columns: columns(),
+            batches,
+            max_rows,
+            deadline: Instant::now() + Duration::from_secs(60),
+            timeout: Duration::from_secs(60),
+            csv: CsvOptions::default(),
+            running: queries.register(),
+            correlation_id: Some("req-1".to_string()),
+        }
+    }
+
+    /// 输出结果，响应体异常结束时返回错误
+    async fn try_collect(
+        format: ResultFormat,
+        batches: Vec<crate::error::Result<Vec<Value>>>,
+        max_rows: Option<u64>,
+    ) -> std::result::Result<Bytes, axum::Error> {
+        let queries = Arc::new(RunningQueries::new());
+        let body = result_stream(format, stream::iter(batches).boxed(), max_rows, &queries)
+            .into_body()
+            .unwrap();
+        let bytes = to_bytes(body, usize::MAX).await;
+        assert!(queries.is_empty());
+        bytes
+    }
+
+    async fn collect(
+        format: ResultFormat,
+        batches: Vec<crate::error::Result<Vec<Value>>>,
+        max_rows: Option<u64>,
+    ) -> String {
+        let bytes = try_collect(format, batches, max_rows).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(ResultFormat::negotiate(&headers, false), ResultFormat::Json);
+        assert_eq!(
+            ResultFormat::negotiate(&headers, true),
+            ResultFormat::JsonStream
+        );
+        headers.insert(
+            header::ACCEPT,
+            "application/json, application/x-ndjson; q=0.9"
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(
+            ResultFormat::negotiate(&headers, false),
+            ResultFormat::Ndjson
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_stream() {
+        let body = collect(
+            ResultFormat::Ndjson,
+            vec![Ok(rows(0..2)), Ok(rows(2..3))],
+            None,
+        )
+        .await;
+        assert_eq!(
+            body,
+            "{\"columns\":[{\"name\":\"n\",\"data_type\":\"int4\"}]}\n[0]\n[1]\n[2]\n{\"truncated\":false}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_json_stream_matches_buffered_response() {
+        let body = collect(
+            ResultFormat::JsonStream,
+            vec![Ok(rows(0..2)), Ok(vec![]), Ok(rows(2..4))],
+            Some(3),
+        )
+        .await;
+        let response: QueryResponse = serde_json::from_str(&body).unwrap();
+        assert_eq!(response.data, rows(0..3));
+        assert!(response.truncated);
+
+        let body = collect(ResultFormat::JsonStream, vec![], Some(3)).await;
+        let response: QueryResponse = serde_json::from_str(&body).unwrap();
+        assert!(response.data.is_empty());
+        assert!(!response.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_error_ends_stream() {
+        let failure = || Err(Error::Connector("connection reset".to_string()));
+        let body = collect(
+            ResultFormat::JsonStream,
+            vec![Ok(rows(0..1)), failure(), Ok(rows(1..2))],
+            None,
+        )
+        .await;
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["data"], serde_json::json!([[0]]));
+        assert_eq!(body["error"]["code"], "CONNECTOR_ERROR");
+        assert_eq!(body["error"]["correlation_id"], "req-1");
+
+        let body = collect(ResultFormat::Ndjson, vec![failure()], None).await;
+        let last: Value = serde_json::from_str(body.lines().last().unwrap()).unwrap();
+        assert_eq!(last["error"]["code"], "CONNECTOR_ERROR");
+    }
+
+    #[tokio::test]
+    async fn test_deadline_ends_stream() {
+        let queries = Arc::new(RunningQueries::new());
+        let mut result = result_stream(
+            ResultFormat::Ndjson,
+            stream::pending().boxed(),
+            None,
+            &queries,
+        );
+        result.deadline = Instant::now();
+        let bytes = to_bytes(result.into_body().unwrap(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(bytes.to_vec()).unwrap();
+        let last: Value = serde_json::from_str(body.lines().last().unwrap()).unwrap();
+        assert_eq!(last["error"]["code"], "QUERY_TIMEOUT");
+    }
+
+    #[tokio::test]
+    async fn test_arrow_stream() {
+        use arrow_array::cast::AsArray;
+        use arrow_array::types::Int32Type;
+        use arrow_ipc::reader::StreamReader;
+
+        let bytes = try_collect(
+            ResultFormat::Arrow,
+            vec![Ok(rows(0..2)), Ok(vec![]), Ok(rows(2..5))],
+            Some(3),
+        )
+        .await
+        .unwrap();
+        let reader = StreamReader::try_new(bytes.as_ref(), None).unwrap();
+        assert_eq!(reader.schema().metadata()["max_rows"], "3");
+        let batches = reader.collect::<std::result::Result<Vec<_>, _>>().unwrap();
+        let values: Vec<i32> = batches
+            .iter()
+            .flat_map(|batch| {
+                batch
+                    .column(0)
+                    .as_primitive::<Int32Type>()
+                    .values()
+                    .to_vec()
+            })
+            .collect();
+        assert_eq!(values, vec![0, 1, 2]);
+
+        // 出错时响应体异常结束
+        let failure = Err(Error::Connector("connection reset".to_string()));
+        let result = try_collect(ResultFormat::Arrow, vec![Ok(rows(0..1)), failure], None).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_csv_and_parquet_export() {
+        let body = collect(
+            ResultFormat::Csv,
+            vec![Ok(rows(0..2)), Ok(rows(2..4))],
+            Some(3),
+        )
+        .await;
+        assert_eq!(body, "n\r\n0\r\n1\r\n2\r\n");
+
+        let bytes = try_collect(
+            ResultFormat::Parquet,
+            vec![Ok(rows(0..2)), Ok(rows(2..4))],
+            Some(3),
+        )
+        .await
+        .unwrap();
+        let builder =
+            parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder::try_new(bytes).unwrap();
+        let metadata = builder
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .unwrap();
+        assert!(metadata
+            .iter()
+            .any(|kv| kv.key == "truncated" && kv.value.as_deref() == Some("true")));
+        let rows: usize = builder
+            .build()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum();
+        assert_eq!(rows, 3);
+
+        let failure = Err(Error::Connector("connection reset".to_string()));
+        assert!(try_collect(ResultFormat::Csv, vec![failure], None)
+            .await
+            .is_err());
+    }
+}
@@ -1,25 +1,449 @@
 //! 配置模块
+//!
+//! 配置按以下顺序分层加载，后者覆盖前者：
+//! 内置默认值 -> 配置文件（TOML / YAML）-> 环境变量 -> 命令行参数。
+//! 环境变量和命令行参数一一对应，如 `MIMIR_PORT` 与 `--port`。
 
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::{Parser, ValueEnum};
 use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+
+use crate::connector::cache::{DEFAULT_IDLE_TIMEOUT, DEFAULT_MAX_POOLS};
+use crate::connector::postgres::DEFAULT_POOL_SIZE;
+use crate::engine::ClsMode;
+use crate::error::{Error, Result};
+use crate::mdl::manifest::DataSource;
 
 /// 应用配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Settings {
     /// 服务器配置
     pub server: ServerConfig,
+    /// 数据源连接配置
+    pub database: DatabaseConfig,
+    /// 日志配置
+    pub logging: LoggingConfig,
+    /// 引擎配置
+    pub engine: EngineConfig,
+    /// 嵌入式数据源允许访问的文件
+    pub files: FilesConfig,
 }
 
 /// 服务器配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct ServerConfig {
+    /// 监听地址
+    pub host: IpAddr,
     /// 服务器端口
     pub port: u16,
+    /// 请求体大小上限（字节）
+    pub body_limit: usize,
+    /// 关闭时等待进行中请求完成的最长时间（秒），超时后取消仍在执行的查询
+    pub shutdown_timeout_secs: u64,
+}
+
+impl ServerConfig {
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_secs)
+    }
 }
 
-impl Default for Settings {
+impl Default for ServerConfig {
     fn default() -> Self {
         Self {
-            server: ServerConfig { port: 8080 },
+            host: IpAddr::from([0, 0, 0, 0]),
+            port: 8080,
+            body_limit: 10 * 1024 * 1024,
+            shutdown_timeout_secs: 30,
+        }
+    }
+}
+
+/// 数据源连接配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// 每组连接信息对应连接池的最大连接数
+    pub pool_size: usize,
+    /// 每个数据源最多缓存的连接池（嵌入式数据源为数据库或会话）数量，超出时关闭最久
+    /// 未使用的一个
+    pub max_pools: usize,
+    /// 连接池未使用超过该时间（秒）后关闭
+    pub pool_idle_timeout_secs: u64,
+    /// 建立连接的超时时间（秒）
+    pub connect_timeout_secs: u64,
+    /// 单个查询的超时时间（秒），请求可以指定更短的超时
+    pub query_timeout_secs: u64,
+    /// 单个查询最多返回的行数，未设置时不限制；请求可以指定更小的上限
+    pub max_rows: Option<u64>,
+}
+
+impl DatabaseConfig {
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs)
+    }
+
+    pub fn pool_idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.pool_idle_timeout_secs)
+    }
+
+    pub fn query_timeout(&self) -> Duration {
+        Duration::from_secs(self.query_timeout_secs)
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            pool_size: DEFAULT_POOL_SIZE,
+            max_pools: DEFAULT_MAX_POOLS,
+            pool_idle_timeout_secs: DEFAULT_IDLE_TIMEOUT.as_secs(),
+            connect_timeout_secs: 10,
+            query_timeout_secs: 300,
+            max_rows: None,
+        }
+    }
+}
+
+/// 日志配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// 输出格式
+    pub format: LogFormat,
+    /// 日志级别过滤，语法同 `RUST_LOG`；设置了 `RUST_LOG` 时以其为准
+    pub level: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            level: "info".to_string(),
+        }
+    }
+}
+
+/// 日志输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// 便于阅读的文本格式
+    #[default]
+    Text,
+    /// 每行一个 JSON 对象
+    Json,
+}
+
+/// 引擎配置
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineConfig {
+    /// 默认数据源，请求和 manifest 都未指定时使用
+    #[serde_as(as = "DisplayFromStr")]
+    pub default_data_source: DataSource,
+    /// 列级访问控制不通过时的处理方式
+    pub cls_mode: ClsMode,
+}
+
+/// 嵌入式数据源（DuckDB、DataFusion、SQLite）允许访问的文件，默认不允许任何文件
+///
+/// 请求中的数据库文件路径、`connection_info.files` 和 MySQL 的 `ssl_ca` 都按此校验
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilesConfig {
+    /// 允许访问的本地目录
+    pub allowed_roots: Vec<PathBuf>,
+    /// 允许访问的远程地址前缀，如 `s3://bucket/data/`；匹配的地址使用进程环境中的凭证
+    pub allowed_urls: Vec<String>,
+    /// 允许匿名访问的远程地址协议，如 `https`
+    pub allowed_schemes: Vec<String>,
+}
+
+/// 命令行参数，未指定的参数从对应的环境变量读取
+#[derive(Debug, Clone, Default, Parser)]
+#[command(version, about)]
+pub struct CliArgs {
+    /// 配置文件路径，按扩展名识别 TOML（.toml）或 YAML（.yaml / .yml）
+    #[arg(short, long, env = "MIMIR_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// 监听地址
+    #[arg(long, env = "MIMIR_HOST")]
+    pub host: Option<IpAddr>,
+    /// 服务器端口
+    #[arg(long, env = "MIMIR_PORT")]
+    pub port: Option<u16>,
+    /// 请求体大小上限（字节）
+    #[arg(long, env = "MIMIR_BODY_LIMIT")]
+    pub body_limit: Option<usize>,
+    /// 关闭时等待进行中请求完成的最长时间（秒）
+    #[arg(long, env = "MIMIR_SHUTDOWN_TIMEOUT")]
+    pub shutdown_timeout: Option<u64>,
+    /// 连接池最大连接数
+    #[arg(long, env = "MIMIR_POOL_SIZE")]
+    pub pool_size: Option<usize>,
+    /// 每个数据源最多缓存的连接池数量
+    #[arg(long, env = "MIMIR_MAX_POOLS")]
+    pub max_pools: Option<usize>,
+    /// 连接池空闲多久（秒）后关闭
+    #[arg(long, env = "MIMIR_POOL_IDLE_TIMEOUT")]
+    pub pool_idle_timeout: Option<u64>,
+    /// 建立连接的超时时间（秒）
+    #[arg(long, env = "MIMIR_CONNECT_TIMEOUT")]
+    pub connect_timeout: Option<u64>,
+    /// 单个查询的超时时间（秒）
+    #[arg(long, env = "MIMIR_QUERY_TIMEOUT")]
+    pub query_timeout: Option<u64>,
+    /// 单个查询最多返回的行数
+    #[arg(long, env = "MIMIR_MAX_ROWS")]
+    pub max_rows: Option<u64>,
+    /// 日志输出格式
+    #[arg(long, env = "MIMIR_LOG_FORMAT")]
+    pub log_format: Option<LogFormat>,
+    /// 日志级别过滤
+    #[arg(long, env = "MIMIR_LOG_LEVEL")]
+    pub log_level: Option<String>,
+    /// 默认数据源
+    #[arg(long, env = "MIMIR_DEFAULT_DATA_SOURCE")]
+    pub default_data_source: Option<DataSource>,
+    /// 列级访问控制不通过时的处理方式（deny / nullify）
+    #[arg(long, env = "MIMIR_CLS_MODE", value_parser = parse_cls_mode)]
+    pub cls_mode: Option<ClsMode>,
+    /// 嵌入式数据源允许访问的本地目录，多个目录以逗号分隔
+    #[arg(long, env = "MIMIR_ALLOWED_ROOTS", value_delimiter = ',')]
+    pub allowed_roots: Option<Vec<PathBuf>>,
+    /// 嵌入式数据源允许访问的远程地址前缀，多个前缀以逗号分隔
+    #[arg(long, env = "MIMIR_ALLOWED_URLS", value_delimiter = ',')]
+    pub allowed_urls: Option<Vec<String>>,
+    /// 嵌入式数据源允许匿名访问的远程地址协议，多个协议以逗号分隔
+    #[arg(long, env = "MIMIR_ALLOWED_SCHEMES", value_delimiter = ',')]
+    pub allowed_schemes: Option<Vec<String>>,
+}
+
+fn parse_cls_mode(s: &str) -> std::result::Result<ClsMode, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "deny" => Ok(ClsMode::Deny),
+        "nullify" => Ok(ClsMode::Nullify),
+        _ => Err(format!(
+            "unknown CLS mode `{s}`, expected `deny` or `nullify`"
+        )),
+    }
+}
+
+impl Settings {
+    /// 按 默认值 -> 配置文件 -> 环境变量 / 命令行参数 的顺序加载并校验配置
+    pub fn load(args: &CliArgs) -> Result<Self> {
+        let mut settings = match &args.config {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        settings.apply(args);
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// 读取配置文件，文件中未出现的配置项使用默认值
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            Error::Config(format!(
+                "failed to read config file `{}`: {e}",
+                path.display()
+            ))
+        })?;
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        let parsed = match extension.as_deref() {
+            Some("toml") => toml::from_str(&content).map_err(|e| e.to_string()),
+            Some("yaml" | "yml") => serde_yaml::from_str(&content).map_err(|e| e.to_string()),
+            _ => {
+                return Err(Error::Config(format!(
+                    "config file `{}` must have a .toml, .yaml or .yml extension",
+                    path.display()
+                )))
+            }
+        };
+        parsed.map_err(|e| Error::Config(format!("invalid config file `{}`: {e}", path.display())))
+    }
+
+    /// 用命令行参数（含环境变量）覆盖配置
+    pub fn apply(&mut self, args: &CliArgs) {
+        fn set<T: Clone>(target: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *target = value.clone();
+            }
         }
+        set(&mut self.server.host, &args.host);
+        set(&mut self.server.port, &args.port);
+        set(&mut self.server.body_limit, &args.body_limit);
+        set(
+            &mut self.server.shutdown_timeout_secs,
+            &args.shutdown_timeout,
+        );
+        set(&mut self.database.pool_size, &args.pool_size);
+        set(&mut self.database.max_pools, &args.max_pools);
+        set(
+            &mut self.database.pool_idle_timeout_secs,
+            &args.pool_idle_timeout,
+        );
+        set(
+            &mut self.database.connect_timeout_secs,
+            &args.connect_timeout,
+        );
+        set(&mut self.database.query_timeout_secs, &args.query_timeout);
+        if args.max_rows.is_some() {
+            self.database.max_rows = args.max_rows;
+        }
+        set(&mut self.logging.format, &args.log_format);
+        set(&mut self.logging.level, &args.log_level);
+        set(
+            &mut self.engine.default_data_source,
+            &args.default_data_source,
+        );
+        set(&mut self.engine.cls_mode, &args.cls_mode);
+        set(&mut self.files.allowed_roots, &args.allowed_roots);
+        set(&mut self.files.allowed_urls, &args.allowed_urls);
+        set(&mut self.files.allowed_schemes, &args.allowed_schemes);
+    }
+
+    /// 校验配置取值
+    pub fn validate(&self) -> Result<()> {
+        let positive = [
+            ("server.body_limit", self.server.body_limit as u64),
+            ("database.pool_size", self.database.pool_size as u64),
+            ("database.max_pools", self.database.max_pools as u64),
+            (
+                "database.pool_idle_timeout_secs",
+                self.database.pool_idle_timeout_secs,
+            ),
+            (
+                "database.connect_timeout_secs",
+                self.database.connect_timeout_secs,
+            ),
+            (
+                "database.query_timeout_secs",
+                self.database.query_timeout_secs,
+            ),
+            (
+                "database.max_rows",
+                self.database.max_rows.unwrap_or(u64::MAX),
+            ),
+        ];
+        if let Some((name, _)) = positive.iter().find(|(_, value)| *value == 0) {
+            return Err(Error::Config(format!("`{name}` must be greater than 0")));
+        }
+        tracing_subscriber::EnvFilter::try_new(&self.logging.level).map_err(|e| {
+            Error::Config(format!(
+                "invalid `logging.level` `{}`: {e}",
+                self.logging.level
+            ))
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(name: &str, content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mimir_config_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_toml_and_yaml() {
+        let path = write_config(
+            "settings.toml",
+            r#"
+                [server]
+                port = 9000
+
+                [engine]
+                default_data_source = "MYSQL"
+                cls_mode = "nullify"
+            "#,
+        );
+        let settings = Settings::from_file(&path).unwrap();
+        assert_eq!(settings.server.port, 9000);
+        assert_eq!(
+            settings.server.body_limit,
+            ServerConfig::default().body_limit
+        );
+        assert_eq!(settings.engine.default_data_source, DataSource::MySQL);
+        assert_eq!(settings.engine.cls_mode, ClsMode::Nullify);
+
+        let path = write_config(
+            "settings.yaml",
+            "database:\n  pool_size: 4\nlogging:\n  format: json\n",
+        );
+        let settings = Settings::from_file(&path).unwrap();
+        assert_eq!(settings.database.pool_size, 4);
+        assert_eq!(settings.logging.format, LogFormat::Json);
+    }
+
+    #[test]
+    fn test_cli_overrides_file() {
+        let path = write_config(
+            "override.toml",
+            "[server]\nport = 9000\nbody_limit = 1024\n",
+        );
+        let args = CliArgs::try_parse_from([
+            "mimir_well_engine",
+            "--config",
+            path.to_str().unwrap(),
+            "--port",
+            "9100",
+            "--cls-mode",
+            "NULLIFY",
+            "--allowed-roots",
+            "/srv/data,/srv/lake",
+            "--max-pools",
+            "8",
+            "--default-data-source",
+            "duckdb",
+        ])
+        .unwrap();
+        let settings = Settings::load(&args).unwrap();
+        assert_eq!(settings.server.port, 9100);
+        assert_eq!(settings.server.body_limit, 1024);
+        assert_eq!(settings.engine.cls_mode, ClsMode::Nullify);
+        assert_eq!(settings.database.max_pools, 8);
+        assert_eq!(settings.engine.default_data_source, DataSource::DuckDB);
+        assert_eq!(
+            settings.files.allowed_roots,
+            [PathBuf::from("/srv/data"), PathBuf::from("/srv/lake")]
+        );
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let path = write_config("unknown.toml", "[server]\nprot = 9000\n");
+        let err = Settings::from_file(&path).unwrap_err();
+        assert!(
+            matches!(&err, Error::Config(message) if message.contains("prot")),
+            "{err}"
+        );
+
+        let path = write_config("settings.json", "{}");
+        assert!(matches!(Settings::from_file(&path), Err(Error::Config(_))));
+
+        let mut settings = Settings::default();
+        settings.database.pool_size = 0;
+        assert_eq!(
+            settings.validate().unwrap_err().to_string(),
+            "Configuration error: `database.pool_size` must be greater than 0"
+        );
     }
 }
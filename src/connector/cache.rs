@@ -0,0 +1,206 @@
+//! 按 `ConnectionInfo` 缓存的连接池、数据库和会话
+//!
+//! 缓存的数量有上限，新建时超出上限则淘汰最久未使用的一项；超过空闲时间未使用的项
+//! 在下次访问缓存时淘汰。被淘汰的项通过 [`Release`] 释放，已取得它的查询不受影响。
+
+use std::collections::HashMap;
+#[cfg(any(test, feature = "datafusion", feature = "duckdb"))]
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::model::ConnectionInfo;
+
+/// 默认最多缓存的连接池数量
+pub const DEFAULT_MAX_POOLS: usize = 64;
+
+/// 连接池默认的空闲时间，超过后淘汰
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// 被淘汰或关闭的缓存项的释放方式
+pub(crate) trait Release {
+    fn release(self);
+}
+
+/// 共享的数据库和会话在最后一个引用释放时关闭
+impl<T> Release for Arc<T> {
+    fn release(self) {}
+}
+
+struct Entry<V> {
+    value: V,
+    last_used: Instant,
+    /// 最近一次使用的序号，按它确定最久未使用的项
+    order: u64,
+}
+
+/// 有数量上限和空闲时间的缓存
+pub(crate) struct PoolCache<V: Clone + Release> {
+    entries: Mutex<HashMap<ConnectionInfo, Entry<V>>>,
+    max_entries: usize,
+    idle_timeout: Duration,
+    clock: AtomicU64,
+}
+
+impl<V: Clone + Release> Default for PoolCache<V> {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            max_entries: DEFAULT_MAX_POOLS,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            clock: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<V: Clone + Release> PoolCache<V> {
+    pub(crate) fn set_max_entries(&mut self, max_entries: usize) {
+        self.max_entries = max_entries.max(1);
+    }
+
+    pub(crate) fn set_idle_timeout(&mut self, idle_timeout: Duration) {
+        self.idle_timeout = idle_timeout;
+    }
+
+    /// 获取连接信息对应的项，不存在时用 `create` 创建
+    #[cfg(any(test, feature = "datafusion", feature = "duckdb"))]
+    pub(crate) fn get_or_insert_with(
+        &self,
+        connection_info: &ConnectionInfo,
+        create: impl FnOnce() -> V,
+    ) -> V {
+        match self.get_or_try_insert_with(connection_info, || Ok::<_, Infallible>(create())) {
+            Ok(value) => value,
+            Err(never) => match never {},
+        }
+    }
+
+    /// 获取连接信息对应的项，不存在时用 `create` 创建，创建失败时不缓存
+    pub(crate) fn get_or_try_insert_with<E>(
+        &self,
+        connection_info: &ConnectionInfo,
+        create: impl FnOnce() -> Result<V, E>,
+    ) -> Result<V, E> {
+        let now = Instant::now();
+        let order = self.clock.fetch_add(1, Ordering::Relaxed);
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let mut evicted = Vec::new();
+        let idle = entries
+            .iter()
+            .filter(|(_, entry)| now.duration_since(entry.last_used) >= self.idle_timeout)
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        evicted.extend(idle.iter().filter_map(|key| entries.remove(key)));
+
+        let value = match entries.get_mut(connection_info) {
+            Some(entry) => {
+                entry.last_used = now;
+                entry.order = order;
+                entry.value.clone()
+            }
+            None => {
+                let value = create()?;
+                while entries.len() >= self.max_entries {
+                    let Some(oldest) = entries
+                        .iter()
+                        .min_by_key(|(_, entry)| entry.order)
+                        .map(|(key, _)| key.clone())
+                    else {
+                        break;
+                    };
+                    evicted.extend(entries.remove(&oldest));
+                }
+                entries.insert(
+                    connection_info.clone(),
+                    Entry {
+                        value: value.clone(),
+                        last_used: now,
+                        order,
+                    },
+                );
+                value
+            }
+        };
+        drop(entries);
+        for entry in evicted {
+            entry.value.release();
+        }
+        Ok(value)
+    }
+
+    /// 移除并释放所有项
+    pub(crate) fn clear(&self) {
+        let entries = std::mem::take(&mut *self.entries.lock().unwrap_or_else(|e| e.into_inner()));
+        for (_, entry) in entries {
+            entry.value.release();
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn connection_info(database: &str) -> ConnectionInfo {
+        serde_json::from_value(serde_json::json!({ "database": database })).unwrap()
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = PoolCache::default();
+        cache.set_max_entries(2);
+        let created = Cell::new(0);
+        let get = |database: &str| {
+            cache.get_or_insert_with(&connection_info(database), || {
+                created.set(created.get() + 1);
+                Arc::new(database.to_string())
+            })
+        };
+        let a = get("a");
+        get("b");
+        get("a");
+        assert_eq!(created.get(), 2);
+        // 再次使用 `a` 后 `b` 是最久未使用的一项
+        get("c");
+        assert_eq!(cache.len(), 2);
+        get("a");
+        get("c");
+        assert_eq!(created.get(), 3);
+        get("b");
+        assert_eq!(created.get(), 4, "`b` was evicted");
+        // 被淘汰的项在持有者释放前仍可使用
+        assert_eq!(Arc::strong_count(&a), 1, "`a` was evicted");
+        assert_eq!(a.as_str(), "a");
+
+        let err = cache
+            .get_or_try_insert_with(&connection_info("d"), || {
+                Err(crate::error::Error::Connector("refused".to_string()))
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("refused"));
+        assert_eq!(cache.len(), 2, "failed creations are not cached");
+
+        cache.clear();
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn test_evicts_idle_entries() {
+        let mut cache = PoolCache::default();
+        cache.set_idle_timeout(Duration::from_millis(20));
+        let a = cache.get_or_insert_with(&connection_info("a"), || Arc::new(1));
+        std::thread::sleep(Duration::from_millis(40));
+        cache.get_or_insert_with(&connection_info("b"), || Arc::new(2));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(Arc::strong_count(&a), 1);
+        let recreated = cache.get_or_insert_with(&connection_info("a"), || Arc::new(3));
+        assert_eq!(*recreated, 3);
+    }
+}
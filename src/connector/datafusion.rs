@@ -0,0 +1,672 @@
+//! DataFusion 连接器（`datafusion` feature）
+//!
+//! 在进程内使用 DataFusion 执行查询，不依赖外部数据库。`files` 中的文件在首次查询时
+//! 注册为表，model 的 `table_reference` 指向表名即可查询：
+//!
+//! - 按扩展名选择格式：`.parquet`、`.csv` / `.tsv`、`.json` / `.ndjson` / `.jsonl`（每行
+//!   一个 JSON 对象）、`.arrow` / `.feather` / `.ipc`（Arrow IPC 文件格式）
+//! - 路径可以是本地文件、目录下的通配符（`data/*.parquet`），或 `s3://`、`gs://`、
+//!   `az://` / `abfss://`、`http(s)://` 等对象存储地址
+//! - 表名为 `table`、`schema.table` 或 `catalog.schema.table`，区分大小写
+//!
+//! 文件位置须经服务端配置放行（见 [`FileAccess`]），对象存储只有匹配配置的地址前缀时
+//! 才使用环境变量中的凭证，否则匿名访问。
+//!
+//! `schema` 为未限定的表名所在的默认 schema。会话按 `ConnectionInfo` 缓存，数量和空闲
+//! 时间有上限；查询只允许只读语句，结果以 Arrow `RecordBatch` 逐批转换为 JSON 行，丢弃结果流即停止执行。
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use datafusion::catalog::{CatalogProvider, MemoryCatalogProvider, MemorySchemaProvider};
+use datafusion::common::TableReference;
+use datafusion::datasource::file_format::options::{ArrowReadOptions, ReadOptions};
+use datafusion::datasource::listing::ListingTableUrl;
+use datafusion::error::DataFusionError;
+use datafusion::prelude::{
+    CsvReadOptions, NdJsonReadOptions, ParquetReadOptions, SQLOptions, SessionConfig,
+    SessionContext,
+};
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use object_store::ObjectStore;
+use tokio::sync::OnceCell;
+use tokio_util::sync::CancellationToken;
+
+use crate::connector::cache::PoolCache;
+use crate::connector::file_access::{FileAccess, Location};
+use crate::connector::registry::{Capabilities, ConnectorContext, ConnectorFactory};
+use crate::connector::trait_::{Connector, QueryStream};
+use crate::error::{Error, Result};
+use crate::format::arrow::{batch_rows, type_name};
+use crate::model::{ColumnInfo, ConnectionInfo, QueryResponse};
+
+/// 未指定 catalog 时使用的 DataFusion 默认 catalog
+const DEFAULT_CATALOG: &str = "datafusion";
+
+/// 未指定 `schema` 时使用的 DataFusion 默认 schema
+const DEFAULT_SCHEMA: &str = "public";
+
+/// 按 `ConnectionInfo` 缓存的 DataFusion 会话，相同连接信息的请求共享已注册的表
+#[derive(Default)]
+pub struct DataFusionSessions {
+    sessions: PoolCache<Arc<Session>>,
+    file_access: Arc<FileAccess>,
+}
+
+impl DataFusionSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置允许注册的文件位置，默认不允许任何文件
+    pub fn with_file_access(mut self, file_access: FileAccess) -> Self {
+        self.file_access = Arc::new(file_access);
+        self
+    }
+
+    /// 最多缓存的会话数量，超出时移除最久未使用的会话
+    pub fn with_max_sessions(mut self, max_sessions: usize) -> Self {
+        self.sessions.set_max_entries(max_sessions);
+        self
+    }
+
+    /// 会话未使用超过该时间后移除
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.sessions.set_idle_timeout(timeout);
+        self
+    }
+
+    /// 获取连接信息对应的会话，不存在时创建；文件在首次查询时才注册
+    pub fn session(&self, connection_info: &ConnectionInfo) -> Arc<Session> {
+        self.sessions.get_or_insert_with(connection_info, || {
+            Arc::new(Session::new(
+                connection_info.clone(),
+                Arc::clone(&self.file_access),
+            ))
+        })
+    }
+
+    /// 创建使用缓存会话的连接器
+    pub fn connector(&self, connection_info: &ConnectionInfo) -> Result<DataFusionConnector> {
+        Ok(DataFusionConnector::from_session(
+            self.session(connection_info),
+        ))
+    }
+
+    /// 移除所有会话，正在执行的查询结束后释放
+    pub fn close(&self) {
+        self.sessions.clear();
+    }
+
+    /// 当前缓存的会话数量
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ConnectorFactory for DataFusionSessions {
+    fn connector(&self, context: &ConnectorContext<'_>) -> Result<Box<dyn Connector>> {
+        Ok(Box::new(DataFusionSessions::connector(
+            self,
+            context.connection_info,
+        )?))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            streaming: true,
+            cancellation: true,
+            embedded: true,
+            files: true,
+            ..Capabilities::default()
+        }
+    }
+
+    fn close(&self) {
+        DataFusionSessions::close(self)
+    }
+}
+
+/// 延迟初始化的 DataFusion 会话
+pub struct Session {
+    connection_info: ConnectionInfo,
+    file_access: Arc<FileAccess>,
+    /// 注册完文件的会话；注册失败时保持为空，下次查询重试
+    context: OnceCell<SessionContext>,
+}
+
+impl Session {
+    fn new(connection_info: ConnectionInfo, file_access: Arc<FileAccess>) -> Self {
+        Self {
+            connection_info,
+            file_access,
+            context: OnceCell::new(),
+        }
+    }
+
+    /// 获取会话上下文，首次调用时创建并注册文件
+    async fn context(&self) -> Result<&SessionContext> {
+        self.context
+            .get_or_try_init(|| open(&self.connection_info, &self.file_access))
+            .await
+    }
+}
+
+async fn open(
+    connection_info: &ConnectionInfo,
+    file_access: &FileAccess,
+) -> Result<SessionContext> {
+    let schema = connection_info.schema.as_deref().unwrap_or(DEFAULT_SCHEMA);
+    let config = SessionConfig::new().with_default_catalog_and_schema(DEFAULT_CATALOG, schema);
+    let context = SessionContext::new_with_config(config);
+    register_files(&context, &connection_info.files, file_access).await?;
+    Ok(context)
+}
+
+/// 把文件注册为表，按扩展名选择格式，对象存储地址先注册对应的存储
+///
+/// 所有位置先经 `file_access` 校验，本地文件使用规范化后的路径
+async fn register_files(
+    context: &SessionContext,
+    files: &BTreeMap<String, String>,
+    file_access: &FileAccess,
+) -> Result<()> {
+    for (name, path) in files {
+        let format = file_format(path)?;
+        let (path, credentials) = match file_access.location(path)? {
+            Location::Local(path) => (path.to_string_lossy().into_owned(), false),
+            Location::Remote { url, credentials } => (url, credentials),
+        };
+        let path = path.as_str();
+        let table = table_reference(context, name)?;
+        let url = ListingTableUrl::parse(path)
+            .map_err(|e| Error::Validation(format!("invalid file path `{path}`: {e}")))?;
+        if let Some(store) = object_store(&url, credentials)? {
+            context.register_object_store(url.object_store().as_ref(), store);
+        }
+        let extension = path
+            .rfind('.')
+            .map(|index| &path[index..])
+            .filter(|extension| !extension.contains('/'))
+            .unwrap_or_default();
+        let config = context.copied_config();
+        let table_options = context.copied_table_options();
+        let options = match format {
+            FileFormat::Parquet => ParquetReadOptions::default()
+                .file_extension(extension)
+                .to_listing_options(&config, table_options),
+            FileFormat::Csv { delimiter } => CsvReadOptions::new()
+                .delimiter(delimiter)
+                .file_extension(extension)
+                .to_listing_options(&config, table_options),
+            FileFormat::Json => NdJsonReadOptions::default()
+                .file_extension(extension)
+                .to_listing_options(&config, table_options),
+            FileFormat::Arrow => ArrowReadOptions {
+                file_extension: extension,
+                ..Default::default()
+            }
+            .to_listing_options(&config, table_options),
+        };
+        context
+            .register_listing_table(table, path, options, None, None)
+            .await
+            .map_err(|e| {
+                Error::Connector(format!("failed to register `{path}` as `{name}`: {e}"))
+            })?;
+    }
+    Ok(())
+}
+
+/// 文件表名对应的表引用，不存在的 catalog 和 schema 会被创建
+///
+/// 表名按原样注册，不做大小写转换，与规划器生成的带引号标识符一致
+fn table_reference(context: &SessionContext, name: &str) -> Result<TableReference> {
+    let parts = name.split('.').collect::<Vec<_>>();
+    let (catalog, schema, table) = match parts.as_slice() {
+        [table] => return Ok(TableReference::bare(*table)),
+        [schema, table] => (None, *schema, *table),
+        [catalog, schema, table] => (Some(*catalog), *schema, *table),
+        _ => {
+            return Err(Error::Validation(format!(
+                "file table `{name}` must be `table`, `schema.table` or `catalog.schema.table`"
+            )))
+        }
+    };
+    let catalog_name = catalog.unwrap_or(DEFAULT_CATALOG);
+    let provider = match context.catalog(catalog_name) {
+        Some(provider) => provider,
+        None => {
+            let provider: Arc<dyn CatalogProvider> = Arc::new(MemoryCatalogProvider::new());
+            context.register_catalog(catalog_name, Arc::clone(&provider));
+            provider
+        }
+    };
+    if provider.schema(schema).is_none() {
+        provider
+            .register_schema(schema, Arc::new(MemorySchemaProvider::new()))
+            .map_err(|e| Error::Connector(format!("failed to create schema `{schema}`: {e}")))?;
+    }
+    Ok(match catalog {
+        Some(catalog) => TableReference::full(catalog, schema, table),
+        None => TableReference::partial(schema, table),
+    })
+}
+
+/// 对象存储地址对应的存储，本地文件返回 `None`
+///
+/// `credentials` 为假时不读取环境变量中的凭证，以匿名方式访问；GCS 不支持匿名访问
+fn object_store(url: &ListingTableUrl, credentials: bool) -> Result<Option<Arc<dyn ObjectStore>>> {
+    use object_store::aws::AmazonS3Builder;
+    use object_store::azure::MicrosoftAzureBuilder;
+    use object_store::gcp::GoogleCloudStorageBuilder;
+
+    let store_url = url.object_store();
+    let store_url = store_url.as_str();
+    let store: Arc<dyn ObjectStore> = match url.scheme() {
+        "file" => return Ok(None),
+        "s3" | "s3a" => {
+            let builder = if credentials {
+                AmazonS3Builder::from_env()
+            } else {
+                AmazonS3Builder::new().with_skip_signature(true)
+            };
+            Arc::new(builder.with_url(store_url).build().map_err(store_error)?)
+        }
+        "gs" if credentials => Arc::new(
+            GoogleCloudStorageBuilder::from_env()
+                .with_url(store_url)
+                .build()
+                .map_err(store_error)?,
+        ),
+        "gs" => {
+            return Err(Error::Validation(format!(
+                "`{store_url}` requires credentials: add it to `files.allowed_urls`"
+            )))
+        }
+        "az" | "adl" | "azure" | "abfs" | "abfss" => {
+            let builder = if credentials {
+                MicrosoftAzureBuilder::from_env()
+            } else {
+                MicrosoftAzureBuilder::new().with_skip_signature(true)
+            };
+            Arc::new(builder.with_url(store_url).build().map_err(store_error)?)
+        }
+        "http" | "https" => Arc::new(
+            object_store::http::HttpBuilder::new()
+                .with_url(store_url)
+                .build()
+                .map_err(store_error)?,
+        ),
+        scheme => {
+            return Err(Error::Validation(format!(
+                "unsupported object store scheme `{scheme}`"
+            )))
+        }
+    };
+    Ok(Some(store))
+}
+
+fn store_error(err: object_store::Error) -> Error {
+    Error::Connector(format!("failed to create object store: {err}"))
+}
+
+/// 注册文件支持的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileFormat {
+    Parquet,
+    Csv { delimiter: u8 },
+    Json,
+    Arrow,
+}
+
+fn file_format(path: &str) -> Result<FileFormat> {
+    let lower = path.to_ascii_lowercase();
+    let extension = lower
+        .rsplit_once('.')
+        .map_or("", |(_, extension)| extension);
+    match extension {
+        "parquet" => Ok(FileFormat::Parquet),
+        "csv" => Ok(FileFormat::Csv { delimiter: b',' }),
+        "tsv" => Ok(FileFormat::Csv { delimiter: b'\t' }),
+        "json" | "ndjson" | "jsonl" => Ok(FileFormat::Json),
+        "arrow" | "feather" | "ipc" => Ok(FileFormat::Arrow),
+        _ => Err(Error::Validation(format!(
+            "unsupported file `{path}`: expected a .parquet, .csv, .tsv, .json or .arrow file"
+        ))),
+    }
+}
+
+/// DataFusion 连接器
+pub struct DataFusionConnector {
+    session: Arc<Session>,
+}
+
+impl DataFusionConnector {
+    /// 创建使用独立会话的 DataFusion 连接器
+    ///
+    /// 不允许注册文件，需要读取文件时通过 [`DataFusionSessions::with_file_access`] 创建
+    pub fn new(connection_info: ConnectionInfo) -> Self {
+        Self::from_session(Arc::new(Session::new(connection_info, Arc::default())))
+    }
+
+    /// 使用已缓存的会话创建连接器
+    pub fn from_session(session: Arc<Session>) -> Self {
+        Self { session }
+    }
+}
+
+#[async_trait::async_trait]
+impl Connector for DataFusionConnector {
+    async fn query(&self, sql: &str) -> Result<QueryResponse> {
+        self.query_with_cancel(sql, CancellationToken::new()).await
+    }
+
+    async fn query_with_cancel(
+        &self,
+        sql: &str,
+        cancel: CancellationToken,
+    ) -> Result<QueryResponse> {
+        let QueryStream { columns, batches } = self.query_stream(sql, cancel).await?;
+        let data = batches.try_concat().await?;
+        Ok(QueryResponse {
+            data,
+            columns,
+            truncated: false,
+        })
+    }
+
+    async fn query_stream(&self, sql: &str, cancel: CancellationToken) -> Result<QueryStream> {
+        let start = async {
+            let context = self.session.context().await?;
+            // 只允许查询，拒绝 CREATE EXTERNAL TABLE、COPY、SET 等语句
+            let options = SQLOptions::new()
+                .with_allow_ddl(false)
+                .with_allow_dml(false)
+                .with_allow_statements(false);
+            let frame = context
+                .sql_with_options(sql, options)
+                .await
+                .map_err(database_error)?;
+            frame.execute_stream().await.map_err(database_error)
+        };
+        let batches = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                return Err(Error::Cancelled("query was cancelled".to_string()));
+            }
+            batches = start => batches?,
+        };
+        let columns = batches
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| ColumnInfo {
+                name: field.name().clone(),
+                data_type: type_name(field.data_type()),
+            })
+            .collect();
+
+        // 结果流被丢弃时 DataFusion 停止执行
+        let batches = stream::unfold(Some((batches, cancel)), |state| async move {
+            let (mut batches, cancel) = state?;
+            loop {
+                let batch = tokio::select! {
+                    batch = batches.next() => batch?,
+                    _ = cancel.cancelled() => {
+                        return Some((Err(Error::Cancelled("query was cancelled".to_string())), None));
+                    }
+                };
+                let rows = match batch {
+                    Ok(batch) if batch.num_rows() == 0 => continue,
+                    Ok(batch) => batch_rows(&batch),
+                    Err(e) => Err(database_error(e)),
+                };
+                let state = rows.is_ok().then_some((batches, cancel));
+                return Some((rows, state));
+            }
+        });
+        Ok(QueryStream {
+            columns,
+            batches: batches.boxed(),
+        })
+    }
+
+    fn name(&self) -> &str {
+        "datafusion"
+    }
+}
+
+/// DataFusion 错误转换为 `Error::Database`
+///
+/// DataFusion 不返回 SQLSTATE，按错误类型映射到 SQLSTATE 的类别：解析、规划和 schema
+/// 错误为 `42000`，除零、类型转换和溢出错误为 `22000`
+pub(crate) fn database_error(err: DataFusionError) -> Error {
+    use datafusion::arrow::error::ArrowError;
+
+    let sqlstate = match err.find_root() {
+        DataFusionError::SQL(..) | DataFusionError::Plan(_) | DataFusionError::SchemaError(..) => {
+            Some("42000")
+        }
+        DataFusionError::ArrowError(
+            ArrowError::DivideByZero | ArrowError::CastError(_) | ArrowError::ArithmeticOverflow(_),
+            _,
+        ) => Some("22000"),
+        _ => None,
+    };
+    Error::Database {
+        message: err.to_string(),
+        sqlstate: sqlstate.map(str::to_string),
+        position: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{Int64Array, RecordBatch, StringArray};
+    use arrow_schema::{DataType, Field, Schema};
+    use serde_json::json;
+
+    fn connection_info() -> ConnectionInfo {
+        ConnectionInfo {
+            host: String::new(),
+            port: 0,
+            database: String::new(),
+            user: String::new(),
+            password: String::new(),
+            schema: None,
+            ssl_mode: Default::default(),
+            ssl_ca: None,
+            files: BTreeMap::new(),
+            options: BTreeMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_types() {
+        let connector = DataFusionConnector::new(connection_info());
+        let response = connector
+            .query(
+                "SELECT CAST(1 AS BIGINT UNSIGNED) AS u, CAST(12.5 AS DECIMAL(10,2)) AS d, \
+                 DATE '2024-01-02' AS day, TIMESTAMP '2024-01-02 03:04:05' AS ts, \
+                 [1, NULL] AS list, CAST(NULL AS VARCHAR) AS n",
+            )
+            .await
+            .unwrap();
+        let types = response
+            .columns
+            .iter()
+            .map(|c| c.data_type.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                "bigint unsigned",
+                "decimal(10,2)",
+                "date",
+                "timestamp",
+                "bigint[]",
+                "varchar"
+            ]
+        );
+        assert_eq!(
+            response.data,
+            vec![json!([
+                1,
+                "12.50",
+                "2024-01-02",
+                "2024-01-02T03:04:05",
+                [1, null],
+                null
+            ])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_files_registered_as_tables() {
+        let dir = std::env::temp_dir().join(format!("mimir-datafusion-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let csv = dir.join("orders.csv");
+        std::fs::write(&csv, "id,amount\n1,10.5\n2,20\n").unwrap();
+        let json = dir.join("regions.ndjson");
+        std::fs::write(&json, "{\"id\": 1, \"region\": \"north\"}\n").unwrap();
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec!["alice", "bob"])),
+            ],
+        )
+        .unwrap();
+        let parquet = dir.join("customers.parquet");
+        let mut writer = parquet::arrow::ArrowWriter::try_new(
+            std::fs::File::create(&parquet).unwrap(),
+            Arc::clone(&schema),
+            None,
+        )
+        .unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+        let arrow = dir.join("customers.arrow");
+        let mut writer =
+            arrow_ipc::writer::FileWriter::try_new(std::fs::File::create(&arrow).unwrap(), &schema)
+                .unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+
+        let path = |path: &std::path::Path| path.to_string_lossy().into_owned();
+        let mut info = connection_info();
+        info.files.insert("Orders".to_string(), path(&csv));
+        info.files.insert("regions".to_string(), path(&json));
+        info.files
+            .insert("sales.customers".to_string(), path(&parquet));
+        info.files
+            .insert("main.sales.customers_ipc".to_string(), path(&arrow));
+        info.files
+            .insert("all_orders".to_string(), path(&dir.join("*.csv")));
+        let sessions =
+            DataFusionSessions::new().with_file_access(FileAccess::new().with_root(&dir));
+        let connector = sessions.connector(&info).unwrap();
+        let response = connector
+            .query(
+                "SELECT c.name, r.region, SUM(o.amount) AS total FROM \"Orders\" o \
+                 JOIN \"sales\".\"customers\" c ON c.id = o.id \
+                 JOIN \"regions\" r ON r.id = o.id GROUP BY c.name, r.region",
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.data, vec![json!(["alice", "north", 10.5])]);
+        let response = connector
+            .query(
+                "SELECT (SELECT COUNT(*) FROM all_orders) AS orders, \
+                 (SELECT COUNT(*) FROM \"main\".\"sales\".\"customers_ipc\") AS customers",
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.data, vec![json!([2, 2])]);
+        // 同一连接信息复用已注册文件的会话
+        sessions.connector(&info).unwrap();
+        assert_eq!(sessions.len(), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let connector = DataFusionConnector::new(connection_info());
+        let err = connector.query("SELECT * FROM missing").await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Database { sqlstate: Some(ref s), .. } if s == "42000"
+        ));
+        let err = connector
+            .query("CREATE EXTERNAL TABLE t STORED AS CSV LOCATION '/etc/hostname'")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Database { .. }), "{err}");
+
+        let mut info = connection_info();
+        info.files
+            .insert("t".to_string(), "/data/events.xlsx".to_string());
+        let err = DataFusionConnector::new(info)
+            .query("SELECT 1")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Validation(_)));
+
+        // 未经配置放行的文件和地址都被拒绝
+        let sessions = DataFusionSessions::new().with_file_access(
+            FileAccess::new()
+                .with_root("/srv/data")
+                .with_scheme("https"),
+        );
+        for path in [
+            "/etc/hosts.csv",
+            "file:///etc/hosts.csv",
+            "s3://bucket/orders.parquet",
+            "http://169.254.169.254/latest.csv",
+            "gs://bucket/orders.parquet",
+        ] {
+            let mut info = connection_info();
+            info.files.insert("t".to_string(), path.to_string());
+            let err = sessions
+                .connector(&info)
+                .unwrap()
+                .query("SELECT 1")
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::Validation(_)), "{path}: {err}");
+        }
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let err = DataFusionConnector::new(connection_info())
+            .query_with_cancel("SELECT 1", cancel)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Cancelled(_)));
+    }
+
+    #[test]
+    fn test_file_format() {
+        assert_eq!(file_format("data/*.PARQUET").unwrap(), FileFormat::Parquet);
+        assert_eq!(
+            file_format("s3://bucket/orders.tsv").unwrap(),
+            FileFormat::Csv { delimiter: b'\t' }
+        );
+        assert_eq!(file_format("events.jsonl").unwrap(), FileFormat::Json);
+        assert_eq!(file_format("orders.feather").unwrap(), FileFormat::Arrow);
+        assert!(file_format("orders.xlsx").is_err());
+    }
+}
@@ -0,0 +1,638 @@
+//! DuckDB 连接器（`duckdb` feature）
+//!
+//! 在进程内打开 DuckDB 数据库：`database` 为文件路径，为空或 `:memory:` 时使用内存
+//! 数据库。`files` 中的 Parquet / CSV 文件在数据库首次使用时注册为视图，model 的
+//! `table_reference` 指向视图名即可直接查询本地文件。
+//!
+//! 数据库文件和 `files` 中的路径须位于服务端配置放行的目录下（见 [`FileAccess`]），
+//! 校验通过后才打开数据库，使用规范化后的路径。注册文件后数据库关闭外部访问，查询
+//! 只能读取已注册的文件和数据库本身。
+//!
+//! 打开的数据库按 `ConnectionInfo` 缓存，超出数量上限或空闲过久的数据库在正在执行的
+//! 查询结束后关闭。每个查询在阻塞线程池中使用独立的连接执行，
+//! 结果以 Arrow `RecordBatch` 逐批转换为 JSON 行。DuckDB 在产出第一批结果前完成
+//! 执行，取消在批次之间生效。
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use duckdb::Connection;
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+
+use crate::connector::cache::PoolCache;
+use crate::connector::file_access::FileAccess;
+use crate::connector::registry::{Capabilities, ConnectorContext, ConnectorFactory};
+use crate::connector::trait_::{Connector, QueryStream};
+use crate::error::{Error, Result};
+use crate::format::arrow::{batch_rows, type_name};
+use crate::model::{ColumnInfo, ConnectionInfo, QueryResponse};
+
+/// 内存数据库的路径
+pub const IN_MEMORY: &str = ":memory:";
+
+/// 按 `ConnectionInfo` 缓存的 DuckDB 数据库，相同连接信息的请求共享同一个数据库
+#[derive(Default)]
+pub struct DuckDbDatabases {
+    databases: PoolCache<Arc<Database>>,
+    file_access: Arc<FileAccess>,
+}
+
+impl DuckDbDatabases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置允许打开的数据库文件和注册的文件所在目录，默认不允许任何文件
+    pub fn with_file_access(mut self, file_access: FileAccess) -> Self {
+        self.file_access = Arc::new(file_access);
+        self
+    }
+
+    /// 最多缓存的数据库数量，超出时移除最久未使用的数据库
+    pub fn with_max_databases(mut self, max_databases: usize) -> Self {
+        self.databases.set_max_entries(max_databases);
+        self
+    }
+
+    /// 数据库未使用超过该时间后移除
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.databases.set_idle_timeout(timeout);
+        self
+    }
+
+    /// 获取连接信息对应的数据库，不存在时创建；数据库在首次查询时才打开
+    pub fn database(&self, connection_info: &ConnectionInfo) -> Arc<Database> {
+        self.databases.get_or_insert_with(connection_info, || {
+            Arc::new(Database::new(
+                connection_info.clone(),
+                Arc::clone(&self.file_access),
+            ))
+        })
+    }
+
+    /// 创建使用缓存数据库的连接器
+    pub fn connector(&self, connection_info: &ConnectionInfo) -> Result<DuckDbConnector> {
+        Ok(DuckDbConnector::from_database(
+            self.database(connection_info),
+        ))
+    }
+
+    /// 移除所有数据库，正在执行的查询结束后关闭
+    pub fn close(&self) {
+        self.databases.clear();
+    }
+
+    /// 当前缓存的数据库数量
+    pub fn len(&self) -> usize {
+        self.databases.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ConnectorFactory for DuckDbDatabases {
+    fn connector(&self, context: &ConnectorContext<'_>) -> Result<Box<dyn Connector>> {
+        Ok(Box::new(DuckDbDatabases::connector(
+            self,
+            context.connection_info,
+        )?))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            streaming: true,
+            embedded: true,
+            files: true,
+            ..Capabilities::default()
+        }
+    }
+
+    fn close(&self) {
+        DuckDbDatabases::close(self)
+    }
+}
+
+/// 延迟打开的 DuckDB 数据库
+pub struct Database {
+    connection_info: ConnectionInfo,
+    file_access: Arc<FileAccess>,
+    /// 打开后保留的连接，查询使用从它克隆的连接；打开失败时保持为空，下次查询重试
+    connection: Mutex<Option<Connection>>,
+}
+
+impl Database {
+    fn new(connection_info: ConnectionInfo, file_access: Arc<FileAccess>) -> Self {
+        Self {
+            connection_info,
+            file_access,
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// 获取一个新连接，数据库未打开时打开并注册文件
+    fn connect(&self) -> Result<Connection> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let root = match connection.as_mut() {
+            Some(root) => root,
+            None => connection.insert(open(&self.connection_info, &self.file_access)?),
+        };
+        let connection = root.try_clone().map_err(database_error)?;
+        // 默认 schema 是会话级设置，每个连接分别设置
+        if let Some(schema) = &self.connection_info.schema {
+            connection
+                .execute_batch(&format!("SET schema = {}", quote_literal(schema)))
+                .map_err(database_error)?;
+        }
+        Ok(connection)
+    }
+}
+
+/// 校验数据库和文件路径后打开数据库；只允许本地文件，路径使用规范化后的结果
+fn open(connection_info: &ConnectionInfo, file_access: &FileAccess) -> Result<Connection> {
+    let database = match connection_info.database.as_str() {
+        "" | IN_MEMORY => None,
+        path => Some(file_access.local_path(path)?),
+    };
+    let files = connection_info
+        .files
+        .iter()
+        .map(|(name, path)| {
+            let path = file_access.local_path(path)?;
+            Ok((name.clone(), path.to_string_lossy().into_owned()))
+        })
+        .collect::<Result<BTreeMap<_, _>>>()?;
+    let connection = match &database {
+        None => Connection::open_in_memory(),
+        Some(path) => Connection::open(path),
+    }
+    .map_err(|e| {
+        Error::Connector(format!(
+            "failed to open DuckDB database `{}`: {e}",
+            connection_info.database
+        ))
+    })?;
+    register_files(&connection, &files)?;
+    restrict_file_access(&connection, &files)?;
+    Ok(connection)
+}
+
+/// 只允许读取已注册的文件，查询中的 `read_csv('/etc/passwd')` 等调用会被拒绝
+///
+/// 关闭外部访问后不能再次开启，设置对数据库的所有连接生效
+fn restrict_file_access(connection: &Connection, files: &BTreeMap<String, String>) -> Result<()> {
+    let mut paths = Vec::new();
+    let mut directories = Vec::new();
+    for path in files.values() {
+        // 通配符路径允许其所在的目录
+        match path.find(['*', '?', '[']) {
+            Some(index) => {
+                let prefix = &path[..index];
+                let directory = &prefix[..prefix.rfind('/').map_or(0, |i| i + 1)];
+                directories.push(quote_literal(directory));
+            }
+            None => paths.push(quote_literal(path)),
+        }
+    }
+    let sql = format!(
+        "SET allowed_paths = [{}];\n\
+         SET allowed_directories = [{}];\n\
+         SET enable_external_access = false;",
+        paths.join(", "),
+        directories.join(", ")
+    );
+    connection
+        .execute_batch(&sql)
+        .map_err(|e| Error::Connector(format!("failed to restrict DuckDB file access: {e}")))
+}
+
+/// 把文件注册为视图，按扩展名选择读取函数；文件数据库中的视图会被持久化
+fn register_files(connection: &Connection, files: &BTreeMap<String, String>) -> Result<()> {
+    for (name, path) in files {
+        let parts = name.split('.').map(quote_ident).collect::<Vec<_>>();
+        if parts.is_empty() || parts.len() > 3 {
+            return Err(Error::Validation(format!(
+                "file table `{name}` must be `table`, `schema.table` or `catalog.schema.table`"
+            )));
+        }
+        let mut sql = String::new();
+        if parts.len() > 1 {
+            let schema = parts[..parts.len() - 1].join(".");
+            sql.push_str(&format!("CREATE SCHEMA IF NOT EXISTS {schema};\n"));
+        }
+        sql.push_str(&format!(
+            "CREATE OR REPLACE VIEW {} AS SELECT * FROM {}({})",
+            parts.join("."),
+            file_reader(path)?,
+            quote_literal(path)
+        ));
+        connection.execute_batch(&sql).map_err(|e| {
+            Error::Connector(format!("failed to register `{path}` as `{name}`: {e}"))
+        })?;
+    }
+    Ok(())
+}
+
+/// 文件对应的 DuckDB 读取函数，压缩的 CSV 按去掉压缩扩展名后的扩展名判断
+fn file_reader(path: &str) -> Result<&'static str> {
+    let lower = path.to_ascii_lowercase();
+    let name = [".gz", ".zst"]
+        .iter()
+        .find_map(|suffix| lower.strip_suffix(suffix))
+        .unwrap_or(&lower);
+    if lower.ends_with(".parquet") {
+        Ok("read_parquet")
+    } else if name.ends_with(".csv") || name.ends_with(".tsv") {
+        Ok("read_csv")
+    } else {
+        Err(Error::Validation(format!(
+            "unsupported file `{path}`: expected a .parquet or .csv file"
+        )))
+    }
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// DuckDB 连接器
+pub struct DuckDbConnector {
+    database: Arc<Database>,
+}
+
+impl DuckDbConnector {
+    /// 创建使用独立数据库的 DuckDB 连接器
+    ///
+    /// 只能使用内存数据库且不允许注册文件，需要读取文件时通过
+    /// [`DuckDbDatabases::with_file_access`] 创建
+    pub fn new(connection_info: ConnectionInfo) -> Self {
+        Self::from_database(Arc::new(Database::new(connection_info, Arc::default())))
+    }
+
+    /// 使用已缓存的数据库创建连接器
+    pub fn from_database(database: Arc<Database>) -> Self {
+        Self { database }
+    }
+}
+
+/// 执行线程和结果流之间缓冲的批次数
+const BATCH_BUFFER: usize = 1;
+
+#[async_trait::async_trait]
+impl Connector for DuckDbConnector {
+    async fn query(&self, sql: &str) -> Result<QueryResponse> {
+        self.query_with_cancel(sql, CancellationToken::new()).await
+    }
+
+    async fn query_with_cancel(
+        &self,
+        sql: &str,
+        cancel: CancellationToken,
+    ) -> Result<QueryResponse> {
+        let QueryStream { columns, batches } = self.query_stream(sql, cancel).await?;
+        let data = batches.try_concat().await?;
+        Ok(QueryResponse {
+            data,
+            columns,
+            truncated: false,
+        })
+    }
+
+    async fn query_stream(&self, sql: &str, cancel: CancellationToken) -> Result<QueryStream> {
+        let (columns_tx, columns_rx) = oneshot::channel();
+        let (batches_tx, batches_rx) = mpsc::channel(BATCH_BUFFER);
+        let database = Arc::clone(&self.database);
+        let sql = sql.to_string();
+        let task_cancel = cancel.clone();
+        tokio::task::spawn_blocking(move || {
+            execute(&database, &sql, columns_tx, &batches_tx, &task_cancel)
+        });
+
+        let columns = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                return Err(Error::Cancelled("query was cancelled".to_string()));
+            }
+            columns = columns_rx => columns.map_err(|_| {
+                Error::Connector("DuckDB query ended without a result".to_string())
+            })??,
+        };
+        // 流被丢弃时接收端关闭，执行线程在下一批时停止
+        let batches = stream::unfold(Some((batches_rx, cancel)), |state| async move {
+            let (mut batches, cancel) = state?;
+            tokio::select! {
+                batch = batches.recv() => {
+                    let batch = batch?;
+                    let state = batch.is_ok().then_some((batches, cancel));
+                    Some((batch, state))
+                }
+                _ = cancel.cancelled() => {
+                    Some((Err(Error::Cancelled("query was cancelled".to_string())), None))
+                }
+            }
+        });
+        Ok(QueryStream {
+            columns,
+            batches: batches.boxed(),
+        })
+    }
+
+    fn name(&self) -> &str {
+        "duckdb"
+    }
+}
+
+/// 在阻塞线程中执行查询，先发送列信息，再逐批发送结果行
+///
+/// 出错时错误发给尚未收到结果的一方：列信息发出前发给 `columns`，之后发给 `batches`
+fn execute(
+    database: &Database,
+    sql: &str,
+    columns: oneshot::Sender<Result<Vec<ColumnInfo>>>,
+    batches: &mpsc::Sender<Result<Vec<Value>>>,
+    cancel: &CancellationToken,
+) {
+    let mut columns = Some(columns);
+    let result = (|| -> Result<()> {
+        let connection = database.connect()?;
+        let mut statement = connection.prepare(sql).map_err(database_error)?;
+        let arrow = statement.query_arrow([]).map_err(database_error)?;
+        let schema = arrow.get_schema();
+        let infos = schema
+            .fields()
+            .iter()
+            .map(|field| ColumnInfo {
+                name: field.name().clone(),
+                data_type: type_name(field.data_type()),
+            })
+            .collect();
+        if let Some(columns) = columns.take() {
+            if columns.send(Ok(infos)).is_err() {
+                return Ok(());
+            }
+        }
+        for batch in arrow {
+            if cancel.is_cancelled() {
+                break;
+            }
+            if batch.num_rows() == 0 {
+                continue;
+            }
+            if batches.blocking_send(Ok(batch_rows(&batch)?)).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        match columns {
+            Some(columns) => {
+                let _ = columns.send(Err(e));
+            }
+            None => {
+                let _ = batches.blocking_send(Err(e));
+            }
+        }
+    }
+}
+
+/// DuckDB 错误转换为 `Error::Database`
+///
+/// DuckDB 不返回 SQLSTATE，按错误类型前缀映射到 SQLSTATE 的类别：解析、绑定和目录
+/// 错误为 `42000`，数据转换和越界错误为 `22000`
+pub(crate) fn database_error(err: duckdb::Error) -> Error {
+    let message = err.to_string();
+    let sqlstate = [
+        ("Parser Error", "42000"),
+        ("Binder Error", "42000"),
+        ("Catalog Error", "42000"),
+        ("Conversion Error", "22000"),
+        ("Out of Range Error", "22000"),
+        ("Invalid Input Error", "22000"),
+    ]
+    .iter()
+    .find(|(prefix, _)| message.starts_with(prefix))
+    .map(|(_, sqlstate)| sqlstate.to_string());
+    Error::Database {
+        message,
+        sqlstate,
+        position: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn connection_info() -> ConnectionInfo {
+        ConnectionInfo {
+            host: String::new(),
+            port: 0,
+            database: IN_MEMORY.to_string(),
+            user: String::new(),
+            password: String::new(),
+            schema: None,
+            ssl_mode: Default::default(),
+            ssl_ca: None,
+            files: BTreeMap::new(),
+            options: BTreeMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_types() {
+        let connector = DuckDbConnector::new(connection_info());
+        let response = connector
+            .query(
+                "SELECT 1::UBIGINT AS u, 12.5::DECIMAL(10,2) AS d, DATE '2024-01-02' AS day, \
+                 TIMESTAMP '2024-01-02 03:04:05' AS ts, TIMESTAMPTZ '2024-01-02 03:04:05+00' AS tz, \
+                 INTERVAL 90 MINUTE AS span, [1, NULL] AS list, {'a': 'x'} AS obj, NULL::VARCHAR AS n",
+            )
+            .await
+            .unwrap();
+        let types = response
+            .columns
+            .iter()
+            .map(|c| c.data_type.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                "bigint unsigned",
+                "decimal(10,2)",
+                "date",
+                "timestamp",
+                "timestamptz",
+                "interval",
+                "integer[]",
+                "struct",
+                "varchar"
+            ]
+        );
+        assert_eq!(
+            response.data,
+            vec![json!([
+                1,
+                "12.50",
+                "2024-01-02",
+                "2024-01-02T03:04:05",
+                "2024-01-02T03:04:05+00:00",
+                "PT1H30M",
+                [1, null],
+                {"a": "x"},
+                null
+            ])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_files_registered_as_tables() {
+        let dir = std::env::temp_dir().join(format!("mimir-duckdb-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let csv = dir.join("orders.csv");
+        std::fs::write(&csv, "id,amount\n1,10.5\n2,20\n").unwrap();
+        let parquet = dir.join("customers.parquet");
+        let setup = Connection::open_in_memory().unwrap();
+        setup
+            .execute_batch(&format!(
+                "COPY (SELECT 1 AS id, 'alice' AS name) TO {} (FORMAT parquet)",
+                quote_literal(&parquet.to_string_lossy())
+            ))
+            .unwrap();
+
+        let mut info = connection_info();
+        info.files
+            .insert("orders".to_string(), csv.to_string_lossy().into_owned());
+        info.files.insert(
+            "sales.customers".to_string(),
+            parquet.to_string_lossy().into_owned(),
+        );
+        info.files.insert(
+            "all_orders".to_string(),
+            dir.join("*.csv").to_string_lossy().into_owned(),
+        );
+        let databases = DuckDbDatabases::new().with_file_access(FileAccess::new().with_root(&dir));
+        let connector = databases.connector(&info).unwrap();
+        let response = connector
+            .query(
+                "SELECT c.name, SUM(o.amount) AS total FROM \"orders\" o \
+                 JOIN \"sales\".\"customers\" c ON c.id = o.id GROUP BY c.name",
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.data, vec![json!(["alice", 10.5])]);
+        let response = connector
+            .query("SELECT COUNT(*) FROM all_orders")
+            .await
+            .unwrap();
+        assert_eq!(response.data, vec![json!([2])]);
+        // 同一连接信息复用已注册文件的数据库
+        databases.connector(&info).unwrap();
+        assert_eq!(databases.len(), 1);
+
+        // 放行目录下的数据库文件可以创建和打开
+        let mut info = connection_info();
+        info.database = dir.join("local.duckdb").to_string_lossy().into_owned();
+        let response = databases
+            .connector(&info)
+            .unwrap()
+            .query("SELECT 1")
+            .await
+            .unwrap();
+        assert_eq!(response.data, vec![json!([1])]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_paths_outside_allowed_roots() {
+        let dir = std::env::temp_dir().join(format!("mimir-duckdb-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let databases = DuckDbDatabases::new().with_file_access(FileAccess::new().with_root(&dir));
+        let outside = std::env::temp_dir().join(format!("mimir-{}.duckdb", uuid::Uuid::new_v4()));
+
+        let mut cases = Vec::new();
+        for database in [
+            outside.to_string_lossy().into_owned(),
+            format!("{}/../escape.duckdb", dir.display()),
+            "/etc/passwd".to_string(),
+        ] {
+            let mut info = connection_info();
+            info.database = database;
+            cases.push(info);
+        }
+        for file in [
+            "/etc/hosts.csv".to_string(),
+            format!("{}/../*.csv", dir.display()),
+            "s3://bucket/orders.parquet".to_string(),
+        ] {
+            let mut info = connection_info();
+            info.files.insert("t".to_string(), file);
+            cases.push(info);
+        }
+        for info in cases {
+            let err = databases
+                .connector(&info)
+                .unwrap()
+                .query("SELECT 1")
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::Validation(_)), "{info:?}: {err}");
+        }
+        // 校验失败时不会创建数据库文件
+        assert!(!outside.exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let connector = DuckDbConnector::new(connection_info());
+        let err = connector.query("SELECT * FROM missing").await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Database { sqlstate: Some(ref s), .. } if s == "42000"
+        ));
+        let err = connector
+            .query("SELECT * FROM read_csv('/etc/hostname')")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Permission Error"), "{err}");
+
+        let mut info = connection_info();
+        info.files
+            .insert("t".to_string(), "/data/events.json".to_string());
+        let err = DuckDbConnector::new(info)
+            .query("SELECT 1")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Validation(_)));
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let err = DuckDbConnector::new(connection_info())
+            .query_with_cancel("SELECT 1", cancel)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Cancelled(_)));
+    }
+
+    #[test]
+    fn test_file_reader() {
+        assert_eq!(file_reader("data/orders.parquet").unwrap(), "read_parquet");
+        assert_eq!(file_reader("data/*.PARQUET").unwrap(), "read_parquet");
+        assert_eq!(file_reader("orders.csv.gz").unwrap(), "read_csv");
+        assert!(file_reader("orders.xlsx").is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! 嵌入式数据源的文件访问控制
+//!
+//! DuckDB、DataFusion 和 SQLite 打开的数据库文件和注册为表的文件都来自请求体，
+//! MySQL 的 `ssl_ca` 证书路径同样来自请求体，它们都只允许访问服务端配置（`files`）放行的位置：
+//!
+//! - 本地路径规范化（解析 `..` 和符号链接）后必须位于 `allowed_roots` 中的某个目录下
+//! - 远程地址必须以 `allowed_urls` 中的某个前缀开头，或使用 `allowed_schemes` 中的协议；
+//!   只有匹配前缀的地址使用进程环境中的凭证，仅匹配协议的地址匿名访问
+//!
+//! 默认不允许任何文件，空的内存数据库不受影响。
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::FilesConfig;
+use crate::error::{Error, Result};
+
+/// 允许访问的文件位置
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileAccess {
+    roots: Vec<PathBuf>,
+    url_prefixes: Vec<String>,
+    schemes: Vec<String>,
+}
+
+/// 校验通过的文件位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    /// 规范化后的本地路径，可以包含通配符
+    Local(PathBuf),
+    /// 远程地址；`credentials` 为真时可以使用进程环境中的凭证
+    Remote { url: String, credentials: bool },
+}
+
+impl FileAccess {
+    /// 不允许访问任何文件
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_config(config: &FilesConfig) -> Self {
+        let access = config
+            .allowed_roots
+            .iter()
+            .fold(Self::new(), |access, root| access.with_root(root));
+        let access = config
+            .allowed_urls
+            .iter()
+            .fold(access, |access, prefix| access.with_url_prefix(prefix));
+        config
+            .allowed_schemes
+            .iter()
+            .fold(access, |access, scheme| access.with_scheme(scheme))
+    }
+
+    /// 允许访问目录下的文件；目录存在时按规范化后的路径比较
+    pub fn with_root(mut self, root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref();
+        self.roots
+            .push(root.canonicalize().unwrap_or_else(|_| root.to_path_buf()));
+        self
+    }
+
+    /// 允许访问以该前缀开头的远程地址，并使用进程环境中的凭证
+    pub fn with_url_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.url_prefixes.push(prefix.into());
+        self
+    }
+
+    /// 允许匿名访问该协议的任意远程地址
+    pub fn with_scheme(mut self, scheme: impl Into<String>) -> Self {
+        self.schemes.push(scheme.into().to_ascii_lowercase());
+        self
+    }
+
+    /// 校验本地路径或远程地址
+    ///
+    /// `file://` 地址可能经百分号编码绕过路径检查，本地文件必须使用普通路径
+    pub fn location(&self, location: &str) -> Result<Location> {
+        let Some((scheme, _)) = split_scheme(location) else {
+            return self.local_path(location).map(Location::Local);
+        };
+        let scheme = scheme.to_ascii_lowercase();
+        if scheme == "file" {
+            return Err(Error::Validation(format!(
+                "`{location}` is not allowed: use a plain path for local files"
+            )));
+        }
+        let has_traversal = location.contains('\\')
+            || location.to_ascii_lowercase().contains("%2e")
+            || location
+                .split(['/', '?', '#'])
+                .any(|segment| segment == ".." || segment == ".");
+        if !has_traversal {
+            if self
+                .url_prefixes
+                .iter()
+                .any(|prefix| has_prefix(location, prefix))
+            {
+                return Ok(Location::Remote {
+                    url: location.to_string(),
+                    credentials: true,
+                });
+            }
+            if self.schemes.contains(&scheme) {
+                return Ok(Location::Remote {
+                    url: location.to_string(),
+                    credentials: false,
+                });
+            }
+        }
+        Err(Error::Validation(format!(
+            "`{location}` is not in an allowed location"
+        )))
+    }
+
+    /// 校验本地路径，返回规范化后的路径
+    ///
+    /// 通配符之前的目录必须存在；其他路径不存在时按所在目录规范化，供创建数据库文件使用
+    pub fn local_path(&self, path: &str) -> Result<PathBuf> {
+        let denied = || Error::Validation(format!("`{path}` is not in an allowed location"));
+        if split_scheme(path).is_some() {
+            return Err(denied());
+        }
+        let (base, pattern) = match path.find(['*', '?', '[']) {
+            Some(index) => match path[..index].rfind('/') {
+                Some(slash) => (&path[..slash], &path[slash + 1..]),
+                None => (".", path),
+            },
+            None => (path, ""),
+        };
+        if Path::new(pattern)
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return Err(denied());
+        }
+        let base = Path::new(if base.is_empty() { "/" } else { base });
+        let resolved = match base.canonicalize() {
+            Ok(resolved) => resolved,
+            Err(_) if pattern.is_empty() => {
+                let (Some(parent), Some(name)) = (base.parent(), base.file_name()) else {
+                    return Err(denied());
+                };
+                let parent = if parent.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    parent
+                };
+                parent.canonicalize().map_err(|_| denied())?.join(name)
+            }
+            Err(_) => return Err(denied()),
+        };
+        if !self.roots.iter().any(|root| resolved.starts_with(root)) {
+            return Err(denied());
+        }
+        Ok(if pattern.is_empty() {
+            resolved
+        } else {
+            resolved.join(pattern)
+        })
+    }
+}
+
+/// `scheme://rest` 中的协议，Windows 盘符等不算协议
+fn split_scheme(location: &str) -> Option<(&str, &str)> {
+    let (scheme, rest) = location.split_once("://")?;
+    let valid = scheme.len() > 1
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then_some((scheme, rest))
+}
+
+/// 前缀必须在路径分隔处结束，`s3://bucket/data` 不匹配 `s3://bucket/database`
+fn has_prefix(url: &str, prefix: &str) -> bool {
+    match url.strip_prefix(prefix) {
+        Some(rest) => prefix.ends_with('/') || rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_paths_confined_to_roots() {
+        let root = std::env::temp_dir().join(format!("mimir-files-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("data")).unwrap();
+        std::fs::write(root.join("data/orders.csv"), "id\n").unwrap();
+        let access = FileAccess::new().with_root(&root);
+        let root = root.canonicalize().unwrap();
+        let path = |p: &str| format!("{}/{p}", root.display());
+
+        assert_eq!(
+            access.local_path(&path("data/orders.csv")).unwrap(),
+            root.join("data/orders.csv")
+        );
+        assert_eq!(
+            access.local_path(&path("data/*.csv")).unwrap(),
+            root.join("data/*.csv")
+        );
+        // 不存在的文件按所在目录校验
+        assert_eq!(
+            access.local_path(&path("new.duckdb")).unwrap(),
+            root.join("new.duckdb")
+        );
+        for denied in [
+            "/etc/passwd".to_string(),
+            path("../outside.csv"),
+            path("data/../../*.csv"),
+            path("data/*/../../../etc/*"),
+            format!("file://{}", path("data/orders.csv")),
+        ] {
+            assert!(access.local_path(&denied).is_err(), "{denied}");
+            assert!(access.location(&denied).is_err(), "{denied}");
+        }
+        assert!(FileAccess::new()
+            .local_path(&path("data/orders.csv"))
+            .is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_remote_locations() {
+        let access = FileAccess::new()
+            .with_url_prefix("s3://lake/data")
+            .with_scheme("HTTPS");
+        assert_eq!(
+            access.location("s3://lake/data/orders.parquet").unwrap(),
+            Location::Remote {
+                url: "s3://lake/data/orders.parquet".to_string(),
+                credentials: true
+            }
+        );
+        assert_eq!(
+            access.location("https://example.com/a.csv").unwrap(),
+            Location::Remote {
+                url: "https://example.com/a.csv".to_string(),
+                credentials: false
+            }
+        );
+        for denied in [
+            "s3://lake/database/orders.parquet",
+            "s3://other/data/orders.parquet",
+            "s3://lake/data/../secret/orders.parquet",
+            "gs://lake/data/orders.parquet",
+            "http://169.254.169.254/latest/meta-data",
+            "https://example.com/%2e%2e/a.csv",
+        ] {
+            assert!(access.location(denied).is_err(), "{denied}");
+        }
+    }
+}
@@ -1,7 +1,32 @@
 //! 连接器层 - 数据库连接和执行
 
+pub mod cache;
+#[cfg(feature = "datafusion")]
+pub mod datafusion;
+#[cfg(feature = "duckdb")]
+pub mod duckdb;
+pub mod file_access;
+#[cfg(feature = "mysql")]
+pub mod mysql;
 pub mod postgres;
+pub mod registry;
+pub mod running;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 pub mod trait_;
 
-pub use postgres::PostgresConnector;
-pub use trait_::Connector;
+#[cfg(feature = "datafusion")]
+pub use datafusion::{DataFusionConnector, DataFusionSessions};
+#[cfg(feature = "duckdb")]
+pub use duckdb::{DuckDbConnector, DuckDbDatabases};
+pub use file_access::FileAccess;
+#[cfg(feature = "mysql")]
+pub use mysql::{MySqlConnector, MySqlPools};
+pub use postgres::{PostgresConnector, PostgresPools};
+pub use registry::{
+    Capabilities, ConnectorContext, ConnectorEntry, ConnectorFactory, ConnectorRegistry,
+};
+pub use running::{RunningQueries, RunningQuery};
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteConnector, SqliteFactory};
+pub use trait_::{Connector, QueryStream, RowBatchStream};
@@ -0,0 +1,798 @@
+//! MySQL / MariaDB 连接器（`mysql` feature）
+//!
+//! 连接池按 `ConnectionInfo` 缓存复用，超出数量上限或空闲过久的连接池被关闭；查询通过二进制协议执行，结果按列的 MySQL
+//! 类型转换为 JSON，每行输出为与 `columns` 顺序一致的数组。会话时区固定为 UTC：
+//! TIMESTAMP 列表示时间点，类型名为 `timestamptz`，值为 RFC 3339 时间；DATETIME
+//! 列不带时区，类型名为 `datetime`，值的格式与 Postgres 的 timestamp 相同。
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use mysql_async::consts::{ColumnFlags, ColumnType};
+use mysql_async::prelude::Queryable;
+use mysql_async::{
+    Column, Conn, Opts, OptsBuilder, Pool, PoolConstraints, PoolOpts, Row, SslOpts,
+    Value as MySqlValue,
+};
+use serde_json::{Number, Value};
+use tokio_util::sync::CancellationToken;
+
+use crate::connector::cache::{PoolCache, Release};
+use crate::connector::file_access::FileAccess;
+use crate::connector::registry::{Capabilities, ConnectorContext, ConnectorFactory};
+use crate::connector::trait_::Connector;
+use crate::error::{Error, Result};
+use crate::model::{ColumnInfo, ConnectionInfo, QueryResponse, SslMode};
+
+type BoxError = Box<dyn std::error::Error + Sync + Send>;
+
+/// 每个连接池默认的最大连接数
+pub const DEFAULT_POOL_SIZE: usize = 16;
+
+/// 按 `ConnectionInfo` 缓存的连接池，相同连接信息的请求共享同一个池
+pub struct MySqlPools {
+    pools: PoolCache<Pool>,
+    max_size: usize,
+    connect_timeout: Option<Duration>,
+    file_access: Arc<FileAccess>,
+}
+
+impl MySqlPools {
+    /// 创建连接池缓存，默认不允许读取任何 CA 证书文件
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            pools: PoolCache::default(),
+            max_size,
+            connect_timeout: None,
+            file_access: Arc::new(FileAccess::new()),
+        }
+    }
+
+    /// 设置 `ssl_ca` 证书文件允许所在的位置
+    pub fn with_file_access(mut self, file_access: FileAccess) -> Self {
+        self.file_access = Arc::new(file_access);
+        self
+    }
+
+    /// 设置获取连接的超时时间
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// 最多缓存的连接池数量，超出时关闭最久未使用的连接池
+    pub fn with_max_pools(mut self, max_pools: usize) -> Self {
+        self.pools.set_max_entries(max_pools);
+        self
+    }
+
+    /// 连接池未使用超过该时间后关闭
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pools.set_idle_timeout(timeout);
+        self
+    }
+
+    /// 获取连接信息对应的连接池，不存在时创建；连接在首次使用时才建立
+    pub fn pool(&self, connection_info: &ConnectionInfo) -> Result<Pool> {
+        self.pools.get_or_try_insert_with(connection_info, || {
+            Ok(Pool::new(opts(
+                connection_info,
+                self.max_size,
+                &self.file_access,
+            )?))
+        })
+    }
+
+    /// 创建使用缓存连接池的连接器
+    pub fn connector(&self, connection_info: &ConnectionInfo) -> Result<MySqlConnector> {
+        let pool = self.pool(connection_info)?;
+        Ok(MySqlConnector::from_pool(pool, self.connect_timeout))
+    }
+
+    /// 关闭并移除所有连接池，空闲连接在后台断开
+    pub fn close(&self) {
+        self.pools.clear();
+    }
+
+    /// 当前缓存的连接池数量
+    pub fn len(&self) -> usize {
+        self.pools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for MySqlPools {
+    fn default() -> Self {
+        Self::new(DEFAULT_POOL_SIZE)
+    }
+}
+
+impl ConnectorFactory for MySqlPools {
+    fn connector(&self, context: &ConnectorContext<'_>) -> Result<Box<dyn Connector>> {
+        Ok(Box::new(MySqlPools::connector(
+            self,
+            context.connection_info,
+        )?))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            cancellation: true,
+            tls: true,
+            ..Capabilities::default()
+        }
+    }
+
+    fn close(&self) {
+        MySqlPools::close(self)
+    }
+}
+
+/// 在后台断开连接池，借出的连接归还后断开；不在 Tokio 运行时中时直接丢弃
+impl Release for Pool {
+    fn release(self) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        runtime.spawn(async move {
+            if let Err(e) = self.disconnect().await {
+                tracing::warn!("failed to close MySQL pool: {e}");
+            }
+        });
+    }
+}
+
+/// 连接选项；MySQL 的 schema 即数据库，指定 `schema` 时以它作为默认数据库
+fn opts(
+    connection_info: &ConnectionInfo,
+    max_size: usize,
+    file_access: &FileAccess,
+) -> Result<Opts> {
+    let constraints = PoolConstraints::new(0, max_size)
+        .ok_or_else(|| Error::Config(format!("invalid MySQL pool size {max_size}")))?;
+    let database = connection_info
+        .schema
+        .clone()
+        .unwrap_or_else(|| connection_info.database.clone());
+    let builder = OptsBuilder::default()
+        .ip_or_hostname(connection_info.host.clone())
+        .tcp_port(connection_info.port)
+        .user(Some(connection_info.user.clone()))
+        .pass(Some(connection_info.password.clone()))
+        .db_name(Some(database))
+        .prefer_socket(false)
+        .init(vec![SESSION_TIME_ZONE])
+        .ssl_opts(ssl_opts(connection_info, file_access)?)
+        .pool_opts(PoolOpts::new().with_constraints(constraints));
+    Ok(builder.into())
+}
+
+/// 会话时区固定为 UTC，TIMESTAMP 列按 UTC 返回
+const SESSION_TIME_ZONE: &str = "SET time_zone = '+00:00'";
+
+/// TLS 选项，`ssl_ca` 来自请求，必须位于允许的目录中
+fn ssl_opts(connection_info: &ConnectionInfo, file_access: &FileAccess) -> Result<Option<SslOpts>> {
+    if connection_info.ssl_mode == SslMode::Disable {
+        return Ok(None);
+    }
+    let root_certs = connection_info
+        .ssl_ca
+        .iter()
+        .map(|path| Ok(file_access.local_path(path)?.into()))
+        .collect::<Result<_>>()?;
+    let opts = SslOpts::default().with_root_certs(root_certs);
+    Ok(match connection_info.ssl_mode {
+        SslMode::Disable => None,
+        SslMode::Require => Some(
+            opts.with_danger_accept_invalid_certs(true)
+                .with_danger_skip_domain_validation(true),
+        ),
+        SslMode::VerifyCa => Some(opts.with_danger_skip_domain_validation(true)),
+        SslMode::VerifyFull => Some(opts),
+    })
+}
+
+/// MySQL 连接器
+pub struct MySqlConnector {
+    pool: Pool,
+    connect_timeout: Option<Duration>,
+}
+
+impl MySqlConnector {
+    /// 创建使用独立连接池的 MySQL 连接器
+    pub fn new(connection_info: ConnectionInfo) -> Result<Self> {
+        let pool = Pool::new(opts(
+            &connection_info,
+            DEFAULT_POOL_SIZE,
+            &FileAccess::new(),
+        )?);
+        Ok(Self::from_pool(pool, None))
+    }
+
+    /// 使用已有连接池创建连接器
+    pub fn from_pool(pool: Pool, connect_timeout: Option<Duration>) -> Self {
+        Self {
+            pool,
+            connect_timeout,
+        }
+    }
+
+    async fn conn(&self) -> Result<Conn> {
+        let conn = self.pool.get_conn();
+        let conn = match self.connect_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, conn).await.map_err(|_| {
+                Error::Connector(format!(
+                    "failed to get MySQL connection: timed out after {timeout:?}"
+                ))
+            })?,
+            None => conn.await,
+        };
+        conn.map_err(connection_error)
+    }
+}
+
+/// 发出 `KILL QUERY` 后等待服务端中止查询的最长时间
+const CANCEL_WAIT: Duration = Duration::from_secs(5);
+
+#[async_trait::async_trait]
+impl Connector for MySqlConnector {
+    async fn query(&self, sql: &str) -> Result<QueryResponse> {
+        let mut conn = self.conn().await?;
+        run_query(&mut conn, sql).await
+    }
+
+    async fn query_with_cancel(
+        &self,
+        sql: &str,
+        cancel: CancellationToken,
+    ) -> Result<QueryResponse> {
+        let mut conn = self.conn().await?;
+        // 请求被丢弃（客户端断开、超时）时也在服务端中止查询
+        let mut guard = KillOnDrop {
+            opts: conn.opts().clone(),
+            connection_id: Some(conn.id()),
+        };
+        let query = run_query(&mut conn, sql);
+        tokio::pin!(query);
+        let result = tokio::select! {
+            result = &mut query => result,
+            _ = cancel.cancelled() => {
+                guard.kill().await;
+                // 等服务端中止查询后再把连接归还连接池
+                let _ = tokio::time::timeout(CANCEL_WAIT, query).await;
+                Err(Error::Cancelled("query was cancelled".to_string()))
+            }
+        };
+        guard.disarm();
+        result
+    }
+
+    fn name(&self) -> &str {
+        "mysql"
+    }
+}
+
+/// 查询结束前被丢弃时通过新连接执行 `KILL QUERY`
+///
+/// 连接池可能已满，因此不从池中取连接
+struct KillOnDrop {
+    opts: Opts,
+    connection_id: Option<u32>,
+}
+
+impl KillOnDrop {
+    async fn kill(&mut self) {
+        if let Some(id) = self.connection_id.take() {
+            if let Err(e) = kill_query(self.opts.clone(), id).await {
+                tracing::warn!("failed to cancel MySQL query: {e}");
+            }
+        }
+    }
+
+    fn disarm(&mut self) {
+        self.connection_id = None;
+    }
+}
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        let Some(id) = self.connection_id.take() else {
+            return;
+        };
+        let opts = self.opts.clone();
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                if let Err(e) = kill_query(opts, id).await {
+                    tracing::warn!("failed to cancel abandoned MySQL query: {e}");
+                }
+            });
+        }
+    }
+}
+
+async fn kill_query(opts: Opts, connection_id: u32) -> mysql_async::Result<()> {
+    let mut conn = Conn::new(opts).await?;
+    conn.query_drop(format!("KILL QUERY {connection_id}"))
+        .await?;
+    conn.disconnect().await
+}
+
+/// 在给定连接上执行查询并按列类型转换结果
+async fn run_query(conn: &mut Conn, sql: &str) -> Result<QueryResponse> {
+    let mut result = conn.exec_iter(sql, ()).await.map_err(database_error)?;
+    let columns = result.columns().unwrap_or_else(|| Vec::new().into());
+    let mut data = Vec::new();
+    while let Some(row) = result.next().await.map_err(database_error)? {
+        data.push(decode_row(&columns, row)?);
+    }
+    Ok(QueryResponse {
+        data,
+        columns: columns
+            .iter()
+            .map(|c| ColumnInfo {
+                name: c.name_str().into_owned(),
+                data_type: column_type_name(c),
+            })
+            .collect(),
+        truncated: false,
+    })
+}
+
+/// 按列类型把一行转换为 JSON 数组
+fn decode_row(columns: &[Column], row: Row) -> Result<Value> {
+    let values = row
+        .unwrap()
+        .into_iter()
+        .zip(columns)
+        .map(|(value, column)| {
+            decode(column, value).map_err(|e| {
+                Error::Connector(format!(
+                    "failed to convert column `{}` of type `{}`: {e}",
+                    column.name_str(),
+                    column_type_name(column)
+                ))
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok(Value::Array(values))
+}
+
+/// 服务端错误转换为 `Error::Database`，保留 SQLSTATE
+pub(crate) fn database_error(err: mysql_async::Error) -> Error {
+    match err {
+        mysql_async::Error::Server(err) => Error::Database {
+            message: err.message,
+            sqlstate: Some(err.state),
+            position: None,
+        },
+        err => Error::Database {
+            message: err.to_string(),
+            sqlstate: None,
+            position: None,
+        },
+    }
+}
+
+fn connection_error(err: mysql_async::Error) -> Error {
+    match err {
+        err @ mysql_async::Error::Server(_) => database_error(err),
+        err => Error::Connector(format!("failed to get MySQL connection: {err}")),
+    }
+}
+
+/// `binary` 字符集，二进制字符串、JSON、DECIMAL 等列使用
+const BINARY_CHARSET: u16 = 63;
+
+/// 列的类型名；无符号整数带 `unsigned` 后缀，DECIMAL 带上精度和小数位，如 `decimal(10,2)`
+fn column_type_name(column: &Column) -> String {
+    use ColumnType::*;
+
+    let flags = column.flags();
+    let binary = column.character_set() == BINARY_CHARSET;
+    let name = match column.column_type() {
+        MYSQL_TYPE_TINY => "tinyint",
+        MYSQL_TYPE_SHORT => "smallint",
+        MYSQL_TYPE_INT24 => "mediumint",
+        MYSQL_TYPE_LONG => "int",
+        MYSQL_TYPE_LONGLONG => "bigint",
+        MYSQL_TYPE_DECIMAL | MYSQL_TYPE_NEWDECIMAL => return decimal_type_name(column),
+        MYSQL_TYPE_FLOAT => "float",
+        MYSQL_TYPE_DOUBLE => "double",
+        MYSQL_TYPE_NULL => "null",
+        MYSQL_TYPE_TIMESTAMP | MYSQL_TYPE_TIMESTAMP2 => "timestamptz",
+        MYSQL_TYPE_DATE | MYSQL_TYPE_NEWDATE => "date",
+        MYSQL_TYPE_TIME | MYSQL_TYPE_TIME2 => "time",
+        MYSQL_TYPE_DATETIME | MYSQL_TYPE_DATETIME2 => "datetime",
+        MYSQL_TYPE_YEAR => "year",
+        MYSQL_TYPE_BIT => return format!("bit({})", column.column_length()),
+        MYSQL_TYPE_JSON => "json",
+        MYSQL_TYPE_ENUM => "enum",
+        MYSQL_TYPE_SET => "set",
+        MYSQL_TYPE_GEOMETRY => "geometry",
+        MYSQL_TYPE_VECTOR => "vector",
+        MYSQL_TYPE_TINY_BLOB | MYSQL_TYPE_MEDIUM_BLOB | MYSQL_TYPE_LONG_BLOB | MYSQL_TYPE_BLOB => {
+            if binary {
+                "blob"
+            } else {
+                "text"
+            }
+        }
+        MYSQL_TYPE_VARCHAR | MYSQL_TYPE_VAR_STRING => {
+            if binary {
+                "varbinary"
+            } else {
+                "varchar"
+            }
+        }
+        // 结果集中的 ENUM 和 SET 列以 STRING 类型加标志位表示
+        MYSQL_TYPE_STRING if flags.contains(ColumnFlags::ENUM_FLAG) => "enum",
+        MYSQL_TYPE_STRING if flags.contains(ColumnFlags::SET_FLAG) => "set",
+        MYSQL_TYPE_STRING if binary => "binary",
+        MYSQL_TYPE_STRING => "char",
+        MYSQL_TYPE_TYPED_ARRAY | MYSQL_TYPE_UNKNOWN => "unknown",
+    };
+    let integer = matches!(
+        column.column_type(),
+        MYSQL_TYPE_TINY
+            | MYSQL_TYPE_SHORT
+            | MYSQL_TYPE_INT24
+            | MYSQL_TYPE_LONG
+            | MYSQL_TYPE_LONGLONG
+    );
+    if integer && flags.contains(ColumnFlags::UNSIGNED_FLAG) {
+        format!("{name} unsigned")
+    } else {
+        name.to_string()
+    }
+}
+
+/// DECIMAL 的显示长度包含小数点和符号位，去掉后得到精度
+fn decimal_type_name(column: &Column) -> String {
+    let scale = u32::from(column.decimals());
+    let unsigned = column.flags().contains(ColumnFlags::UNSIGNED_FLAG);
+    let precision = column
+        .column_length()
+        .saturating_sub(u32::from(scale > 0))
+        .saturating_sub(u32::from(!unsigned));
+    format!("decimal({precision},{scale})")
+}
+
+/// 将二进制协议的值按列类型转换为 JSON
+///
+/// DECIMAL 转为字符串以保留精度，BIT(1) 转为布尔值，二进制字符串转为 base64，
+/// 无法表示的日期（如 `0000-00-00`）转为 null
+fn decode(column: &Column, value: MySqlValue) -> std::result::Result<Value, BoxError> {
+    use ColumnType::*;
+
+    let value = match value {
+        MySqlValue::NULL => Value::Null,
+        MySqlValue::Int(n) => Value::from(n),
+        MySqlValue::UInt(n) => Value::from(n),
+        MySqlValue::Float(n) => float(n.into()),
+        MySqlValue::Double(n) => float(n),
+        MySqlValue::Date(year, month, day, hour, minute, second, micros) => {
+            let Some(datetime) = NaiveDate::from_ymd_opt(year.into(), month.into(), day.into())
+                .and_then(|date| {
+                    date.and_hms_micro_opt(hour.into(), minute.into(), second.into(), micros)
+                })
+            else {
+                return Ok(Value::Null);
+            };
+            Value::String(match column.column_type() {
+                MYSQL_TYPE_DATE | MYSQL_TYPE_NEWDATE => datetime.date().to_string(),
+                MYSQL_TYPE_TIMESTAMP | MYSQL_TYPE_TIMESTAMP2 => {
+                    DateTime::<Utc>::from_naive_utc_and_offset(datetime, Utc).to_rfc3339()
+                }
+                _ => format_datetime(&datetime),
+            })
+        }
+        MySqlValue::Time(negative, days, hours, minutes, seconds, micros) => {
+            let sign = if negative { "-" } else { "" };
+            let hours = days * 24 + u32::from(hours);
+            let mut time = format!("{sign}{hours:02}:{minutes:02}:{seconds:02}");
+            if micros > 0 {
+                time.push_str(&format!(".{micros:06}"));
+            }
+            Value::String(time)
+        }
+        MySqlValue::Bytes(bytes) => match column.column_type() {
+            MYSQL_TYPE_DECIMAL | MYSQL_TYPE_NEWDECIMAL => Value::String(String::from_utf8(bytes)?),
+            MYSQL_TYPE_JSON => serde_json::from_slice(&bytes)?,
+            MYSQL_TYPE_BIT => decode_bit(column, &bytes)?,
+            _ if column.character_set() == BINARY_CHARSET => Value::String(STANDARD.encode(bytes)),
+            _ => Value::String(String::from_utf8(bytes)?),
+        },
+    };
+    Ok(value)
+}
+
+/// BIT 的值是大端字节串，BIT(1) 转为布尔值，更宽的转为无符号整数
+fn decode_bit(column: &Column, bytes: &[u8]) -> std::result::Result<Value, BoxError> {
+    if bytes.len() > 8 {
+        return Err(format!("invalid bit value of {} bytes", bytes.len()).into());
+    }
+    let value = bytes
+        .iter()
+        .fold(0u64, |value, byte| value << 8 | u64::from(*byte));
+    if column.column_length() == 1 {
+        Ok(Value::Bool(value != 0))
+    } else {
+        Ok(Value::from(value))
+    }
+}
+
+fn format_datetime(datetime: &NaiveDateTime) -> String {
+    datetime.format("%Y-%m-%dT%H:%M:%S%.f").to_string()
+}
+
+/// NaN 和无穷大无法表示为 JSON 数字，转为字符串
+fn float(value: f64) -> Value {
+    Number::from_f64(value)
+        .map(Value::Number)
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn column(column_type: ColumnType) -> Column {
+        Column::new(column_type).with_name(b"c")
+    }
+
+    fn connection_info() -> ConnectionInfo {
+        ConnectionInfo {
+            host: "localhost".to_string(),
+            port: 3306,
+            database: "db".to_string(),
+            user: "user".to_string(),
+            password: "secret".to_string(),
+            schema: None,
+            ssl_mode: SslMode::Disable,
+            ssl_ca: None,
+            files: Default::default(),
+            options: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_column_type_names() {
+        use ColumnType::*;
+
+        let unsigned = column(MYSQL_TYPE_LONGLONG).with_flags(ColumnFlags::UNSIGNED_FLAG);
+        assert_eq!(column_type_name(&unsigned), "bigint unsigned");
+        assert_eq!(column_type_name(&column(MYSQL_TYPE_TINY)), "tinyint");
+
+        let decimal = column(MYSQL_TYPE_NEWDECIMAL)
+            .with_column_length(12)
+            .with_decimals(2);
+        assert_eq!(column_type_name(&decimal), "decimal(10,2)");
+        let decimal = column(MYSQL_TYPE_NEWDECIMAL)
+            .with_column_length(5)
+            .with_flags(ColumnFlags::UNSIGNED_FLAG);
+        assert_eq!(column_type_name(&decimal), "decimal(5,0)");
+
+        assert_eq!(column_type_name(&column(MYSQL_TYPE_DATETIME)), "datetime");
+        assert_eq!(
+            column_type_name(&column(MYSQL_TYPE_TIMESTAMP)),
+            "timestamptz"
+        );
+        assert_eq!(
+            column_type_name(&column(MYSQL_TYPE_BIT).with_column_length(1)),
+            "bit(1)"
+        );
+        assert_eq!(
+            column_type_name(&column(MYSQL_TYPE_VAR_STRING).with_character_set(BINARY_CHARSET)),
+            "varbinary"
+        );
+        assert_eq!(
+            column_type_name(&column(MYSQL_TYPE_STRING).with_flags(ColumnFlags::ENUM_FLAG)),
+            "enum"
+        );
+    }
+
+    #[test]
+    fn test_decode_values() {
+        use ColumnType::*;
+
+        let decimal = column(MYSQL_TYPE_NEWDECIMAL).with_character_set(BINARY_CHARSET);
+        assert_eq!(
+            decode(&decimal, MySqlValue::Bytes(b"-12.50".to_vec())).unwrap(),
+            json!("-12.50")
+        );
+        assert_eq!(
+            decode(&column(MYSQL_TYPE_LONGLONG), MySqlValue::UInt(u64::MAX)).unwrap(),
+            json!(u64::MAX)
+        );
+
+        let datetime = MySqlValue::Date(2024, 2, 29, 13, 5, 9, 120_000);
+        assert_eq!(
+            decode(&column(MYSQL_TYPE_DATETIME), datetime.clone()).unwrap(),
+            json!("2024-02-29T13:05:09.120")
+        );
+        assert_eq!(
+            decode(&column(MYSQL_TYPE_TIMESTAMP), datetime).unwrap(),
+            json!("2024-02-29T13:05:09.120+00:00")
+        );
+        assert_eq!(
+            decode(
+                &column(MYSQL_TYPE_DATE),
+                MySqlValue::Date(2024, 2, 29, 0, 0, 0, 0)
+            )
+            .unwrap(),
+            json!("2024-02-29")
+        );
+        assert_eq!(
+            decode(
+                &column(MYSQL_TYPE_DATE),
+                MySqlValue::Date(0, 0, 0, 0, 0, 0, 0)
+            )
+            .unwrap(),
+            Value::Null
+        );
+        assert_eq!(
+            decode(
+                &column(MYSQL_TYPE_TIME),
+                MySqlValue::Time(true, 1, 2, 3, 4, 5)
+            )
+            .unwrap(),
+            json!("-26:03:04.000005")
+        );
+
+        let json_column = column(MYSQL_TYPE_JSON).with_character_set(BINARY_CHARSET);
+        assert_eq!(
+            decode(
+                &json_column,
+                MySqlValue::Bytes(br#"{"a": [1, null]}"#.to_vec())
+            )
+            .unwrap(),
+            json!({"a": [1, null]})
+        );
+
+        let flag = column(MYSQL_TYPE_BIT).with_column_length(1);
+        assert_eq!(
+            decode(&flag, MySqlValue::Bytes(vec![1])).unwrap(),
+            json!(true)
+        );
+        let bits = column(MYSQL_TYPE_BIT).with_column_length(12);
+        assert_eq!(
+            decode(&bits, MySqlValue::Bytes(vec![0x0a, 0xbc])).unwrap(),
+            json!(0x0abc)
+        );
+
+        let binary = column(MYSQL_TYPE_BLOB).with_character_set(BINARY_CHARSET);
+        assert_eq!(
+            decode(&binary, MySqlValue::Bytes(vec![0, 255])).unwrap(),
+            json!("AP8=")
+        );
+        assert_eq!(
+            decode(
+                &column(MYSQL_TYPE_VAR_STRING),
+                MySqlValue::Bytes(b"abc".to_vec())
+            )
+            .unwrap(),
+            json!("abc")
+        );
+        assert_eq!(
+            decode(&column(MYSQL_TYPE_DOUBLE), MySqlValue::Double(f64::NAN)).unwrap(),
+            json!("NaN")
+        );
+    }
+
+    #[test]
+    fn test_pools_and_tls_options() {
+        let dir = std::env::temp_dir().join(format!("mimir-mysql-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let ca = dir.join("ca.pem");
+        std::fs::write(&ca, "").unwrap();
+        let file_access = FileAccess::new().with_root(&dir);
+
+        let info = connection_info();
+        let pools = MySqlPools::new(4).with_file_access(file_access.clone());
+        pools.pool(&info).unwrap();
+        pools.pool(&info).unwrap();
+        assert_eq!(pools.len(), 1);
+
+        let tls = ConnectionInfo {
+            ssl_mode: SslMode::VerifyCa,
+            ssl_ca: Some(ca.to_string_lossy().into_owned()),
+            ..info.clone()
+        };
+        pools.pool(&tls).unwrap();
+        assert_eq!(pools.len(), 2);
+        pools.close();
+        assert!(pools.is_empty());
+
+        assert!(ssl_opts(&info, &file_access).unwrap().is_none());
+        let verify_ca = ssl_opts(&tls, &file_access).unwrap().unwrap();
+        assert_eq!(verify_ca.root_certs().len(), 1);
+        assert!(verify_ca.skip_domain_validation());
+        assert!(!verify_ca.accept_invalid_certs());
+        let require = ssl_opts(
+            &ConnectionInfo {
+                ssl_mode: SslMode::Require,
+                ..info.clone()
+            },
+            &file_access,
+        )
+        .unwrap()
+        .unwrap();
+        assert!(require.accept_invalid_certs());
+
+        // 请求中的 CA 路径必须位于允许的目录中
+        for path in ["/etc/passwd", "../ca.pem", "file:///etc/passwd"] {
+            let outside = ConnectionInfo {
+                ssl_ca: Some(path.to_string()),
+                ..tls.clone()
+            };
+            assert!(
+                matches!(pools.pool(&outside), Err(Error::Validation(_))),
+                "{path}"
+            );
+        }
+        assert!(matches!(
+            MySqlPools::new(4).pool(&tls),
+            Err(Error::Validation(_))
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_database_error_keeps_sqlstate() {
+        let err = database_error(mysql_async::Error::Server(mysql_async::ServerError {
+            code: 1146,
+            message: "Table 'db.t' doesn't exist".to_string(),
+            state: "42S02".to_string(),
+        }));
+        assert!(matches!(
+            err,
+            Error::Database { sqlstate: Some(ref s), .. } if s == "42S02"
+        ));
+    }
+
+    /// 需要 MySQL / MariaDB 服务，例如：
+    /// `docker run -e MYSQL_ALLOW_EMPTY_PASSWORD=1 -e MYSQL_DATABASE=test -p 3306:3306 mysql:8`，
+    /// 然后通过 `MIMIR_TEST_MYSQL_PORT` 等环境变量指定连接信息
+    #[tokio::test]
+    #[ignore]
+    async fn test_query_against_server() {
+        let env = |name: &str, default: &str| {
+            std::env::var(format!("MIMIR_TEST_MYSQL_{name}"))
+                .unwrap_or_else(|_| default.to_string())
+        };
+        let info = ConnectionInfo {
+            host: env("HOST", "127.0.0.1"),
+            port: env("PORT", "3306").parse().unwrap(),
+            database: env("DATABASE", "test"),
+            user: env("USER", "root"),
+            password: env("PASSWORD", ""),
+            ..connection_info()
+        };
+        let connector = MySqlConnector::new(info).unwrap();
+        let response = connector
+            .query(
+                "SELECT CAST(1.50 AS DECIMAL(10,2)) AS d, CAST(18446744073709551615 AS UNSIGNED) AS u, \
+                 TIMESTAMP '2024-01-02 03:04:05' AS dt, b'1' AS f, JSON_OBJECT('a', 1) AS j",
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.columns[0].data_type, "decimal(10,2)");
+        assert_eq!(response.columns[1].data_type, "bigint unsigned");
+        assert_eq!(
+            response.data,
+            vec![json!(["1.50", u64::MAX, "2024-01-02T03:04:05", "AQ==", {"a": 1}])]
+        );
+
+        let err = connector
+            .query("SELECT * FROM missing_table")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Database { sqlstate: Some(ref s), .. } if s == "42S02"));
+    }
+}
@@ -1,33 +1,1148 @@
 //! PostgreSQL 连接器
+//!
+//! 连接池按 `ConnectionInfo` 缓存复用，超出数量上限或空闲过久的连接池被关闭；查询结果按列的 Postgres 类型从二进制格式
+//! 转换为 JSON，每行输出为与 `columns` 顺序一致的数组。
 
-use crate::connector::trait_::Connector;
-use crate::error::Result;
-use crate::model::{ConnectionInfo, QueryResponse};
+use std::pin::Pin;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use deadpool_postgres::{
+    Client, Config, ManagerConfig, Object, Pool, PoolConfig, PoolError, RecyclingMethod, Runtime,
+};
+use fallible_iterator::FallibleIterator;
+use futures_util::stream::{self, StreamExt};
+use serde_json::{Number, Value};
+use tokio_postgres::error::ErrorPosition;
+use tokio_postgres::types::{FromSql, Kind, Type};
+use tokio_postgres::{CancelToken, NoTls, Row, RowStream, Statement};
+use tokio_util::sync::CancellationToken;
+
+use crate::connector::cache::{PoolCache, Release};
+use crate::connector::registry::{Capabilities, ConnectorContext, ConnectorFactory};
+use crate::connector::trait_::{Connector, QueryStream};
+use crate::error::{Error, Result};
+use crate::format::iso8601_duration;
+use crate::model::{ColumnInfo, ConnectionInfo, QueryResponse, SslMode};
+
+type BoxError = Box<dyn std::error::Error + Sync + Send>;
+
+/// 每个连接池默认的最大连接数
+pub const DEFAULT_POOL_SIZE: usize = 16;
+
+/// 按 `ConnectionInfo` 缓存的连接池，相同连接信息的请求共享同一个池
+pub struct PostgresPools {
+    pools: PoolCache<Pool>,
+    max_size: usize,
+    connect_timeout: Option<Duration>,
+}
+
+impl PostgresPools {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            pools: PoolCache::default(),
+            max_size,
+            connect_timeout: None,
+        }
+    }
+
+    /// 设置新建连接的超时时间
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// 最多缓存的连接池数量，超出时关闭最久未使用的连接池
+    pub fn with_max_pools(mut self, max_pools: usize) -> Self {
+        self.pools.set_max_entries(max_pools);
+        self
+    }
+
+    /// 连接池未使用超过该时间后关闭
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pools.set_idle_timeout(timeout);
+        self
+    }
+
+    /// 获取连接信息对应的连接池，不存在时创建；连接在首次使用时才建立
+    pub fn pool(&self, connection_info: &ConnectionInfo) -> Result<Pool> {
+        self.pools.get_or_try_insert_with(connection_info, || {
+            create_pool(connection_info, self.max_size, self.connect_timeout)
+        })
+    }
+
+    /// 创建使用缓存连接池的连接器
+    pub fn connector(&self, connection_info: &ConnectionInfo) -> Result<PostgresConnector> {
+        self.pool(connection_info).map(PostgresConnector::from_pool)
+    }
+
+    /// 关闭并移除所有连接池
+    pub fn close(&self) {
+        self.pools.clear();
+    }
+
+    /// 当前缓存的连接池数量
+    pub fn len(&self) -> usize {
+        self.pools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for PostgresPools {
+    fn default() -> Self {
+        Self::new(DEFAULT_POOL_SIZE)
+    }
+}
+
+impl ConnectorFactory for PostgresPools {
+    fn connector(&self, context: &ConnectorContext<'_>) -> Result<Box<dyn Connector>> {
+        Ok(Box::new(PostgresPools::connector(
+            self,
+            context.connection_info,
+        )?))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            streaming: true,
+            cancellation: true,
+            ..Capabilities::default()
+        }
+    }
+
+    fn close(&self) {
+        PostgresPools::close(self)
+    }
+}
+
+/// 关闭连接池，借出的连接在归还时断开
+impl Release for Pool {
+    fn release(self) {
+        self.close();
+    }
+}
+
+fn create_pool(
+    connection_info: &ConnectionInfo,
+    max_size: usize,
+    connect_timeout: Option<Duration>,
+) -> Result<Pool> {
+    if connection_info.ssl_mode != SslMode::Disable {
+        return Err(Error::Validation(
+            "TLS is not supported for Postgres connections yet".to_string(),
+        ));
+    }
+    let mut config = Config::new();
+    config.host = Some(connection_info.host.clone());
+    config.port = Some(connection_info.port);
+    config.dbname = Some(connection_info.database.clone());
+    config.user = Some(connection_info.user.clone());
+    config.password = Some(connection_info.password.clone());
+    config.options = connection_info.schema.as_deref().map(search_path_option);
+    config.application_name = Some("mimir_well_engine".to_string());
+    config.connect_timeout = connect_timeout;
+    config.manager = Some(ManagerConfig {
+        recycling_method: RecyclingMethod::Fast,
+    });
+    config.pool = Some(PoolConfig::new(max_size));
+
+    config
+        .create_pool(Some(Runtime::Tokio1), NoTls)
+        .map_err(|e| Error::Connector(format!("failed to create Postgres pool: {e}")))
+}
+
+/// 通过启动参数设置 search_path，池中每个连接建立时即生效
+fn search_path_option(schema: &str) -> String {
+    let schema = format!("\"{}\"", schema.replace('"', "\"\""));
+    // options 中空格分隔参数，值里的空格和反斜杠需要转义
+    let escaped = schema.replace('\\', "\\\\").replace(' ', "\\ ");
+    format!("-c search_path={escaped}")
+}
 
 /// PostgreSQL 连接器
 pub struct PostgresConnector {
-    _connection_info: ConnectionInfo,
+    pool: Pool,
 }
 
 impl PostgresConnector {
-    /// 创建新的 PostgreSQL 连接器
-    pub fn new(connection_info: ConnectionInfo) -> Self {
-        Self {
-            _connection_info: connection_info,
-        }
+    /// 创建使用独立连接池的 PostgreSQL 连接器
+    pub fn new(connection_info: ConnectionInfo) -> Result<Self> {
+        create_pool(&connection_info, DEFAULT_POOL_SIZE, None).map(Self::from_pool)
+    }
+
+    /// 使用已有连接池创建连接器
+    pub fn from_pool(pool: Pool) -> Self {
+        Self { pool }
     }
 }
 
+/// 发出取消请求后等待服务端中止查询的最长时间
+const CANCEL_WAIT: Duration = Duration::from_secs(5);
+
 #[async_trait::async_trait]
 impl Connector for PostgresConnector {
-    async fn query(&self, _sql: &str) -> Result<QueryResponse> {
-        // TODO: 实现 PostgreSQL 查询执行
-        Err(crate::error::Error::Connector(
-            "Not implemented yet".to_string(),
-        ))
+    async fn query(&self, sql: &str) -> Result<QueryResponse> {
+        let client = self.pool.get().await.map_err(pool_error)?;
+        run_query(&client, sql).await
+    }
+
+    async fn query_with_cancel(
+        &self,
+        sql: &str,
+        cancel: CancellationToken,
+    ) -> Result<QueryResponse> {
+        let client = self.pool.get().await.map_err(pool_error)?;
+        // 请求被丢弃（客户端断开、超时）时也在服务端取消查询
+        let mut guard = CancelOnDrop(Some(client.cancel_token()));
+        let query = run_query(&client, sql);
+        tokio::pin!(query);
+        let result = tokio::select! {
+            result = &mut query => result,
+            _ = cancel.cancelled() => {
+                guard.cancel().await;
+                // 等服务端中止查询后再把连接归还连接池
+                let _ = tokio::time::timeout(CANCEL_WAIT, query).await;
+                Err(Error::Cancelled("query was cancelled".to_string()))
+            }
+        };
+        guard.disarm();
+        result
+    }
+
+    async fn query_stream(&self, sql: &str, cancel: CancellationToken) -> Result<QueryStream> {
+        let client = self.pool.get().await.map_err(pool_error)?;
+        let (columns, rows) = {
+            let mut guard = CancelOnDrop(Some(client.cancel_token()));
+            let start = start_query(&client, sql);
+            tokio::pin!(start);
+            let started = tokio::select! {
+                result = &mut start => result,
+                _ = cancel.cancelled() => {
+                    guard.cancel().await;
+                    let _ = tokio::time::timeout(CANCEL_WAIT, start).await;
+                    Err(Error::Cancelled("query was cancelled".to_string()))
+                }
+            };
+            guard.disarm();
+            started?
+        };
+
+        let rows = PostgresRows {
+            cancel_token: client.cancel_token(),
+            client: Some(client),
+            rows: Box::pin(rows),
+            cancel,
+            finished: false,
+        };
+        let rows = stream::unfold(Some(rows), |state| async move {
+            let mut state = state?;
+            let next = tokio::select! {
+                next = state.rows.next() => next,
+                _ = state.cancel.cancelled() => {
+                    return Some((Err(Error::Cancelled("query was cancelled".to_string())), None));
+                }
+            };
+            match next {
+                Some(Ok(row)) => {
+                    let row = decode_row(&row);
+                    let state = row.is_ok().then_some(state);
+                    Some((row, state))
+                }
+                Some(Err(e)) => {
+                    state.finished = true;
+                    Some((Err(database_error(e)), None))
+                }
+                None => {
+                    state.finished = true;
+                    None
+                }
+            }
+        });
+        let batches = rows
+            .ready_chunks(STREAM_BATCH_SIZE)
+            .flat_map(|rows| stream::iter(split_error(rows)));
+        Ok(QueryStream {
+            columns,
+            batches: batches.boxed(),
+        })
     }
 
     fn name(&self) -> &str {
         "postgres"
     }
 }
+
+/// 把一批行拆成出错前的行和错误，出错前已读到的行仍然输出
+fn split_error(rows: Vec<Result<Value>>) -> impl Iterator<Item = Result<Vec<Value>>> {
+    let mut batch = Vec::with_capacity(rows.len());
+    let mut error = None;
+    for row in rows {
+        match row {
+            Ok(row) => batch.push(row),
+            Err(e) => {
+                error = Some(e);
+                break;
+            }
+        }
+    }
+    let batch = (!batch.is_empty() || error.is_none()).then_some(Ok(batch));
+    batch.into_iter().chain(error.map(Err))
+}
+
+/// 流式查询时每个批次的最大行数，已到达的行不足一批时立即发出
+const STREAM_BATCH_SIZE: usize = 1024;
+
+/// 正在流式读取的查询
+///
+/// 驱动与连接之间的通道容量有限，下游停止读取时连接也停止从服务端接收数据，
+/// 以此形成背压。查询未读完就被丢弃时，连接移出连接池，发送取消请求后关闭，
+/// 避免尚未结束的查询影响之后复用该连接的请求。
+struct PostgresRows {
+    client: Option<Client>,
+    cancel_token: CancelToken,
+    rows: Pin<Box<RowStream>>,
+    cancel: CancellationToken,
+    /// 服务端已经结束查询，连接可以归还连接池
+    finished: bool,
+}
+
+impl Drop for PostgresRows {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let Some(client) = self.client.take() else {
+            return;
+        };
+        let client = Object::take(client);
+        let token = self.cancel_token.clone();
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                if let Err(e) = token.cancel_query(NoTls).await {
+                    tracing::warn!("failed to cancel abandoned Postgres query: {e}");
+                }
+                drop(client);
+            });
+        }
+    }
+}
+
+/// 查询结束前被丢弃时向服务端发送取消请求
+struct CancelOnDrop(Option<CancelToken>);
+
+impl CancelOnDrop {
+    async fn cancel(&mut self) {
+        if let Some(token) = self.0.take() {
+            if let Err(e) = token.cancel_query(NoTls).await {
+                tracing::warn!("failed to cancel Postgres query: {e}");
+            }
+        }
+    }
+
+    fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let Some(token) = self.0.take() else {
+            return;
+        };
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                if let Err(e) = token.cancel_query(NoTls).await {
+                    tracing::warn!("failed to cancel abandoned Postgres query: {e}");
+                }
+            });
+        }
+    }
+}
+
+/// 在给定连接上执行查询并按列类型转换结果
+async fn run_query(client: &Client, sql: &str) -> Result<QueryResponse> {
+    let statement = client.prepare(sql).await.map_err(database_error)?;
+    let rows = client
+        .query(&statement, &[])
+        .await
+        .map_err(database_error)?;
+
+    let data = rows.iter().map(decode_row).collect::<Result<_>>()?;
+    Ok(QueryResponse {
+        data,
+        columns: columns(&statement),
+        truncated: false,
+    })
+}
+
+/// 在给定连接上开始查询，结果行由返回的 `RowStream` 逐行读取
+async fn start_query(client: &Client, sql: &str) -> Result<(Vec<ColumnInfo>, RowStream)> {
+    let statement = client.prepare(sql).await.map_err(database_error)?;
+    let rows = client
+        .query_raw(
+            &statement,
+            std::iter::empty::<&(dyn tokio_postgres::types::ToSql + Sync)>(),
+        )
+        .await
+        .map_err(database_error)?;
+    Ok((columns(&statement), rows))
+}
+
+fn columns(statement: &Statement) -> Vec<ColumnInfo> {
+    statement
+        .columns()
+        .iter()
+        .map(|c| ColumnInfo {
+            name: c.name().to_string(),
+            data_type: column_type_name(c.type_(), c.type_modifier()),
+        })
+        .collect()
+}
+
+/// 列的类型名，声明了精度的 numeric 带上精度和小数位，如 `numeric(10,2)`
+fn column_type_name(ty: &Type, type_modifier: i32) -> String {
+    match ty.kind() {
+        Kind::Array(element) => format!("{}[]", column_type_name(element, type_modifier)),
+        _ if *ty == Type::NUMERIC && type_modifier >= VARHDRSZ => {
+            let modifier = type_modifier - VARHDRSZ;
+            let precision = (modifier >> 16) & 0xffff;
+            // 小数位占低 11 位，可以为负数
+            let scale = ((modifier & 0x7ff) ^ 1024) - 1024;
+            format!("{}({precision},{scale})", ty.name())
+        }
+        _ => type_name(ty),
+    }
+}
+
+/// numeric 的类型修饰符包含的头部长度
+const VARHDRSZ: i32 = 4;
+
+/// 按列类型把一行转换为 JSON 数组
+fn decode_row(row: &Row) -> Result<Value> {
+    let mut values = Vec::with_capacity(row.len());
+    for (index, column) in row.columns().iter().enumerate() {
+        let RawValue(raw) = row.try_get(index).map_err(database_error)?;
+        let value = match raw {
+            Some(raw) => decode(column.type_(), raw).map_err(|e| {
+                Error::Connector(format!(
+                    "failed to convert column `{}` of type `{}`: {e}",
+                    column.name(),
+                    type_name(column.type_())
+                ))
+            })?,
+            None => Value::Null,
+        };
+        values.push(value);
+    }
+    Ok(Value::Array(values))
+}
+
+/// 驱动错误转换为 `Error::Database`，保留服务端返回的 SQLSTATE
+pub(crate) fn database_error(err: tokio_postgres::Error) -> Error {
+    match err.as_db_error() {
+        Some(db) => {
+            let mut message = db.message().to_string();
+            if let Some(detail) = db.detail() {
+                message.push_str(&format!(" DETAIL: {detail}"));
+            }
+            if let Some(hint) = db.hint() {
+                message.push_str(&format!(" HINT: {hint}"));
+            }
+            let position = match db.position() {
+                Some(ErrorPosition::Original(position)) => Some(*position),
+                _ => None,
+            };
+            Error::Database {
+                message,
+                sqlstate: Some(db.code().code().to_string()),
+                position,
+            }
+        }
+        None => Error::Database {
+            message: err.to_string(),
+            sqlstate: err.code().map(|c| c.code().to_string()),
+            position: None,
+        },
+    }
+}
+
+fn pool_error(err: PoolError) -> Error {
+    match err {
+        PoolError::Backend(err) => database_error(err),
+        err => Error::Connector(format!("failed to get Postgres connection: {err}")),
+    }
+}
+
+/// 列类型名称，数组显示为 `int4[]` 形式
+///
+/// 单字节的 `"char"` 与 Postgres 自身的显示一样带引号，以区别于 SQL 标准的 `char`
+fn type_name(ty: &Type) -> String {
+    match ty.kind() {
+        Kind::Array(element) => format!("{}[]", type_name(element)),
+        _ if *ty == Type::CHAR => "\"char\"".to_string(),
+        _ => ty.name().to_string(),
+    }
+}
+
+/// 不做转换的原始值，按列类型在 `decode` 中解码
+struct RawValue<'a>(Option<&'a [u8]>);
+
+impl<'a> FromSql<'a> for RawValue<'a> {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> std::result::Result<Self, BoxError> {
+        Ok(Self(Some(raw)))
+    }
+
+    fn from_sql_null(_: &Type) -> std::result::Result<Self, BoxError> {
+        Ok(Self(None))
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+}
+
+/// 将二进制格式的值按类型转换为 JSON
+///
+/// numeric 转为字符串以保留精度，bytea 转为 base64，interval 转为 ISO 8601 时长；
+/// 网络地址、几何、范围等类型转为与 Postgres 文本格式相同的字符串，composite 转为对象。
+/// 没有专门转换的类型按原始值处理：是 UTF-8 文本时原样返回，否则转为 base64
+fn decode(ty: &Type, raw: &[u8]) -> std::result::Result<Value, BoxError> {
+    match ty.kind() {
+        Kind::Array(element) => return decode_array(element, raw),
+        Kind::Domain(base) => return decode(base, raw),
+        Kind::Enum(_) => return Ok(Value::String(String::from_utf8(raw.to_vec())?)),
+        Kind::Range(element) => return Ok(Value::String(decode_range(element, raw)?)),
+        Kind::Multirange(range) => return decode_multirange(range, raw),
+        Kind::Composite(fields) => return decode_composite(fields, raw),
+        _ => {}
+    }
+
+    let value = match *ty {
+        Type::BOOL => Value::Bool(bool::from_sql(ty, raw)?),
+        Type::CHAR => Value::from(i8::from_sql(ty, raw)?),
+        Type::INT2 => Value::from(i16::from_sql(ty, raw)?),
+        Type::INT4 => Value::from(i32::from_sql(ty, raw)?),
+        Type::INT8 => Value::from(i64::from_sql(ty, raw)?),
+        Type::OID => Value::from(u32::from_sql(ty, raw)?),
+        Type::FLOAT4 => float(f32::from_sql(ty, raw)?.into()),
+        Type::FLOAT8 => float(f64::from_sql(ty, raw)?),
+        Type::NUMERIC => Value::String(decode_numeric(raw)?),
+        Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN => {
+            Value::String(String::from_sql(ty, raw)?)
+        }
+        Type::JSON | Type::JSONB => serde_json::Value::from_sql(ty, raw)?,
+        Type::UUID => Value::String(uuid::Uuid::from_sql(ty, raw)?.to_string()),
+        Type::BYTEA => Value::String(STANDARD.encode(raw)),
+        Type::DATE => Value::String(NaiveDate::from_sql(ty, raw)?.to_string()),
+        Type::TIME => Value::String(NaiveTime::from_sql(ty, raw)?.to_string()),
+        Type::TIMESTAMP => Value::String(
+            NaiveDateTime::from_sql(ty, raw)?
+                .format("%Y-%m-%dT%H:%M:%S%.f")
+                .to_string(),
+        ),
+        Type::TIMESTAMPTZ => Value::String(DateTime::<Utc>::from_sql(ty, raw)?.to_rfc3339()),
+        Type::INTERVAL => Value::String(decode_interval(raw)?),
+        Type::TIMETZ => Value::String(decode_timetz(raw)?),
+        Type::MONEY => Value::String(decode_money(raw)?),
+        Type::INET | Type::CIDR => Value::String(decode_inet(ty, raw)?),
+        Type::MACADDR | Type::MACADDR8 => Value::String(decode_macaddr(raw)),
+        Type::BIT | Type::VARBIT => Value::String(decode_bit(raw)?),
+        Type::POINT
+        | Type::LSEG
+        | Type::BOX
+        | Type::PATH
+        | Type::POLYGON
+        | Type::LINE
+        | Type::CIRCLE => Value::String(decode_geometry(ty, raw)?),
+        _ => match std::str::from_utf8(raw) {
+            Ok(text) => Value::String(text.to_string()),
+            Err(_) => Value::String(STANDARD.encode(raw)),
+        },
+    };
+    Ok(value)
+}
+
+/// 值在范围、多范围等文本格式中的表示，字符串不带引号
+fn value_text(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// 范围转为 `[1,10)` 形式，空范围为 `empty`，无界的一端为空
+fn decode_range(element: &Type, raw: &[u8]) -> std::result::Result<String, BoxError> {
+    use postgres_protocol::types::{range_from_sql, Range, RangeBound};
+
+    let (lower, upper) = match range_from_sql(raw)? {
+        Range::Empty => return Ok("empty".to_string()),
+        Range::Nonempty(lower, upper) => (lower, upper),
+    };
+    let bound = |bound: RangeBound<Option<&[u8]>>| -> std::result::Result<_, BoxError> {
+        let (inclusive, value) = match bound {
+            RangeBound::Inclusive(value) => (true, value),
+            RangeBound::Exclusive(value) => (false, value),
+            RangeBound::Unbounded => (false, None),
+        };
+        let text = match value {
+            Some(raw) => value_text(decode(element, raw)?),
+            None => String::new(),
+        };
+        Ok((inclusive, text))
+    };
+    let (lower_inclusive, lower) = bound(lower)?;
+    let (upper_inclusive, upper) = bound(upper)?;
+    Ok(format!(
+        "{}{lower},{upper}{}",
+        if lower_inclusive { '[' } else { '(' },
+        if upper_inclusive { ']' } else { ')' }
+    ))
+}
+
+/// 多范围的二进制格式：范围个数，及每个范围的长度和内容；转为 `{[1,3),[5,7)}` 形式
+fn decode_multirange(range: &Type, raw: &[u8]) -> std::result::Result<Value, BoxError> {
+    let Kind::Range(element) = range.kind() else {
+        return Err(format!("`{}` is not a range type", range.name()).into());
+    };
+    let mut reader = Reader(raw);
+    let count = reader.i32()?;
+    let ranges = (0..count)
+        .map(|_| {
+            let len = usize::try_from(reader.i32()?)?;
+            decode_range(element, reader.take(len)?)
+        })
+        .collect::<std::result::Result<Vec<_>, BoxError>>()?;
+    Ok(Value::String(format!("{{{}}}", ranges.join(","))))
+}
+
+/// composite 的二进制格式：字段个数，及每个字段的类型 OID、长度和内容；转为 JSON 对象
+fn decode_composite(
+    fields: &[tokio_postgres::types::Field],
+    raw: &[u8],
+) -> std::result::Result<Value, BoxError> {
+    let mut reader = Reader(raw);
+    let count = usize::try_from(reader.i32()?)?;
+    if count != fields.len() {
+        return Err(format!("expected {} fields, found {count}", fields.len()).into());
+    }
+    let mut object = serde_json::Map::new();
+    for field in fields {
+        reader.i32()?;
+        let value = match reader.i32()? {
+            -1 => Value::Null,
+            len => decode(field.type_(), reader.take(usize::try_from(len)?)?)?,
+        };
+        object.insert(field.name().to_string(), value);
+    }
+    Ok(Value::Object(object))
+}
+
+/// timetz 的二进制格式：当天的微秒数和以秒计、向西为正的时区偏移；转为 `04:05:06.5+08:00` 形式
+fn decode_timetz(raw: &[u8]) -> std::result::Result<String, BoxError> {
+    let mut reader = Reader(raw);
+    let micros = reader.i64()?;
+    let zone = reader.i32()?;
+    let time = NaiveTime::from_num_seconds_from_midnight_opt(
+        u32::try_from(micros / 1_000_000)?,
+        u32::try_from(micros % 1_000_000)? * 1000,
+    )
+    .ok_or("invalid timetz value")?;
+    let offset = -zone;
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.unsigned_abs();
+    Ok(format!(
+        "{time}{sign}{:02}:{:02}",
+        offset / 3600,
+        offset % 3600 / 60
+    ))
+}
+
+/// money 的二进制格式是以分计的 int8，按两位小数转为字符串
+fn decode_money(raw: &[u8]) -> std::result::Result<String, BoxError> {
+    let cents = Reader(raw).i64()?;
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    Ok(format!("{sign}{}.{:02}", cents / 100, cents % 100))
+}
+
+/// inet 的掩码覆盖整个地址时省略，cidr 总是带掩码
+fn decode_inet(ty: &Type, raw: &[u8]) -> std::result::Result<String, BoxError> {
+    let inet = postgres_protocol::types::inet_from_sql(raw)?;
+    let full = if inet.addr().is_ipv4() { 32 } else { 128 };
+    if *ty == Type::INET && inet.netmask() == full {
+        Ok(inet.addr().to_string())
+    } else {
+        Ok(format!("{}/{}", inet.addr(), inet.netmask()))
+    }
+}
+
+/// macaddr 和 macaddr8 转为 `08:00:2b:01:02:03` 形式
+fn decode_macaddr(raw: &[u8]) -> String {
+    raw.iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// bit 和 varbit 转为 `0101` 形式
+fn decode_bit(raw: &[u8]) -> std::result::Result<String, BoxError> {
+    let bits = postgres_protocol::types::varbit_from_sql(raw)?;
+    Ok((0..bits.len())
+        .map(|i| {
+            if bits.bytes()[i / 8] & (0x80 >> (i % 8)) != 0 {
+                '1'
+            } else {
+                '0'
+            }
+        })
+        .collect())
+}
+
+/// 几何类型由 float8 坐标组成，转为与 Postgres 文本格式相同的字符串
+fn decode_geometry(ty: &Type, raw: &[u8]) -> std::result::Result<String, BoxError> {
+    let mut reader = Reader(raw);
+    let point = |reader: &mut Reader<'_>| -> std::result::Result<String, BoxError> {
+        Ok(format!("({},{})", reader.f64()?, reader.f64()?))
+    };
+    let points = |reader: &mut Reader<'_>| -> std::result::Result<String, BoxError> {
+        let count = reader.i32()?;
+        let points = (0..count)
+            .map(|_| point(reader))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(points.join(","))
+    };
+    let text = match *ty {
+        Type::POINT => point(&mut reader)?,
+        Type::LSEG => format!("[{},{}]", point(&mut reader)?, point(&mut reader)?),
+        Type::BOX => format!("{},{}", point(&mut reader)?, point(&mut reader)?),
+        Type::PATH => {
+            let closed = reader.take(1)?[0] != 0;
+            let points = points(&mut reader)?;
+            if closed {
+                format!("({points})")
+            } else {
+                format!("[{points}]")
+            }
+        }
+        Type::POLYGON => format!("({})", points(&mut reader)?),
+        Type::LINE => format!("{{{},{},{}}}", reader.f64()?, reader.f64()?, reader.f64()?),
+        Type::CIRCLE => format!("<{},{}>", point(&mut reader)?, reader.f64()?),
+        _ => return Err(format!("`{}` is not a geometric type", ty.name()).into()),
+    };
+    Ok(text)
+}
+
+/// 按大端序依次读取二进制值
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> std::result::Result<&'a [u8], BoxError> {
+        if self.0.len() < len {
+            return Err("unexpected end of value".into());
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn i32(&mut self) -> std::result::Result<i32, BoxError> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn i64(&mut self) -> std::result::Result<i64, BoxError> {
+        Ok(i64::from_be_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn f64(&mut self) -> std::result::Result<f64, BoxError> {
+        Ok(f64::from_be_bytes(self.take(8)?.try_into()?))
+    }
+}
+
+/// NaN 和无穷大无法表示为 JSON 数字，转为字符串
+fn float(value: f64) -> Value {
+    Number::from_f64(value)
+        .map(Value::Number)
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+/// 多维数组转换为嵌套的 JSON 数组
+fn decode_array(element: &Type, raw: &[u8]) -> std::result::Result<Value, BoxError> {
+    use postgres_protocol::types::array_from_sql;
+
+    let array = array_from_sql(raw)?;
+    let dimensions = array
+        .dimensions()
+        .map(|d| Ok(d.len as usize))
+        .collect::<Vec<_>>()?;
+    let values = array
+        .values()
+        .map(|v| match v {
+            Some(raw) => decode(element, raw),
+            None => Ok(Value::Null),
+        })
+        .collect::<Vec<_>>()?;
+
+    fn nest(values: &mut std::vec::IntoIter<Value>, dimensions: &[usize]) -> Value {
+        match dimensions {
+            [] => Value::Array(vec![]),
+            [len] => Value::Array(values.take(*len).collect()),
+            [len, rest @ ..] => Value::Array((0..*len).map(|_| nest(values, rest)).collect()),
+        }
+    }
+    Ok(nest(&mut values.into_iter(), &dimensions))
+}
+
+/// numeric 的二进制格式：位数、权重、符号、小数位数和以 10000 为基的各位
+fn decode_numeric(raw: &[u8]) -> std::result::Result<String, BoxError> {
+    let read = |offset: usize| -> std::result::Result<u16, BoxError> {
+        raw.get(offset..offset + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(|| "invalid numeric value".into())
+    };
+    let ndigits = read(0)? as usize;
+    let weight = read(2)? as i16 as i32;
+    let sign = read(4)?;
+    let dscale = read(6)? as usize;
+    let digits = (0..ndigits)
+        .map(|i| read(8 + i * 2))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    match sign {
+        0xC000 => return Ok("NaN".to_string()),
+        0xD000 => return Ok("Infinity".to_string()),
+        0xF000 => return Ok("-Infinity".to_string()),
+        _ => {}
+    }
+
+    let digit = |i: i32| -> u16 {
+        usize::try_from(i)
+            .ok()
+            .and_then(|i| digits.get(i))
+            .copied()
+            .unwrap_or(0)
+    };
+
+    let mut integer = String::new();
+    for i in 0..=weight {
+        if integer.is_empty() {
+            integer = digit(i).to_string();
+        } else {
+            integer.push_str(&format!("{:04}", digit(i)));
+        }
+    }
+    if integer.is_empty() || integer.chars().all(|c| c == '0') {
+        integer = "0".to_string();
+    }
+
+    let mut fraction = String::new();
+    let mut i = weight + 1;
+    while fraction.len() < dscale {
+        fraction.push_str(&format!("{:04}", digit(i)));
+        i += 1;
+    }
+    fraction.truncate(dscale);
+
+    let negative = sign == 0x4000 && digits.iter().any(|d| *d != 0);
+    let mut result = if negative {
+        "-".to_string()
+    } else {
+        String::new()
+    };
+    result.push_str(&integer);
+    if !fraction.is_empty() {
+        result.push('.');
+        result.push_str(&fraction);
+    }
+    Ok(result)
+}
+
+/// interval 的二进制格式：微秒、天、月，转为 ISO 8601 时长如 `P1Y2M3DT4H5M6.5S`
+fn decode_interval(raw: &[u8]) -> std::result::Result<String, BoxError> {
+    if raw.len() != 16 {
+        return Err("invalid interval value".into());
+    }
+    let micros = i64::from_be_bytes(raw[0..8].try_into()?);
+    let days = i32::from_be_bytes(raw[8..12].try_into()?);
+    let months = i32::from_be_bytes(raw[12..16].try_into()?);
+
+    Ok(iso8601_duration(months.into(), days.into(), micros))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numeric(weight: i16, sign: u16, dscale: u16, digits: &[u16]) -> Vec<u8> {
+        let mut raw = Vec::new();
+        raw.extend((digits.len() as u16).to_be_bytes());
+        raw.extend(weight.to_be_bytes());
+        raw.extend(sign.to_be_bytes());
+        raw.extend(dscale.to_be_bytes());
+        for digit in digits {
+            raw.extend(digit.to_be_bytes());
+        }
+        raw
+    }
+
+    #[test]
+    fn test_decode_numeric() {
+        // 12345.678
+        let raw = numeric(1, 0, 3, &[1, 2345, 6780]);
+        assert_eq!(decode_numeric(&raw).unwrap(), "12345.678");
+        // -0.0012
+        let raw = numeric(-1, 0x4000, 4, &[12]);
+        assert_eq!(decode_numeric(&raw).unwrap(), "-0.0012");
+        // 100000000 (尾部的 0 位被省略)
+        let raw = numeric(2, 0, 0, &[1]);
+        assert_eq!(decode_numeric(&raw).unwrap(), "100000000");
+        let raw = numeric(0, 0, 2, &[]);
+        assert_eq!(decode_numeric(&raw).unwrap(), "0.00");
+        let raw = numeric(0, 0xC000, 0, &[]);
+        assert_eq!(decode_numeric(&raw).unwrap(), "NaN");
+    }
+
+    #[test]
+    fn test_decode_interval() {
+        let interval = |micros: i64, days: i32, months: i32| {
+            let mut raw = Vec::new();
+            raw.extend(micros.to_be_bytes());
+            raw.extend(days.to_be_bytes());
+            raw.extend(months.to_be_bytes());
+            decode_interval(&raw).unwrap()
+        };
+        assert_eq!(
+            interval(4 * 3_600_000_000 + 5 * 60_000_000 + 6_500_000, 3, 14),
+            "P1Y2M3DT4H5M6.5S"
+        );
+        assert_eq!(interval(0, -1, 0), "P-1D");
+        assert_eq!(interval(0, 0, 0), "PT0S");
+    }
+
+    #[test]
+    fn test_decode_scalar_and_array_types() {
+        let mut buf = bytes::BytesMut::new();
+        postgres_protocol::types::array_to_sql(
+            [
+                postgres_protocol::types::ArrayDimension {
+                    len: 2,
+                    lower_bound: 1,
+                },
+                postgres_protocol::types::ArrayDimension {
+                    len: 2,
+                    lower_bound: 1,
+                },
+            ],
+            Type::INT4.oid(),
+            [Some(1), None, Some(3), Some(4)],
+            |v, buf| match v {
+                Some(v) => {
+                    postgres_protocol::types::int4_to_sql(v, buf);
+                    Ok(postgres_protocol::IsNull::No)
+                }
+                None => Ok(postgres_protocol::IsNull::Yes),
+            },
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(
+            decode(&Type::INT4_ARRAY, &buf).unwrap(),
+            serde_json::json!([[1, null], [3, 4]])
+        );
+        assert_eq!(type_name(&Type::INT4_ARRAY), "int4[]");
+
+        assert_eq!(
+            decode(&Type::BYTEA, b"\x00\xff").unwrap(),
+            Value::String("AP8=".to_string())
+        );
+        assert_eq!(
+            decode(&Type::FLOAT8, &f64::NAN.to_be_bytes()).unwrap(),
+            Value::String("NaN".to_string())
+        );
+        let uuid = uuid::Uuid::from_u128(1);
+        assert_eq!(
+            decode(&Type::UUID, uuid.as_bytes()).unwrap(),
+            Value::String(uuid.to_string())
+        );
+    }
+
+    #[test]
+    fn test_decode_types_without_json_mapping() {
+        use postgres_protocol::types as pg;
+        use postgres_protocol::IsNull;
+        use tokio_postgres::types::Field;
+
+        let encode = |f: &dyn Fn(&mut bytes::BytesMut)| {
+            let mut buf = bytes::BytesMut::new();
+            f(&mut buf);
+            buf.to_vec()
+        };
+        let text = |ty: &Type, raw: &[u8]| match decode(ty, raw).unwrap() {
+            Value::String(s) => s,
+            value => panic!("expected a string, found {value}"),
+        };
+
+        let inet =
+            |netmask| encode(&|buf| pg::inet_to_sql("10.0.0.1".parse().unwrap(), netmask, buf));
+        assert_eq!(text(&Type::INET, &inet(32)), "10.0.0.1");
+        assert_eq!(text(&Type::INET, &inet(8)), "10.0.0.1/8");
+        assert_eq!(text(&Type::CIDR, &inet(32)), "10.0.0.1/32");
+        assert_eq!(
+            text(&Type::MACADDR, &[0x08, 0x00, 0x2b, 0x01, 0x02, 0x03]),
+            "08:00:2b:01:02:03"
+        );
+        assert_eq!(text(&Type::MONEY, &(-12345i64).to_be_bytes()), "-123.45");
+        let bits = encode(&|buf| pg::varbit_to_sql(5, [0b1010_1000].into_iter(), buf).unwrap());
+        assert_eq!(text(&Type::VARBIT, &bits), "10101");
+
+        let mut timetz = (4 * 3_600_000_000i64 + 500_000).to_be_bytes().to_vec();
+        timetz.extend((-8 * 3600i32).to_be_bytes());
+        assert_eq!(text(&Type::TIMETZ, &timetz), "04:00:00.500+08:00");
+        assert_eq!(text(&Type::XML, b"<a/>"), "<a/>");
+
+        let point = encode(&|buf| pg::point_to_sql(1.5, -2.0, buf));
+        assert_eq!(text(&Type::POINT, &point), "(1.5,-2)");
+        let path = encode(&|buf| pg::path_to_sql(false, [(0.0, 0.0), (1.0, 1.0)], buf).unwrap());
+        assert_eq!(text(&Type::PATH, &path), "[(0,0),(1,1)]");
+
+        let range = encode(&|buf| {
+            pg::range_to_sql(
+                |buf| {
+                    pg::int4_to_sql(1, buf);
+                    Ok(pg::RangeBound::Inclusive(IsNull::No))
+                },
+                |_| Ok(pg::RangeBound::Unbounded),
+                buf,
+            )
+            .unwrap()
+        });
+        assert_eq!(text(&Type::INT4_RANGE, &range), "[1,)");
+        assert_eq!(
+            text(&Type::INT4_RANGE, &encode(&pg::empty_range_to_sql)),
+            "empty"
+        );
+
+        let composite = Type::new(
+            "pair".to_string(),
+            0,
+            Kind::Composite(vec![
+                Field::new("id".to_string(), Type::INT4),
+                Field::new("label".to_string(), Type::TEXT),
+            ]),
+            "public".to_string(),
+        );
+        let mut raw = 2i32.to_be_bytes().to_vec();
+        raw.extend(Type::INT4.oid().to_be_bytes());
+        raw.extend(4i32.to_be_bytes());
+        raw.extend(7i32.to_be_bytes());
+        raw.extend(Type::TEXT.oid().to_be_bytes());
+        raw.extend((-1i32).to_be_bytes());
+        assert_eq!(
+            decode(&composite, &raw).unwrap(),
+            serde_json::json!({ "id": 7, "label": null })
+        );
+
+        // 没有专门转换的类型按原始值处理
+        let custom = Type::new("custom".to_string(), 0, Kind::Simple, "public".to_string());
+        assert_eq!(text(&custom, b"a.b.c"), "a.b.c");
+        assert_eq!(text(&custom, b"\x00\xff"), "AP8=");
+    }
+
+    #[test]
+    fn test_search_path_option() {
+        assert_eq!(search_path_option("sales"), r#"-c search_path="sales""#);
+        assert_eq!(
+            search_path_option("my schema"),
+            r#"-c search_path="my\ schema""#
+        );
+    }
+
+    #[test]
+    fn test_pools_keyed_by_connection_info() {
+        let info = ConnectionInfo {
+            host: "localhost".to_string(),
+            port: 5432,
+            database: "db".to_string(),
+            user: "user".to_string(),
+            password: "secret".to_string(),
+            schema: None,
+            ssl_mode: SslMode::Disable,
+            ssl_ca: None,
+            files: Default::default(),
+            options: Default::default(),
+        };
+        let pools = PostgresPools::new(4);
+        pools.pool(&info).unwrap();
+        pools.pool(&info).unwrap();
+        assert_eq!(pools.len(), 1);
+
+        let other = ConnectionInfo {
+            schema: Some("sales".to_string()),
+            ..info.clone()
+        };
+        pools.pool(&other).unwrap();
+        assert_eq!(pools.len(), 2);
+
+        pools.close();
+        assert!(pools.is_empty());
+
+        // 超出数量上限时关闭最久未使用的连接池
+        let bounded = PostgresPools::new(4).with_max_pools(1);
+        let first = bounded.pool(&info).unwrap();
+        bounded.pool(&other).unwrap();
+        assert_eq!(bounded.len(), 1);
+        assert!(first.is_closed());
+
+        let tls = ConnectionInfo {
+            ssl_mode: SslMode::Require,
+            ..other
+        };
+        assert!(matches!(pools.pool(&tls), Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn test_database_error_keeps_sqlstate() {
+        let err = Error::Database {
+            message: "relation \"t\" does not exist".to_string(),
+            sqlstate: Some("42P01".to_string()),
+            position: Some(15),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Database error: relation \"t\" does not exist (SQLSTATE 42P01)"
+        );
+    }
+
+    #[test]
+    fn test_numeric_type_modifier() {
+        let modifier =
+            |precision: i32, scale: i32| ((precision << 16) | (scale & 0x7ff)) + VARHDRSZ;
+        assert_eq!(column_type_name(&Type::NUMERIC, -1), "numeric");
+        assert_eq!(
+            column_type_name(&Type::NUMERIC, modifier(10, 2)),
+            "numeric(10,2)"
+        );
+        assert_eq!(
+            column_type_name(&Type::NUMERIC_ARRAY, modifier(5, -2)),
+            "numeric(5,-2)[]"
+        );
+        assert_eq!(column_type_name(&Type::INT4, -1), "int4");
+        assert_eq!(column_type_name(&Type::CHAR, -1), "\"char\"");
+    }
+
+    #[test]
+    fn test_split_error_keeps_rows_before_error() {
+        let error = || Err(Error::Connector("boom".to_string()));
+        let batches: Vec<_> = split_error(vec![Ok(Value::from(1)), error()]).collect();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].as_ref().unwrap(), &vec![Value::from(1)]);
+        assert!(batches[1].is_err());
+
+        assert_eq!(split_error(vec![error()]).count(), 1);
+        assert_eq!(split_error(vec![Ok(Value::Null)]).count(), 1);
+    }
+}
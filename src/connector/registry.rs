@@ -0,0 +1,273 @@
+//! 连接器注册表 - 把 API 路径中的数据源名称路由到连接器
+//!
+//! 连接器以名称注册，同时声明改写 SQL 时使用的方言。内置连接器在 `AppState` 创建时
+//! 按启用的 feature 注册；下游 crate 可以注册自己的连接器（方言取内置数据源之一），
+//! 或以同名注册替换内置连接器。
+//!
+//! 名称大小写不敏感；没有同名连接器时按内置数据源的别名（如 `postgresql`）查找。
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::connector::trait_::Connector;
+use crate::error::{Error, Result};
+use crate::mdl::manifest::{DataSource, Manifest};
+use crate::model::ConnectionInfo;
+
+/// 创建连接器时可用的请求信息
+pub struct ConnectorContext<'a> {
+    /// 请求中的连接信息
+    pub connection_info: &'a ConnectionInfo,
+    /// 查询所用的 manifest
+    pub manifest: &'a Manifest,
+}
+
+/// 连接器工厂，为每个查询创建连接器
+///
+/// 连接池等跨请求的资源由工厂持有
+pub trait ConnectorFactory: Send + Sync {
+    /// 创建连接器
+    fn connector(&self, context: &ConnectorContext<'_>) -> Result<Box<dyn Connector>>;
+
+    /// 连接器支持的能力
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// 释放持有的资源，服务退出前调用
+    fn close(&self) {}
+}
+
+impl<T: ConnectorFactory + ?Sized> ConnectorFactory for Arc<T> {
+    fn connector(&self, context: &ConnectorContext<'_>) -> Result<Box<dyn Connector>> {
+        (**self).connector(context)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        (**self).capabilities()
+    }
+
+    fn close(&self) {
+        (**self).close()
+    }
+}
+
+/// 只使用连接信息的工厂
+struct FnFactory<F>(F);
+
+impl<F> ConnectorFactory for FnFactory<F>
+where
+    F: Fn(&ConnectionInfo) -> Result<Box<dyn Connector>> + Send + Sync,
+{
+    fn connector(&self, context: &ConnectorContext<'_>) -> Result<Box<dyn Connector>> {
+        (self.0)(context.connection_info)
+    }
+}
+
+/// 连接器的能力，由 `GET /v3/connector` 返回
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// 逐批读取结果，而不是缓冲完整结果后返回
+    pub streaming: bool,
+    /// 取消或超时时在数据库侧中止查询
+    pub cancellation: bool,
+    /// 在进程内执行，不需要外部数据库
+    pub embedded: bool,
+    /// 支持把 `connection_info.files` 中的文件注册为表
+    pub files: bool,
+    /// 支持 TLS 连接
+    pub tls: bool,
+}
+
+/// 已注册的连接器
+#[derive(Clone)]
+pub struct ConnectorEntry {
+    name: String,
+    dialect: DataSource,
+    factory: Arc<dyn ConnectorFactory>,
+}
+
+impl ConnectorEntry {
+    /// 注册的名称，小写
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 改写 SQL 时使用的方言
+    pub fn dialect(&self) -> DataSource {
+        self.dialect
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        self.factory.capabilities()
+    }
+
+    /// 创建连接器
+    pub fn connector(&self, context: &ConnectorContext<'_>) -> Result<Box<dyn Connector>> {
+        self.factory.connector(context)
+    }
+}
+
+/// 按名称索引的连接器
+#[derive(Clone, Default)]
+pub struct ConnectorRegistry {
+    connectors: BTreeMap<String, ConnectorEntry>,
+}
+
+impl ConnectorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册连接器，已有同名连接器时替换
+    pub fn register(
+        &mut self,
+        name: &str,
+        dialect: DataSource,
+        factory: impl ConnectorFactory + 'static,
+    ) -> &mut Self {
+        let name = name.to_ascii_lowercase();
+        self.connectors.insert(
+            name.clone(),
+            ConnectorEntry {
+                name,
+                dialect,
+                factory: Arc::new(factory),
+            },
+        );
+        self
+    }
+
+    /// 注册只需要连接信息的连接器，不声明任何能力
+    pub fn register_fn<F>(&mut self, name: &str, dialect: DataSource, factory: F) -> &mut Self
+    where
+        F: Fn(&ConnectionInfo) -> Result<Box<dyn Connector>> + Send + Sync + 'static,
+    {
+        self.register(name, dialect, FnFactory(factory))
+    }
+
+    /// 查找连接器，没有同名连接器时按内置数据源的别名查找
+    pub fn get(&self, name: &str) -> Option<&ConnectorEntry> {
+        let name = name.to_ascii_lowercase();
+        self.connectors.get(&name).or_else(|| {
+            let data_source = name.parse::<DataSource>().ok()?;
+            self.connectors.get(data_source.as_str())
+        })
+    }
+
+    /// 名称对应的 SQL 方言
+    ///
+    /// 未注册的内置数据源仍可规划 SQL，只是不能执行查询
+    pub fn dialect(&self, name: &str) -> Result<DataSource> {
+        match self.get(name) {
+            Some(entry) => Ok(entry.dialect),
+            None => name.parse(),
+        }
+    }
+
+    /// 创建名称对应的连接器
+    pub fn connector(
+        &self,
+        name: &str,
+        context: &ConnectorContext<'_>,
+    ) -> Result<Box<dyn Connector>> {
+        match self.get(name) {
+            Some(entry) => entry.connector(context),
+            None => Err(Error::Validation(format!(
+                "data source `{}` is not supported for query execution",
+                name.parse::<DataSource>()?
+            ))),
+        }
+    }
+
+    /// 按名称排序的所有连接器
+    pub fn iter(&self) -> impl Iterator<Item = &ConnectorEntry> {
+        self.connectors.values()
+    }
+
+    /// 关闭所有连接器持有的资源
+    pub fn close(&self) {
+        for entry in self.connectors.values() {
+            entry.factory.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::QueryResponse;
+
+    struct Echo;
+
+    #[async_trait::async_trait]
+    impl Connector for Echo {
+        async fn query(&self, _sql: &str) -> Result<QueryResponse> {
+            Ok(QueryResponse {
+                data: vec![],
+                columns: vec![],
+                truncated: false,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "echo"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routes_by_name_and_alias() {
+        let mut registry = ConnectorRegistry::new();
+        registry
+            .register_fn("Echo", DataSource::Postgres, |_| Ok(Box::new(Echo)))
+            .register_fn("postgres", DataSource::Postgres, |info| {
+                if info.options.contains_key("fail") {
+                    return Err(Error::Connector("refused".to_string()));
+                }
+                Ok(Box::new(Echo))
+            });
+        assert_eq!(
+            registry
+                .iter()
+                .map(ConnectorEntry::name)
+                .collect::<Vec<_>>(),
+            ["echo", "postgres"]
+        );
+        assert_eq!(
+            registry.get("postgresql").unwrap().name(),
+            "postgres",
+            "aliases of built-in data sources resolve to the registered name"
+        );
+        assert_eq!(registry.dialect("ECHO").unwrap(), DataSource::Postgres);
+        assert_eq!(registry.dialect("mysql").unwrap(), DataSource::MySQL);
+        assert!(registry.dialect("oracle").is_err());
+
+        let mut info: ConnectionInfo = serde_json::from_str("{}").unwrap();
+        let manifest: Manifest =
+            serde_json::from_str(r#"{ "catalog": "wren", "schema": "public" }"#).unwrap();
+        let context = ConnectorContext {
+            connection_info: &info,
+            manifest: &manifest,
+        };
+        let connector = registry.connector("echo", &context).unwrap();
+        assert_eq!(connector.query("SELECT 1").await.unwrap().data.len(), 0);
+        let err = registry.connector("mysql", &context).err().unwrap();
+        assert!(err.to_string().contains("not supported"), "{err}");
+
+        info.options.insert("fail".to_string(), "1".to_string());
+        let context = ConnectorContext {
+            connection_info: &info,
+            manifest: &manifest,
+        };
+        assert!(matches!(
+            registry.connector("postgres", &context),
+            Err(Error::Connector(_))
+        ));
+        assert_eq!(
+            registry.get("echo").unwrap().capabilities(),
+            Capabilities::default()
+        );
+    }
+}
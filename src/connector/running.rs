@@ -0,0 +1,148 @@
+//! 运行中的查询 - 登记正在执行的查询，以便按 ID 或在服务关闭时取消
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+
+/// 调用方指定的查询 ID 的最大长度
+const MAX_QUERY_ID_LEN: usize = 128;
+
+/// 正在执行的查询及其取消令牌
+#[derive(Debug, Default)]
+pub struct RunningQueries {
+    queries: Mutex<HashMap<String, CancellationToken>>,
+}
+
+impl RunningQueries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 以自动生成的 ID 登记一个查询，返回的句柄释放时自动注销
+    pub fn register(self: &Arc<Self>) -> RunningQuery {
+        let id = Uuid::new_v4().to_string();
+        let token = CancellationToken::new();
+        self.lock().insert(id.clone(), token.clone());
+        RunningQuery {
+            id,
+            token,
+            queries: Arc::clone(self),
+        }
+    }
+
+    /// 以调用方指定的 ID 登记一个查询，ID 正在使用时返回错误
+    pub fn register_with_id(self: &Arc<Self>, id: &str) -> Result<RunningQuery> {
+        if id.is_empty() || id.len() > MAX_QUERY_ID_LEN {
+            return Err(Error::Validation(format!(
+                "query id must be between 1 and {MAX_QUERY_ID_LEN} bytes"
+            )));
+        }
+        let mut queries = self.lock();
+        if queries.contains_key(id) {
+            return Err(Error::Validation(format!(
+                "query `{id}` is already running"
+            )));
+        }
+        let token = CancellationToken::new();
+        queries.insert(id.to_string(), token.clone());
+        Ok(RunningQuery {
+            id: id.to_string(),
+            token,
+            queries: Arc::clone(self),
+        })
+    }
+
+    /// 取消指定的查询，查询不存在（未登记或已结束）时返回 `false`
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.lock().get(id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 取消所有正在执行的查询，返回取消的数量
+    pub fn cancel_all(&self) -> usize {
+        let queries = self.lock();
+        for token in queries.values() {
+            token.cancel();
+        }
+        queries.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, CancellationToken>> {
+        self.queries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 已登记查询的句柄
+#[derive(Debug)]
+pub struct RunningQuery {
+    id: String,
+    token: CancellationToken,
+    queries: Arc<RunningQueries>,
+}
+
+impl RunningQuery {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// 查询被取消时触发的令牌
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+}
+
+impl Drop for RunningQuery {
+    fn drop(&mut self) {
+        self.queries.lock().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_and_cancel_all() {
+        let queries = Arc::new(RunningQueries::new());
+        let first = queries.register();
+        let second = queries.register();
+        assert_ne!(first.id(), second.id());
+        assert_eq!(queries.len(), 2);
+
+        drop(second);
+        assert_eq!(queries.len(), 1);
+
+        assert!(!queries.cancel("missing"));
+        assert!(queries.cancel(first.id()));
+        assert!(first.token().is_cancelled());
+
+        let named = queries.register_with_id("report-1").unwrap();
+        assert_eq!(named.id(), "report-1");
+        assert!(queries.register_with_id("report-1").is_err());
+        assert!(queries.register_with_id("").is_err());
+        drop(named);
+        assert!(queries.register_with_id("report-1").is_ok());
+
+        assert_eq!(queries.cancel_all(), 1);
+        assert!(first.token().is_cancelled());
+        drop(first);
+        assert!(queries.is_empty());
+    }
+}
@@ -0,0 +1,728 @@
+//! SQLite 连接器（`sqlite` feature）
+//!
+//! `database` 为 SQLite 文件路径，每个查询在阻塞线程池中以只读方式打开独立的连接，
+//! 为空或 `:memory:` 时使用空的内存数据库。文件路径须位于服务端配置放行的目录下
+//! （见 [`FileAccess`]），不接受 `file:` URI。
+//!
+//! SQLite 是动态类型的，列类型按以下顺序确定：
+//!
+//! - 结果列直接来自表列时使用表定义中声明的类型
+//! - 否则使用 manifest 中同名列声明的类型
+//! - 都没有时按第一行的值的存储类型推断，整列为 NULL 时为 `null`
+//!
+//! 声明的类型按 SQLite 的类型亲和性规则归一化为 `bigint`、`double`、`varchar`、`blob`
+//! 和 `numeric`，布尔、日期和时间类型保留，使 Arrow 等格式能够按类型转换结果。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::NaiveDateTime;
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+
+use crate::connector::file_access::FileAccess;
+use crate::connector::registry::{Capabilities, ConnectorContext, ConnectorFactory};
+use crate::connector::trait_::{Connector, QueryStream};
+use crate::error::{Error, Result};
+use crate::mdl::manifest::Manifest;
+use crate::model::{ColumnInfo, ConnectionInfo, QueryResponse};
+
+/// 内存数据库的路径
+pub const IN_MEMORY: &str = ":memory:";
+
+/// 等待写锁释放的默认时间
+const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 每批发送的行数
+const STREAM_BATCH_SIZE: usize = 1024;
+
+/// 执行线程和结果流之间缓冲的批次数
+const BATCH_BUFFER: usize = 1;
+
+/// 执行多少条虚拟机指令检查一次取消
+const CANCEL_CHECK_INTERVAL: i32 = 1000;
+
+/// manifest 中声明的列类型，按列名索引
+///
+/// 包括 model 的列和 metric 的维度、度量列；同名列声明了不同类型时无法确定，不包含该列
+pub fn manifest_column_types(manifest: &Manifest) -> HashMap<String, String> {
+    let columns = manifest
+        .models
+        .iter()
+        .flat_map(|model| model.columns.iter())
+        .chain(
+            manifest
+                .metrics
+                .iter()
+                .flat_map(|metric| metric.dimension.iter().chain(metric.measure.iter())),
+        );
+    let mut types = HashMap::new();
+    let mut conflicting = Vec::new();
+    for column in columns {
+        match types.get(&column.name) {
+            Some(existing) if existing != &column.r#type => conflicting.push(column.name.clone()),
+            Some(_) => {}
+            None => {
+                types.insert(column.name.clone(), column.r#type.clone());
+            }
+        }
+    }
+    for name in conflicting {
+        types.remove(&name);
+    }
+    types
+}
+
+/// SQLite 连接器
+pub struct SqliteConnector {
+    connection_info: ConnectionInfo,
+    busy_timeout: Duration,
+    column_types: HashMap<String, String>,
+    file_access: Arc<FileAccess>,
+}
+
+impl SqliteConnector {
+    /// 创建 SQLite 连接器，默认只能使用内存数据库
+    pub fn new(connection_info: ConnectionInfo) -> Self {
+        Self {
+            connection_info,
+            busy_timeout: DEFAULT_BUSY_TIMEOUT,
+            column_types: HashMap::new(),
+            file_access: Arc::default(),
+        }
+    }
+
+    /// 允许打开的数据库文件所在目录
+    pub fn with_file_access(mut self, file_access: Arc<FileAccess>) -> Self {
+        self.file_access = file_access;
+        self
+    }
+
+    /// 数据库被写入方锁定时等待的时间
+    pub fn with_busy_timeout(mut self, timeout: Duration) -> Self {
+        self.busy_timeout = timeout;
+        self
+    }
+
+    /// SQLite 没有给出声明类型的列使用的类型，按列名索引
+    pub fn with_column_types(mut self, column_types: HashMap<String, String>) -> Self {
+        self.column_types = column_types;
+        self
+    }
+}
+
+/// 创建 SQLite 连接器，没有声明类型的列使用 manifest 中声明的类型
+pub struct SqliteFactory {
+    busy_timeout: Duration,
+    file_access: Arc<FileAccess>,
+}
+
+impl SqliteFactory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 数据库被写入方锁定时等待的时间
+    pub fn with_busy_timeout(mut self, timeout: Duration) -> Self {
+        self.busy_timeout = timeout;
+        self
+    }
+
+    /// 设置允许打开的数据库文件所在目录，默认只能使用内存数据库
+    pub fn with_file_access(mut self, file_access: FileAccess) -> Self {
+        self.file_access = Arc::new(file_access);
+        self
+    }
+}
+
+impl Default for SqliteFactory {
+    fn default() -> Self {
+        Self {
+            busy_timeout: DEFAULT_BUSY_TIMEOUT,
+            file_access: Arc::default(),
+        }
+    }
+}
+
+impl ConnectorFactory for SqliteFactory {
+    fn connector(&self, context: &ConnectorContext<'_>) -> Result<Box<dyn Connector>> {
+        Ok(Box::new(
+            SqliteConnector::new(context.connection_info.clone())
+                .with_busy_timeout(self.busy_timeout)
+                .with_file_access(Arc::clone(&self.file_access))
+                .with_column_types(manifest_column_types(context.manifest)),
+        ))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            streaming: true,
+            cancellation: true,
+            embedded: true,
+            ..Capabilities::default()
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Connector for SqliteConnector {
+    async fn query(&self, sql: &str) -> Result<QueryResponse> {
+        self.query_with_cancel(sql, CancellationToken::new()).await
+    }
+
+    async fn query_with_cancel(
+        &self,
+        sql: &str,
+        cancel: CancellationToken,
+    ) -> Result<QueryResponse> {
+        let QueryStream { columns, batches } = self.query_stream(sql, cancel).await?;
+        let data = batches.try_concat().await?;
+        Ok(QueryResponse {
+            data,
+            columns,
+            truncated: false,
+        })
+    }
+
+    async fn query_stream(&self, sql: &str, cancel: CancellationToken) -> Result<QueryStream> {
+        let (columns_tx, columns_rx) = oneshot::channel();
+        let (batches_tx, batches_rx) = mpsc::channel(BATCH_BUFFER);
+        let connection_info = self.connection_info.clone();
+        let busy_timeout = self.busy_timeout;
+        let file_access = Arc::clone(&self.file_access);
+        let column_types = self.column_types.clone();
+        let sql = sql.to_string();
+        let task_cancel = cancel.clone();
+        tokio::task::spawn_blocking(move || {
+            match open(&connection_info, &file_access, busy_timeout) {
+                Ok(connection) => execute(
+                    &connection,
+                    &sql,
+                    &column_types,
+                    columns_tx,
+                    &batches_tx,
+                    task_cancel,
+                ),
+                Err(e) => {
+                    let _ = columns_tx.send(Err(e));
+                }
+            }
+        });
+
+        let columns = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                return Err(Error::Cancelled("query was cancelled".to_string()));
+            }
+            columns = columns_rx => columns.map_err(|_| {
+                Error::Connector("SQLite query ended without a result".to_string())
+            })??,
+        };
+        // 流被丢弃时接收端关闭，执行线程在下一批时停止
+        let batches = stream::unfold(Some((batches_rx, cancel)), |state| async move {
+            let (mut batches, cancel) = state?;
+            tokio::select! {
+                batch = batches.recv() => {
+                    let batch = batch?;
+                    let state = batch.is_ok().then_some((batches, cancel));
+                    Some((batch, state))
+                }
+                _ = cancel.cancelled() => {
+                    Some((Err(Error::Cancelled("query was cancelled".to_string())), None))
+                }
+            }
+        });
+        Ok(QueryStream {
+            columns,
+            batches: batches.boxed(),
+        })
+    }
+
+    fn name(&self) -> &str {
+        "sqlite"
+    }
+}
+
+/// 校验路径后以只读方式打开数据库，使用规范化后的路径
+fn open(
+    connection_info: &ConnectionInfo,
+    file_access: &FileAccess,
+    busy_timeout: Duration,
+) -> Result<Connection> {
+    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+    let path = connection_info.database.as_str();
+    match path {
+        "" | IN_MEMORY => Connection::open_in_memory_with_flags(flags),
+        path => Connection::open_with_flags(file_access.local_path(path)?, flags),
+    }
+    .and_then(|connection| {
+        connection.busy_timeout(busy_timeout)?;
+        Ok(connection)
+    })
+    .map_err(|e| Error::Connector(format!("failed to open SQLite database `{path}`: {e}")))
+}
+
+/// 在阻塞线程中执行查询，读到第一行后确定列类型并发送列信息，再逐批发送结果行
+///
+/// 出错时错误发给尚未收到结果的一方：列信息发出前发给 `columns`，之后发给 `batches`
+fn execute(
+    connection: &Connection,
+    sql: &str,
+    column_types: &HashMap<String, String>,
+    columns: oneshot::Sender<Result<Vec<ColumnInfo>>>,
+    batches: &mpsc::Sender<Result<Vec<Value>>>,
+    cancel: CancellationToken,
+) {
+    let mut columns = Some(columns);
+    let result = (|| -> Result<()> {
+        // 取消后中断正在执行的语句，包括产出第一行前的聚合和排序
+        let handler_cancel = cancel.clone();
+        connection.progress_handler(
+            CANCEL_CHECK_INTERVAL,
+            Some(move || handler_cancel.is_cancelled()),
+        );
+        let query_error = |e| {
+            if cancel.is_cancelled() {
+                Error::Cancelled("query was cancelled".to_string())
+            } else {
+                database_error(e)
+            }
+        };
+        let mut statement = connection.prepare(sql).map_err(query_error)?;
+        let declared = statement
+            .columns()
+            .iter()
+            .map(|column| {
+                let declared = column
+                    .decl_type()
+                    .or_else(|| column_types.get(column.name()).map(String::as_str));
+                (column.name().to_string(), declared.map(column_type))
+            })
+            .collect::<Vec<_>>();
+
+        let mut rows = statement.query([]).map_err(query_error)?;
+        let mut batch = Vec::new();
+        let first = rows.next().map_err(query_error)?;
+        let infos = declared
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, data_type))| {
+                let data_type = data_type
+                    .or_else(|| {
+                        let value = first?.get_ref(index).ok()?;
+                        storage_type(value)
+                    })
+                    .unwrap_or("null");
+                ColumnInfo {
+                    name,
+                    data_type: data_type.to_string(),
+                }
+            })
+            .collect::<Vec<_>>();
+        if let Some(row) = first {
+            batch.push(decode_row(row, &infos)?);
+        }
+        if let Some(columns) = columns.take() {
+            if columns.send(Ok(infos.clone())).is_err() {
+                return Ok(());
+            }
+        }
+
+        while let Some(row) = rows.next().map_err(query_error)? {
+            batch.push(decode_row(row, &infos)?);
+            if batch.len() >= STREAM_BATCH_SIZE
+                && batches
+                    .blocking_send(Ok(std::mem::take(&mut batch)))
+                    .is_err()
+            {
+                return Ok(());
+            }
+        }
+        if !batch.is_empty() {
+            let _ = batches.blocking_send(Ok(batch));
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        match columns {
+            Some(columns) => {
+                let _ = columns.send(Err(e));
+            }
+            None => {
+                let _ = batches.blocking_send(Err(e));
+            }
+        }
+    }
+}
+
+/// 按 SQLite 的类型亲和性规则把声明的类型归一化为连接器输出的类型名
+///
+/// 布尔、日期、时间和时间戳不属于 SQLite 的存储类型，但常用于声明列的用途，予以保留
+fn column_type(declared: &str) -> &'static str {
+    let declared = declared.trim().to_ascii_lowercase();
+    let base = declared.split('(').next().unwrap_or_default().trim();
+    match base {
+        "bool" | "boolean" => return "boolean",
+        "date" => return "date",
+        "time" => return "time",
+        "datetime" | "timestamp" => return "timestamp",
+        _ => {}
+    }
+    if declared.contains("int") {
+        "bigint"
+    } else if ["char", "clob", "text"]
+        .iter()
+        .any(|s| declared.contains(s))
+    {
+        "varchar"
+    } else if declared.is_empty() || declared.contains("blob") {
+        "blob"
+    } else if ["real", "floa", "doub"]
+        .iter()
+        .any(|s| declared.contains(s))
+    {
+        "double"
+    } else {
+        "numeric"
+    }
+}
+
+/// 值的存储类型对应的类型名，NULL 没有类型
+fn storage_type(value: ValueRef<'_>) -> Option<&'static str> {
+    match value {
+        ValueRef::Null => None,
+        ValueRef::Integer(_) => Some("bigint"),
+        ValueRef::Real(_) => Some("double"),
+        ValueRef::Text(_) => Some("varchar"),
+        ValueRef::Blob(_) => Some("blob"),
+    }
+}
+
+fn decode_row(row: &rusqlite::Row<'_>, columns: &[ColumnInfo]) -> Result<Value> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            let value = row.get_ref(index).map_err(database_error)?;
+            Ok(decode_value(value, &column.data_type))
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(Value::Array)
+}
+
+/// 按存储类型转换值，与列类型不一致的值保持原样
+///
+/// 布尔列中的整数转为布尔值，时间戳列中的文本转为 ISO 8601 格式，二进制转为 base64
+fn decode_value(value: ValueRef<'_>, data_type: &str) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) if data_type == "boolean" => Value::Bool(i != 0),
+        ValueRef::Integer(i) => Value::from(i),
+        ValueRef::Real(f) => match serde_json::Number::from_f64(f) {
+            Some(number) => Value::Number(number),
+            // SQLite 不保存 NaN，只有无穷大无法表示为 JSON 数字
+            None if f > 0.0 => Value::String("Infinity".to_string()),
+            None => Value::String("-Infinity".to_string()),
+        },
+        ValueRef::Text(text) => {
+            let text = String::from_utf8_lossy(text);
+            if data_type == "timestamp" {
+                if let Some(timestamp) = iso_timestamp(&text) {
+                    return Value::String(timestamp);
+                }
+            }
+            Value::String(text.into_owned())
+        }
+        ValueRef::Blob(bytes) => Value::String(STANDARD.encode(bytes)),
+    }
+}
+
+/// SQLite 时间函数输出的 `YYYY-MM-DD HH:MM[:SS[.SSS]]` 转为 ISO 8601 格式
+fn iso_timestamp(text: &str) -> Option<String> {
+    [
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+    .map(|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S%.f").to_string())
+}
+
+/// SQLite 错误转换为 `Error::Database`
+///
+/// SQLite 不返回 SQLSTATE，按结果码映射到 SQLSTATE 的类别：一般 SQL 错误（语法错误、
+/// 表或列不存在）为 `42000`，类型不匹配为 `22000`，约束冲突为 `23000`。
+/// 语法错误的字节偏移转换为从 1 开始的字符位置
+pub(crate) fn database_error(err: rusqlite::Error) -> Error {
+    let sqlstate = |failure: &rusqlite::ffi::Error| {
+        match failure.extended_code & 0xff {
+            rusqlite::ffi::SQLITE_ERROR => Some("42000"),
+            rusqlite::ffi::SQLITE_MISMATCH => Some("22000"),
+            rusqlite::ffi::SQLITE_CONSTRAINT => Some("23000"),
+            _ => None,
+        }
+        .map(str::to_string)
+    };
+    match &err {
+        rusqlite::Error::SqlInputError {
+            error,
+            msg,
+            sql,
+            offset,
+        } => {
+            let position = usize::try_from(*offset)
+                .ok()
+                .and_then(|offset| sql.get(..offset))
+                .and_then(|prefix| u32::try_from(prefix.chars().count() + 1).ok());
+            Error::Database {
+                message: msg.clone(),
+                sqlstate: sqlstate(error),
+                position,
+            }
+        }
+        rusqlite::Error::SqliteFailure(failure, _) => Error::Database {
+            message: err.to_string(),
+            sqlstate: sqlstate(failure),
+            position: None,
+        },
+        _ => Error::Database {
+            message: err.to_string(),
+            sqlstate: None,
+            position: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn connection_info(database: &str) -> ConnectionInfo {
+        ConnectionInfo {
+            host: String::new(),
+            port: 0,
+            database: database.to_string(),
+            user: String::new(),
+            password: String::new(),
+            schema: None,
+            ssl_mode: Default::default(),
+            ssl_ca: None,
+            files: Default::default(),
+            options: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_types() {
+        let dir = std::env::temp_dir().join(format!("mimir-sqlite-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("orders.db");
+        let setup = Connection::open(&path).unwrap();
+        setup
+            .execute_batch(
+                "CREATE TABLE orders (id INTEGER, amount DECIMAL(10,2), paid BOOLEAN, \
+                 created_at DATETIME, note VARCHAR(20), payload BLOB);\n\
+                 INSERT INTO orders VALUES (1, 10.5, 1, '2024-01-02 03:04:05', 'a', x'0102');\n\
+                 INSERT INTO orders VALUES (2, 20, 'no', NULL, 3, NULL);",
+            )
+            .unwrap();
+
+        let connector = SqliteConnector::new(connection_info(&path.to_string_lossy()))
+            .with_file_access(Arc::new(FileAccess::new().with_root(&dir)))
+            .with_column_types(HashMap::from([("total".to_string(), "double".to_string())]));
+        let response = connector
+            .query(
+                "SELECT id, amount, paid, created_at, note, payload, \
+                 amount * 2 AS total, 'x' AS label, NULL AS empty \
+                 FROM (SELECT * FROM orders) AS o ORDER BY id",
+            )
+            .await
+            .unwrap();
+        let types = response
+            .columns
+            .iter()
+            .map(|c| c.data_type.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                "bigint",
+                "numeric",
+                "boolean",
+                "timestamp",
+                "varchar",
+                "blob",
+                "double",
+                "varchar",
+                "null"
+            ]
+        );
+        // 与声明类型不一致的值保持原样
+        assert_eq!(
+            response.data,
+            vec![
+                json!([
+                    1,
+                    10.5,
+                    true,
+                    "2024-01-02T03:04:05",
+                    "a",
+                    "AQI=",
+                    21.0,
+                    "x",
+                    null
+                ]),
+                json!([2, 20, "no", null, "3", null, 40, "x", null]),
+            ]
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_paths_outside_allowed_roots() {
+        let dir = std::env::temp_dir().join(format!("mimir-sqlite-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("orders.db");
+        Connection::open(&path).unwrap();
+        let factory = SqliteFactory::new().with_file_access(FileAccess::new().with_root(&dir));
+        let manifest: Manifest =
+            serde_json::from_str(r#"{ "catalog": "wren", "schema": "public" }"#).unwrap();
+        let query = |database: String| {
+            let info = connection_info(&database);
+            let connector = factory
+                .connector(&ConnectorContext {
+                    connection_info: &info,
+                    manifest: &manifest,
+                })
+                .unwrap();
+            async move { connector.query("SELECT 1").await }
+        };
+
+        let response = query(path.to_string_lossy().into_owned()).await.unwrap();
+        assert_eq!(response.data, vec![json!([1])]);
+        // 只读打开，放行目录下不存在的文件不会被创建
+        let missing = dir.join("missing.db");
+        let err = query(missing.to_string_lossy().into_owned())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Connector(_)), "{err}");
+        assert!(!missing.exists());
+        for database in [
+            "/etc/passwd".to_string(),
+            format!("{}/../orders.db", dir.display()),
+            // 不再按 URI 解析，`file:` 开头的路径是普通的相对路径
+            format!("file:{}?mode=rwc", path.display()),
+        ] {
+            let err = query(database.clone()).await.unwrap_err();
+            assert!(matches!(err, Error::Validation(_)), "{database}: {err}");
+        }
+        // 未配置目录时不能打开任何文件
+        let err = SqliteConnector::new(connection_info(&path.to_string_lossy()))
+            .query("SELECT 1")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Validation(_)), "{err}");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let connector = SqliteConnector::new(connection_info(IN_MEMORY));
+        let err = connector.query("SELECT * FROM missing").await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Database { sqlstate: Some(ref s), .. } if s == "42000"
+        ));
+        // `FORM` 被当作列别名，错误位于 `t`
+        let err = connector.query("SELECT 1 FORM t").await.unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::Database {
+                    position: Some(15),
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+        // 只读打开，写入被拒绝
+        let err = connector
+            .query("CREATE TABLE t (id INTEGER)")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Database { .. }), "{err}");
+
+        let cancel = CancellationToken::new();
+        let task_cancel = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            task_cancel.cancel();
+        });
+        let err = connector
+            .query_with_cancel(
+                "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n) \
+                 SELECT COUNT(*) FROM n",
+                cancel,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Cancelled(_)));
+    }
+
+    #[test]
+    fn test_column_type() {
+        assert_eq!(column_type("UNSIGNED BIG INT"), "bigint");
+        assert_eq!(column_type("NVARCHAR(100)"), "varchar");
+        assert_eq!(column_type(""), "blob");
+        assert_eq!(column_type("DOUBLE PRECISION"), "double");
+        assert_eq!(column_type("decimal(10,2)"), "numeric");
+        assert_eq!(column_type("Boolean"), "boolean");
+        assert_eq!(column_type("timestamp"), "timestamp");
+    }
+
+    #[test]
+    fn test_manifest_column_types() {
+        let manifest = crate::mdl::decode_manifest(
+            &json!({
+            "catalog": "wren",
+            "schema": "public",
+            "models": [
+                {
+                    "name": "orders",
+                    "tableReference": { "table": "orders" },
+                    "columns": [
+                        { "name": "id", "type": "integer" },
+                        { "name": "status", "type": "varchar" }
+                    ]
+                },
+                {
+                    "name": "customers",
+                    "tableReference": { "table": "customers" },
+                    "columns": [
+                        { "name": "id", "type": "varchar" },
+                        { "name": "status", "type": "varchar" }
+                    ]
+                }
+            ]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let types = manifest_column_types(&manifest);
+        assert_eq!(
+            types,
+            HashMap::from([("status".to_string(), "varchar".to_string())])
+        );
+    }
+}
@@ -1,7 +1,22 @@
 //! 连接器 Trait 定义
 
-use crate::error::Result;
-use crate::model::QueryResponse;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use serde_json::Value;
+use tokio_util::sync::CancellationToken;
+
+use crate::error::{Error, Result};
+use crate::model::{ColumnInfo, QueryResponse};
+
+/// 按批产出的查询结果，每批包含若干行，每行是与 `columns` 顺序一致的数组
+pub type RowBatchStream = BoxStream<'static, Result<Vec<Value>>>;
+
+/// 流式查询结果
+pub struct QueryStream {
+    /// 列元数据
+    pub columns: Vec<ColumnInfo>,
+    /// 行批次，出错后结束；在读完前丢弃时查询随之取消
+    pub batches: RowBatchStream,
+}
 
 /// 连接器 Trait
 /// 定义数据库连接器的统一接口
@@ -10,6 +25,31 @@ pub trait Connector: Send + Sync {
     /// 执行 SQL 查询
     async fn query(&self, sql: &str) -> Result<QueryResponse>;
 
+    /// 执行 SQL 查询，`cancel` 触发时中止查询并返回 `Error::Cancelled`
+    ///
+    /// 默认只是不再等待结果，支持服务端取消的连接器应覆盖此方法
+    async fn query_with_cancel(
+        &self,
+        sql: &str,
+        cancel: CancellationToken,
+    ) -> Result<QueryResponse> {
+        tokio::select! {
+            result = self.query(sql) => result,
+            _ = cancel.cancelled() => Err(Error::Cancelled("query was cancelled".to_string())),
+        }
+    }
+
+    /// 以行批次流式执行 SQL 查询，`cancel` 触发时流以 `Error::Cancelled` 结束
+    ///
+    /// 默认缓冲完整结果后作为单个批次返回，能够逐行读取结果的连接器应覆盖此方法
+    async fn query_stream(&self, sql: &str, cancel: CancellationToken) -> Result<QueryStream> {
+        let response = self.query_with_cancel(sql, cancel).await?;
+        Ok(QueryStream {
+            columns: response.columns,
+            batches: stream::once(async move { Ok(response.data) }).boxed(),
+        })
+    }
+
     /// 获取连接器名称
     fn name(&self) -> &str;
 }
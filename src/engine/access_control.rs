@@ -0,0 +1,485 @@
+//! 访问控制 - 根据会话属性执行行级（RLAC）和列级（CLS）访问控制
+//!
+//! 参考 wren-engine 的 RLAC：条件中的 `@property` 替换为会话属性的值，
+//! 多条规则之间以 AND 连接，注入到对该 model 的每一次扫描中。
+//! CLS 用会话属性的值与策略的阈值比较，不满足时拒绝查询或将列投影为 NULL。
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{
+    visit_expressions_mut, BinaryOperator, Expr, Ident, Query, SelectItem, SetExpr, Value, Visit,
+    Visitor,
+};
+
+use crate::error::{Error, Result};
+use crate::mdl::analyzed::normalize_name;
+use crate::mdl::manifest::{
+    Column, ColumnLevelAccessControl, ColumnLevelOperator, Model, NormalizedExpr,
+    NormalizedExprType, RowLevelAccessControl, SessionProperty,
+};
+use crate::mdl::utils::{column_references, parse_expr};
+
+/// 会话属性，名称大小写不敏感
+///
+/// 调用方传入的值一律作为字符串字面量代入，避免把请求内容当作 SQL 拼接
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionProperties {
+    properties: HashMap<String, String>,
+}
+
+impl SessionProperties {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: impl AsRef<str>, value: impl Into<String>) {
+        self.properties
+            .insert(normalize_name(name.as_ref()), value.into());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.properties
+            .get(&normalize_name(name))
+            .map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.properties.is_empty()
+    }
+}
+
+impl<K: AsRef<str>, V: Into<String>> FromIterator<(K, V)> for SessionProperties {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut properties = Self::new();
+        for (name, value) in iter {
+            properties.insert(name, value);
+        }
+        properties
+    }
+}
+
+/// 列级访问控制不通过时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClsMode {
+    /// 查询用到受保护的列时拒绝整个查询
+    #[default]
+    Deny,
+    /// 将受保护的列投影为 NULL
+    Nullify,
+}
+
+/// 一条语句引用到的列名（规范化后的最后一段），用于判断查询是否用到受保护的列
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReferencedColumns {
+    names: HashSet<String>,
+    /// 存在 `*` 或 `t.*` 投影时视为引用了所有列
+    wildcard: bool,
+}
+
+impl ReferencedColumns {
+    /// 收集语句中的列引用和通配符投影
+    pub(crate) fn collect<T: Visit>(node: &T) -> Self {
+        struct Collector(ReferencedColumns);
+
+        impl Collector {
+            fn visit_set_expr(&mut self, body: &SetExpr) {
+                match body {
+                    SetExpr::Select(select) => {
+                        self.0.wildcard |= select.projection.iter().any(|item| {
+                            matches!(
+                                item,
+                                SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..)
+                            )
+                        });
+                    }
+                    SetExpr::SetOperation { left, right, .. } => {
+                        self.visit_set_expr(left);
+                        self.visit_set_expr(right);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        impl Visitor for Collector {
+            type Break = ();
+
+            fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+                self.visit_set_expr(&query.body);
+                ControlFlow::Continue(())
+            }
+
+            fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<()> {
+                for path in column_references(expr) {
+                    if let Some(name) = path.last() {
+                        self.0.names.insert(normalize_name(name));
+                    }
+                }
+                ControlFlow::Continue(())
+            }
+        }
+
+        let mut collector = Collector(Self::default());
+        let _ = node.visit(&mut collector);
+        collector.0
+    }
+
+    /// 由表达式和列名组成的引用集合，用于 metric 和派生 model 的定义
+    pub(crate) fn from_columns<'c>(columns: impl IntoIterator<Item = &'c Arc<Column>>) -> Self {
+        let mut referenced = Self::default();
+        for column in columns {
+            referenced.names.insert(normalize_name(&column.name));
+            if let Some(expr) = column
+                .expression
+                .as_deref()
+                .and_then(|e| parse_expr(e).ok())
+            {
+                referenced.names.extend(
+                    column_references(&expr)
+                        .iter()
+                        .filter_map(|path| path.last())
+                        .map(|name| normalize_name(name)),
+                );
+            }
+        }
+        referenced
+    }
+
+    pub(crate) fn insert(&mut self, name: &str) {
+        self.names.insert(normalize_name(name));
+    }
+
+    pub(crate) fn contains(&self, column: &str) -> bool {
+        self.wildcard || self.names.contains(&normalize_name(column))
+    }
+}
+
+/// 会话属性是否满足列上的访问控制策略；未配置策略时总是允许
+///
+/// 可选属性缺失且没有默认值时策略不生效
+pub(crate) fn column_access_allowed(
+    column: &Column,
+    properties: &SessionProperties,
+) -> Result<bool> {
+    let Some(policy) = &column.column_level_access_control else {
+        return Ok(true);
+    };
+    let property = policy.required_properties.first().ok_or_else(|| {
+        Error::mdl(format!(
+            "column level access control `{}` has no required property",
+            policy.name
+        ))
+    })?;
+
+    let value = match properties.get(&property.name) {
+        Some(value) => value.to_string(),
+        None => match &property.default_expr {
+            Some(default_expr) => NormalizedExpr::new(default_expr).value,
+            None if property.required => {
+                return Err(Error::AccessControl(format!(
+                    "session property `{}` is required by column level access control `{}` on column `{}`",
+                    property.name, policy.name, column.name
+                )));
+            }
+            None => return Ok(true),
+        },
+    };
+    evaluate(policy, &value)
+}
+
+/// `value <operator> threshold`，按阈值的类型决定数值比较还是字符串比较
+fn evaluate(policy: &ColumnLevelAccessControl, value: &str) -> Result<bool> {
+    let threshold = &policy.threshold;
+    let ordering = match threshold.data_type {
+        NormalizedExprType::Numeric => {
+            let parse = |v: &str| v.trim().parse::<f64>().ok();
+            let (Some(value), Some(threshold)) = (parse(value), parse(&threshold.value)) else {
+                return Err(Error::AccessControl(format!(
+                    "column level access control `{}` compares numbers, but got `{value}` and `{}`",
+                    policy.name, threshold.value
+                )));
+            };
+            value.partial_cmp(&threshold).unwrap_or(Ordering::Less)
+        }
+        NormalizedExprType::String => value.cmp(threshold.value.as_str()),
+    };
+    Ok(match policy.operator {
+        ColumnLevelOperator::Equals => ordering == Ordering::Equal,
+        ColumnLevelOperator::NotEquals => ordering != Ordering::Equal,
+        ColumnLevelOperator::GreaterThan => ordering == Ordering::Greater,
+        ColumnLevelOperator::LessThan => ordering == Ordering::Less,
+        ColumnLevelOperator::GreaterThanOrEquals => ordering != Ordering::Less,
+        ColumnLevelOperator::LessThanOrEquals => ordering != Ordering::Greater,
+    })
+}
+
+/// 行级访问控制合并后的过滤条件及生效的规则名称
+#[derive(Debug, Clone)]
+pub(crate) struct RowLevelFilter {
+    pub(crate) condition: Expr,
+    pub(crate) rules: Vec<String>,
+}
+
+impl Display for RowLevelFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.condition.fmt(f)
+    }
+}
+
+/// model 所有行级访问控制规则合并后的过滤条件；没有生效的规则时返回 `None`
+///
+/// 条件中的列引用可以用 model 名称限定，生成时去掉限定以便作用在任意别名上
+pub(crate) fn row_level_filter(
+    model: &Model,
+    properties: &SessionProperties,
+) -> Result<Option<RowLevelFilter>> {
+    let mut filter: Option<RowLevelFilter> = None;
+    for rule in &model.row_level_access_controls {
+        let Some(condition) = rule_condition(model, rule, properties)? else {
+            continue;
+        };
+        let condition = Expr::Nested(Box::new(condition));
+        filter = Some(match filter {
+            Some(mut filter) => {
+                filter.condition = Expr::BinaryOp {
+                    left: Box::new(filter.condition),
+                    op: BinaryOperator::And,
+                    right: Box::new(condition),
+                };
+                filter.rules.push(rule.name.clone());
+                filter
+            }
+            None => RowLevelFilter {
+                condition,
+                rules: vec![rule.name.clone()],
+            },
+        });
+    }
+    Ok(filter)
+}
+
+/// 单条规则代入会话属性后的条件；可选属性缺失且没有默认值时规则不生效
+fn rule_condition(
+    model: &Model,
+    rule: &RowLevelAccessControl,
+    properties: &SessionProperties,
+) -> Result<Option<Expr>> {
+    let mut values = HashMap::new();
+    for property in &rule.required_properties {
+        match property_value(property, properties)? {
+            Some(value) => {
+                values.insert(normalize_name(&property.name), value);
+            }
+            None if property.required => {
+                return Err(Error::AccessControl(format!(
+                    "session property `{}` is required by row level access control `{}` on model `{}`",
+                    property.name, rule.name, model.name
+                )));
+            }
+            None => return Ok(None),
+        }
+    }
+
+    let mut condition = parse_expr(&rule.condition).map_err(|e| {
+        Error::mdl(format!(
+            "failed to parse condition of row level access control `{}` on model `{}`: {e}",
+            rule.name, model.name
+        ))
+    })?;
+
+    let result = visit_expressions_mut(&mut condition, |expr| {
+        match expr {
+            Expr::Identifier(ident) => {
+                let Some(name) = ident.value.strip_prefix('@') else {
+                    *expr = column_ident(model, ident);
+                    return ControlFlow::Continue(());
+                };
+                let value = match values.get(&normalize_name(name)) {
+                    Some(value) => value.clone(),
+                    // 未在 required_properties 中声明的属性只能由调用方提供
+                    None => match properties.get(name) {
+                        Some(value) => string_literal(value),
+                        None => {
+                            return ControlFlow::Break(Error::AccessControl(format!(
+                                "session property `{name}` used by row level access control `{}` on model `{}` is not provided",
+                                rule.name, model.name
+                            )))
+                        }
+                    },
+                };
+                *expr = match value {
+                    value @ Expr::Value(_) => value,
+                    value => Expr::Nested(Box::new(value)),
+                };
+            }
+            Expr::CompoundIdentifier(idents)
+                if idents.len() == 2
+                    && normalize_name(&idents[0].value) == normalize_name(&model.name) =>
+            {
+                *expr = column_ident(model, &idents[1]);
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    });
+    if let ControlFlow::Break(err) = result {
+        return Err(err);
+    }
+    Ok(Some(condition))
+}
+
+/// 会话中的值优先，其次是 manifest 声明的默认表达式
+fn property_value(
+    property: &SessionProperty,
+    properties: &SessionProperties,
+) -> Result<Option<Expr>> {
+    if let Some(value) = properties.get(&property.name) {
+        return Ok(Some(string_literal(value)));
+    }
+    match &property.default_expr {
+        Some(default_expr) => parse_expr(default_expr).map(Some).map_err(|e| {
+            Error::mdl(format!(
+                "failed to parse default expression of session property `{}`: {e}",
+                property.name
+            ))
+        }),
+        None => Ok(None),
+    }
+}
+
+/// model 的列统一按声明的名称加引号引用，其他标识符保持不变
+fn column_ident(model: &Model, ident: &Ident) -> Expr {
+    match model
+        .columns
+        .iter()
+        .find(|c| normalize_name(&c.name) == normalize_name(&ident.value))
+    {
+        Some(column) => Expr::Identifier(Ident::with_quote('"', column.name.clone())),
+        None => Expr::Identifier(ident.clone()),
+    }
+}
+
+fn string_literal(value: &str) -> Expr {
+    Expr::value(Value::SingleQuotedString(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdl::decode_manifest;
+
+    const MODEL: &str = r#"{
+        "catalog": "wren",
+        "schema": "public",
+        "models": [
+            {
+                "name": "Orders",
+                "tableReference": { "table": "orders" },
+                "columns": [
+                    { "name": "region", "type": "varchar" },
+                    { "name": "o_custkey", "type": "integer" }
+                ],
+                "rowLevelAccessControls": [
+                    {
+                        "name": "region_rule",
+                        "requiredProperties": [{ "name": "Session_Region", "required": false, "defaultExpr": "'global'" }],
+                        "condition": "Orders.region = @session_region OR @session_region = 'global'"
+                    },
+                    {
+                        "name": "customer_rule",
+                        "requiredProperties": [{ "name": "session_user", "required": true }],
+                        "condition": "o_custkey = @session_user"
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    fn model() -> std::sync::Arc<Model> {
+        decode_manifest(MODEL).unwrap().models[0].clone()
+    }
+
+    #[test]
+    fn test_row_level_filter_substitutes_properties() {
+        let properties = [("SESSION_USER", "42"), ("session_region", "it's")]
+            .into_iter()
+            .collect::<SessionProperties>();
+        let filter = row_level_filter(&model(), &properties).unwrap().unwrap();
+        assert_eq!(
+            filter.to_string(),
+            r#"("region" = 'it''s' OR 'it''s' = 'global') AND ("o_custkey" = '42')"#
+        );
+        assert_eq!(filter.rules, ["region_rule", "customer_rule"]);
+    }
+
+    #[test]
+    fn test_row_level_filter_uses_default() {
+        let properties = [("session_user", "1")]
+            .into_iter()
+            .collect::<SessionProperties>();
+        let filter = row_level_filter(&model(), &properties).unwrap().unwrap();
+        assert!(
+            filter
+                .to_string()
+                .starts_with(r#"("region" = 'global' OR 'global' = 'global')"#),
+            "{filter}"
+        );
+    }
+
+    fn protected_column(operator: &str, threshold: &str) -> Column {
+        serde_json::from_str(&format!(
+            r#"{{
+                "name": "salary",
+                "type": "double",
+                "columnLevelAccessControl": {{
+                    "name": "salary_policy",
+                    "requiredProperties": [{{ "name": "level", "required": true }}],
+                    "operator": "{operator}",
+                    "threshold": "{threshold}"
+                }}
+            }}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_column_access_numeric_and_string() {
+        let level = |v: &str| [("level", v)].into_iter().collect::<SessionProperties>();
+
+        // 数值比较：10 > 9，按字符串比较则相反
+        let column = protected_column("GREATER_THAN_OR_EQUALS", "9");
+        assert!(column_access_allowed(&column, &level("10")).unwrap());
+        assert!(!column_access_allowed(&column, &level("8.5")).unwrap());
+        assert!(matches!(
+            column_access_allowed(&column, &level("high")),
+            Err(Error::AccessControl(_))
+        ));
+
+        let column = protected_column("GREATER_THAN_OR_EQUALS", "'9'");
+        assert!(!column_access_allowed(&column, &level("10")).unwrap());
+
+        let column = protected_column("EQUALS", "'admin'");
+        assert!(column_access_allowed(&column, &level("admin")).unwrap());
+        assert!(!column_access_allowed(&column, &level("guest")).unwrap());
+
+        assert!(matches!(
+            column_access_allowed(&column, &SessionProperties::new()),
+            Err(Error::AccessControl(_))
+        ));
+    }
+
+    #[test]
+    fn test_missing_required_property() {
+        let err = row_level_filter(&model(), &SessionProperties::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Access denied: session property `session_user` is required by row level access control `customer_rule` on model `Orders`"
+        );
+    }
+}
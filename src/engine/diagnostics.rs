@@ -0,0 +1,46 @@
+//! 规划诊断 - 记录一次改写中语义层实际用到的对象和访问控制
+//!
+//! 供 BI 工具向用户展示语义层对查询做了什么：展开了哪些 model / metric / view，
+//! 读取了哪些列、连接了哪些 relationship，以及生效的行级和列级访问控制。
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::mdl::lineage::ColumnRef;
+
+/// 单次改写的规划诊断，集合按名称排序以保证输出稳定
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanDiagnostics {
+    /// 被扫描的 model，包括经 relationship 连接和作为 base object 的 model
+    pub models: BTreeSet<String>,
+    /// 被展开的 metric
+    pub metrics: BTreeSet<String>,
+    /// 被展开的 view
+    pub views: BTreeSet<String>,
+    /// 查询及计算列表达式读取到的列
+    pub columns: BTreeSet<ColumnRef>,
+    /// 被连接的 relationship
+    pub relationships: BTreeSet<String>,
+    /// 注入到扫描中的行级访问控制规则
+    pub row_level_access_controls: BTreeSet<AppliedRowLevelAccessControl>,
+    /// 导致列被投影为 NULL 的列级访问控制策略
+    pub column_level_access_controls: BTreeSet<AppliedColumnLevelAccessControl>,
+}
+
+/// 生效的行级访问控制规则
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct AppliedRowLevelAccessControl {
+    pub model: String,
+    pub name: String,
+}
+
+/// 生效的列级访问控制策略
+///
+/// `protected_column` 是配置了策略的列，可能是 `column` 本身，也可能是它的上游列
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct AppliedColumnLevelAccessControl {
+    pub column: ColumnRef,
+    pub protected_column: ColumnRef,
+    pub name: String,
+}
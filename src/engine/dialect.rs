@@ -0,0 +1,147 @@
+//! SQL 方言 - 按 `DataSource` 生成目标数据库可执行的 SQL
+
+use sqlparser::dialect::GenericDialect;
+use sqlparser::tokenizer::{Token, Tokenizer};
+
+use crate::error::{Error, Result};
+use crate::mdl::manifest::{DataSource, TimeUnit};
+
+/// 目标 SQL 方言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SqlDialect {
+    data_source: DataSource,
+}
+
+impl SqlDialect {
+    pub fn new(data_source: DataSource) -> Self {
+        Self { data_source }
+    }
+
+    pub fn data_source(&self) -> DataSource {
+        self.data_source
+    }
+
+    /// 目标方言的标识符引号
+    pub fn identifier_quote(&self) -> char {
+        match self.data_source {
+            DataSource::MySQL => '`',
+            DataSource::Datafusion
+            | DataSource::Postgres
+            | DataSource::DuckDB
+            | DataSource::SQLite => '"',
+        }
+    }
+
+    /// 将时间表达式截断到指定粒度
+    pub fn date_trunc(&self, unit: &TimeUnit, expr: &str) -> String {
+        match self.data_source {
+            DataSource::MySQL => {
+                let format = match unit {
+                    TimeUnit::Year => "%Y-01-01 00:00:00",
+                    TimeUnit::Month => "%Y-%m-01 00:00:00",
+                    TimeUnit::Day => "%Y-%m-%d 00:00:00",
+                    TimeUnit::Hour => "%Y-%m-%d %H:00:00",
+                    TimeUnit::Minute => "%Y-%m-%d %H:%i:00",
+                    TimeUnit::Second => "%Y-%m-%d %H:%i:%s",
+                };
+                format!("CAST(DATE_FORMAT({expr}, '{format}') AS DATETIME)")
+            }
+            // SQLite 没有时间类型，时间以 `YYYY-MM-DD HH:MM:SS` 文本表示
+            DataSource::SQLite => {
+                let format = match unit {
+                    TimeUnit::Year => "%Y-01-01 00:00:00",
+                    TimeUnit::Month => "%Y-%m-01 00:00:00",
+                    TimeUnit::Day => "%Y-%m-%d 00:00:00",
+                    TimeUnit::Hour => "%Y-%m-%d %H:00:00",
+                    TimeUnit::Minute => "%Y-%m-%d %H:%M:00",
+                    TimeUnit::Second => "%Y-%m-%d %H:%M:%S",
+                };
+                format!("STRFTIME('{format}', {expr})")
+            }
+            DataSource::Datafusion | DataSource::Postgres | DataSource::DuckDB => {
+                let unit = match unit {
+                    TimeUnit::Year => "year",
+                    TimeUnit::Month => "month",
+                    TimeUnit::Day => "day",
+                    TimeUnit::Hour => "hour",
+                    TimeUnit::Minute => "minute",
+                    TimeUnit::Second => "second",
+                };
+                format!("DATE_TRUNC('{unit}', {expr})")
+            }
+        }
+    }
+
+    /// 将引擎内部生成的 SQL（ANSI 双引号标识符）转换为目标方言
+    pub fn unparse(&self, sql: &str) -> Result<String> {
+        let quote = self.identifier_quote();
+        if quote == '"' {
+            return Ok(sql.to_string());
+        }
+
+        // 在 token 层面替换引号，保证字符串字面量和空白不受影响
+        let tokens = Tokenizer::new(&GenericDialect, sql)
+            .with_unescape(false)
+            .tokenize()
+            .map_err(|e| Error::Planning(format!("failed to tokenize planned SQL: {e}")))?;
+
+        let mut output = String::with_capacity(sql.len());
+        for token in tokens {
+            match token {
+                Token::Word(word) if word.quote_style == Some('"') => {
+                    let value = word.value.replace("\"\"", "\"");
+                    let escaped = value.replace(quote, &format!("{quote}{quote}"));
+                    output.push(quote);
+                    output.push_str(&escaped);
+                    output.push(quote);
+                }
+                token => output.push_str(&token.to_string()),
+            }
+        }
+        Ok(output)
+    }
+}
+
+impl From<DataSource> for SqlDialect {
+    fn from(data_source: DataSource) -> Self {
+        Self::new(data_source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unparse_keeps_ansi_quotes() {
+        let dialect = SqlDialect::new(DataSource::Postgres);
+        let sql = r#"SELECT "a" FROM "t""#;
+        assert_eq!(dialect.unparse(sql).unwrap(), sql);
+    }
+
+    #[test]
+    fn test_date_trunc() {
+        assert_eq!(
+            SqlDialect::new(DataSource::Postgres).date_trunc(&TimeUnit::Month, "\"d\""),
+            "DATE_TRUNC('month', \"d\")"
+        );
+        assert_eq!(
+            SqlDialect::new(DataSource::MySQL).date_trunc(&TimeUnit::Hour, "\"d\""),
+            "CAST(DATE_FORMAT(\"d\", '%Y-%m-%d %H:00:00') AS DATETIME)"
+        );
+        assert_eq!(
+            SqlDialect::new(DataSource::SQLite).date_trunc(&TimeUnit::Day, "\"d\""),
+            "STRFTIME('%Y-%m-%d 00:00:00', \"d\")"
+        );
+    }
+
+    #[test]
+    fn test_unparse_mysql_backticks() {
+        let dialect = SqlDialect::new(DataSource::MySQL);
+        let sql = r#"SELECT "a""b", 'it''s "x"' FROM "sch"."t" WHERE "c" = 1"#;
+        assert_eq!(
+            dialect.unparse(sql).unwrap(),
+            r#"SELECT `a"b`, 'it''s "x"' FROM `sch`.`t` WHERE `c` = 1"#
+        );
+    }
+}
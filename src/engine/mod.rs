@@ -1,5 +1,12 @@
 //! 引擎层 - SQL 规划核心
 
+pub mod access_control;
+pub mod diagnostics;
+pub mod dialect;
+mod planner;
 pub mod rewriter;
 
-pub use rewriter::Rewriter;
+pub use access_control::{ClsMode, SessionProperties};
+pub use diagnostics::PlanDiagnostics;
+pub use dialect::SqlDialect;
+pub use rewriter::{Plan, Rewriter};
@@ -0,0 +1,848 @@
+//! 关系规划 - 将 MDL 对象展开为物理 SQL 子查询
+//!
+//! 参考 wren-engine 的 model_generation：每个 model 展开为两层查询，
+//! 内层从 `table_reference` / `ref_sql` / `base_object` 读取物理列，
+//! 外层在内层结果上计算计算列，并按需通过 relationship 连接其他 model。
+
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use sqlparser::ast::{visit_expressions_mut, BinaryOperator, Expr, Ident, Query, Statement};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+
+use crate::engine::access_control::{
+    column_access_allowed, row_level_filter, ClsMode, ReferencedColumns, SessionProperties,
+};
+use crate::engine::diagnostics::{
+    AppliedColumnLevelAccessControl, AppliedRowLevelAccessControl, PlanDiagnostics,
+};
+use crate::engine::dialect::SqlDialect;
+use crate::engine::rewriter::expand_relations;
+use crate::error::{Error, Result};
+use crate::mdl::analyzed::{normalize_name, AnalyzedMdl, RelationshipHop};
+use crate::mdl::lineage::ColumnRef;
+use crate::mdl::manifest::{Column, JoinType, Metric, Model, TimeGrain, TimeUnit, View};
+use crate::mdl::utils::{column_references, parse_expr, parse_statement};
+
+/// 生成带双引号的标识符，内部统一使用 ANSI 引号，输出时再按方言转换
+pub(crate) fn quote_ident(name: &str) -> String {
+    Ident::with_quote('"', name).to_string()
+}
+
+/// 解析引擎生成的查询
+pub(crate) fn parse_query(sql: &str) -> Result<Query> {
+    Parser::new(&GenericDialect)
+        .try_with_sql(sql)
+        .and_then(|mut parser| parser.parse_query())
+        .map(|query| *query)
+        .map_err(|e| Error::Planning(format!("failed to parse generated SQL `{sql}`: {e}")))
+}
+
+fn qualified(alias: &str, column: &str) -> Expr {
+    Expr::CompoundIdentifier(vec![
+        Ident::with_quote('"', alias),
+        Ident::with_quote('"', column),
+    ])
+}
+
+/// 解析时间粒度名称（大小写不敏感）
+pub(crate) fn parse_time_unit(unit: &str) -> Option<TimeUnit> {
+    match unit.to_ascii_uppercase().as_str() {
+        "YEAR" => Some(TimeUnit::Year),
+        "MONTH" => Some(TimeUnit::Month),
+        "DAY" => Some(TimeUnit::Day),
+        "HOUR" => Some(TimeUnit::Hour),
+        "MINUTE" => Some(TimeUnit::Minute),
+        "SECOND" => Some(TimeUnit::Second),
+        _ => None,
+    }
+}
+
+/// 列是否取自 `column`：同名，或表达式只引用该列（可带限定名）
+fn resolves_to_column(c: &Column, column: &str) -> bool {
+    let column = normalize_name(column);
+    if normalize_name(&c.name) == column {
+        return true;
+    }
+    match c.expression.as_deref().map(parse_expr) {
+        Some(Ok(Expr::Identifier(ident))) => normalize_name(&ident.value) == column,
+        Some(Ok(Expr::CompoundIdentifier(idents))) => idents
+            .last()
+            .is_some_and(|ident| normalize_name(&ident.value) == column),
+        _ => false,
+    }
+}
+
+/// 从 `hop.from` 经过该 relationship 到 `hop.to` 是否会产生多行
+fn is_to_many(hop: &RelationshipHop) -> bool {
+    let forward = hop
+        .relationship
+        .models
+        .first()
+        .is_some_and(|m| normalize_name(m) == normalize_name(&hop.from.name));
+    match hop.relationship.join_type {
+        JoinType::OneToOne => false,
+        JoinType::ManyToOne => !forward,
+        JoinType::OneToMany => forward,
+        JoinType::ManyToMany => true,
+    }
+}
+
+/// 单个 model 外层查询的 join 上下文
+///
+/// join 别名由关系列路径生成（如 `orders.customer.nation`），同一路径只连接一次
+#[derive(Default)]
+struct JoinScope {
+    joins: Vec<String>,
+    aliases: HashSet<String>,
+}
+
+impl JoinScope {
+    fn sql(&self) -> String {
+        self.joins.concat()
+    }
+}
+
+/// MDL 对象的关系规划器，单次改写内共享
+pub(crate) struct RelationPlanner<'a> {
+    mdl: &'a AnalyzedMdl,
+    dialect: SqlDialect,
+    properties: &'a SessionProperties,
+    cls_mode: ClsMode,
+    /// 正在展开的对象，用于检测循环引用
+    visiting: Vec<String>,
+    /// 当前语句引用到的列，栈顶为最内层正在展开的语句
+    referenced: Vec<ReferencedColumns>,
+    /// 规划过程中用到的对象和访问控制
+    diagnostics: PlanDiagnostics,
+}
+
+impl<'a> RelationPlanner<'a> {
+    pub(crate) fn new(
+        mdl: &'a AnalyzedMdl,
+        dialect: SqlDialect,
+        properties: &'a SessionProperties,
+        cls_mode: ClsMode,
+    ) -> Self {
+        Self {
+            mdl,
+            dialect,
+            properties,
+            cls_mode,
+            visiting: Vec::new(),
+            referenced: Vec::new(),
+            diagnostics: PlanDiagnostics::default(),
+        }
+    }
+
+    /// 结束规划，返回收集到的诊断信息
+    pub(crate) fn into_diagnostics(self) -> PlanDiagnostics {
+        self.diagnostics
+    }
+
+    /// 在 `referenced` 作为当前语句引用列的上下文中执行 `f`
+    pub(crate) fn with_referenced<T>(
+        &mut self,
+        referenced: ReferencedColumns,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        self.referenced.push(referenced);
+        let result = f(self);
+        self.referenced.pop();
+        result
+    }
+
+    pub(crate) fn mdl(&self) -> &'a AnalyzedMdl {
+        self.mdl
+    }
+
+    /// 按名称展开 MDL 对象（model、metric 或 view）；名称不是 MDL 对象时返回 `None`
+    pub(crate) fn object_query(&mut self, name: &str) -> Result<Option<Query>> {
+        if let Some(model) = self.mdl.model(name) {
+            let model = Arc::clone(model);
+            return self.model_query(&model).map(Some);
+        }
+        if let Some(metric) = self.mdl.metric(name) {
+            let metric = Arc::clone(metric);
+            return self.metric_query(&metric, None).map(Some);
+        }
+        if let Some(view) = self.mdl.view(name) {
+            let view = Arc::clone(view);
+            return self.view_query(&view).map(Some);
+        }
+        Ok(None)
+    }
+
+    /// 将 view 的语句内联为子查询，语句中引用的 model / metric / view 递归展开
+    pub(crate) fn view_query(&mut self, view: &Arc<View>) -> Result<Query> {
+        self.enter(&view.name)?;
+        self.diagnostics.views.insert(view.name.clone());
+        let result = self.build_view_query(view);
+        self.visiting.pop();
+        result
+    }
+
+    /// 将 model 展开为子查询，只投影 model 声明的列
+    pub(crate) fn model_query(&mut self, model: &Arc<Model>) -> Result<Query> {
+        self.enter(&model.name)?;
+        let result = self.build_model_query(model);
+        self.visiting.pop();
+        result
+    }
+
+    /// 将 metric 展开为聚合查询：按 dimension 分组，measure 作为聚合表达式
+    ///
+    /// 指定 `grain` 时额外输出按时间粒度截断后的 `ref_column`，并参与分组
+    pub(crate) fn metric_query(
+        &mut self,
+        metric: &Arc<Metric>,
+        grain: Option<(&TimeGrain, TimeUnit)>,
+    ) -> Result<Query> {
+        self.enter(&metric.name)?;
+        self.diagnostics.metrics.insert(metric.name.clone());
+        let result = self.build_metric_query(metric, grain);
+        self.visiting.pop();
+        result
+    }
+
+    /// 展开 `roll_up(metric, time_grain, unit)`
+    pub(crate) fn roll_up_query(&mut self, metric: &str, grain: &str, unit: &str) -> Result<Query> {
+        let metric = self
+            .mdl
+            .metric(metric)
+            .cloned()
+            .ok_or_else(|| Error::Planning(format!("metric `{metric}` does not exist")))?;
+        let time_grain = metric
+            .time_grain
+            .iter()
+            .find(|g| normalize_name(&g.name) == normalize_name(grain))
+            .ok_or_else(|| {
+                Error::Planning(format!(
+                    "metric `{}` has no time grain `{grain}`",
+                    metric.name
+                ))
+            })?;
+        let unit = parse_time_unit(unit)
+            .ok_or_else(|| Error::Planning(format!("unknown time unit `{unit}`")))?;
+        if !time_grain.date_parts.is_empty() && !time_grain.date_parts.contains(&unit) {
+            return Err(Error::Planning(format!(
+                "time grain `{}` of metric `{}` does not support {unit:?}",
+                time_grain.name, metric.name
+            )));
+        }
+        self.metric_query(&metric, Some((time_grain, unit)))
+    }
+
+    fn build_metric_query(
+        &mut self,
+        metric: &Arc<Metric>,
+        grain: Option<(&TimeGrain, TimeUnit)>,
+    ) -> Result<Query> {
+        if metric.measure.is_empty() {
+            return Err(Error::Planning(format!(
+                "metric `{}` has no measures",
+                metric.name
+            )));
+        }
+        let mut referenced =
+            ReferencedColumns::from_columns(metric.dimension.iter().chain(&metric.measure));
+        for time_grain in &metric.time_grain {
+            referenced.insert(&time_grain.ref_column);
+        }
+        let base = self
+            .with_referenced(referenced, |planner| {
+                planner.object_query(&metric.base_object)
+            })?
+            .ok_or_else(|| {
+                Error::Planning(format!(
+                    "base object `{}` of metric `{}` does not exist",
+                    metric.base_object, metric.name
+                ))
+            })?;
+
+        let column_expr = |c: &Column| c.expression.clone().unwrap_or_else(|| quote_ident(&c.name));
+
+        let mut items = Vec::new();
+        let mut group_by = Vec::new();
+        if let Some((time_grain, unit)) = &grain {
+            let source = metric
+                .dimension
+                .iter()
+                .find(|d| normalize_name(&d.name) == normalize_name(&time_grain.ref_column))
+                .map(|d| column_expr(d))
+                .unwrap_or_else(|| quote_ident(&time_grain.ref_column));
+            let truncated = self.dialect.date_trunc(unit, &source);
+            items.push(format!("{truncated} AS {}", quote_ident(&time_grain.name)));
+            group_by.push(truncated);
+        }
+        for dimension in &metric.dimension {
+            // 与时间粒度同名或取自粒度所引用的列的 dimension 由截断后的值代替，否则按原值
+            // 分组会使截断失效
+            if grain.as_ref().is_some_and(|(g, _)| {
+                normalize_name(&g.name) == normalize_name(&dimension.name)
+                    || resolves_to_column(dimension, &g.ref_column)
+            }) {
+                continue;
+            }
+            let expr = column_expr(dimension);
+            items.push(format!("{expr} AS {}", quote_ident(&dimension.name)));
+            group_by.push(expr);
+        }
+        for measure in &metric.measure {
+            items.push(format!(
+                "{} AS {}",
+                column_expr(measure),
+                quote_ident(&measure.name)
+            ));
+        }
+
+        let mut sql = format!(
+            "SELECT {} FROM ({base}) AS {}",
+            items.join(", "),
+            quote_ident(&metric.base_object)
+        );
+        if !group_by.is_empty() {
+            sql.push_str(&format!(" GROUP BY {}", group_by.join(", ")));
+        }
+        parse_query(&sql)
+    }
+
+    fn build_view_query(&mut self, view: &Arc<View>) -> Result<Query> {
+        let statement = parse_statement(&view.statement).map_err(|e| {
+            Error::Planning(format!(
+                "failed to parse statement of view `{}`: {e}",
+                view.name
+            ))
+        })?;
+        let Statement::Query(mut query) = statement else {
+            return Err(Error::Planning(format!(
+                "view `{}` is not a SELECT statement",
+                view.name
+            )));
+        };
+        expand_relations(self, query.as_mut())?;
+        Ok(*query)
+    }
+
+    fn enter(&mut self, name: &str) -> Result<()> {
+        let normalized = normalize_name(name);
+        if let Some(start) = self.visiting.iter().position(|v| *v == normalized) {
+            let cycle = self.visiting[start..]
+                .iter()
+                .chain(std::iter::once(&normalized))
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(Error::Planning(format!("circular reference: {cycle}")));
+        }
+        self.visiting.push(normalized);
+        Ok(())
+    }
+
+    fn build_model_query(&mut self, model: &Arc<Model>) -> Result<Query> {
+        let alias = model.name.as_str();
+        let base = self.base_query(model)?;
+
+        let mut scope = JoinScope::default();
+        let mut items = Vec::new();
+        for column in &model.columns {
+            if column.relationship.is_none() && self.is_touched(column) {
+                self.diagnostics
+                    .columns
+                    .insert(ColumnRef::new(&model.name, &column.name));
+            }
+            let name = quote_ident(&column.name);
+            if column.relationship.is_none() && self.is_masked(model, column)? {
+                items.push(format!("NULL AS {name}"));
+            } else if column.is_calculated {
+                let expr = self.calculated_expr(&mut scope, model, alias, column)?;
+                items.push(format!("{expr} AS {name}"));
+            } else if column.relationship.is_none() {
+                items.push(format!("{} AS {name}", qualified(alias, &column.name)));
+            }
+        }
+
+        parse_query(&format!(
+            "SELECT {} FROM ({base}) AS {}{}",
+            items.join(", "),
+            quote_ident(alias),
+            scope.sql()
+        ))
+    }
+
+    /// 列是否因列级访问控制被屏蔽，列本身或其依赖的任一列不满足策略即屏蔽
+    ///
+    /// 当前语句用到该列时，拒绝模式直接报错；其他情况屏蔽的列都投影为 NULL，
+    /// 避免通过未识别的引用方式读到原值
+    fn is_masked(&mut self, model: &Model, column: &Arc<Column>) -> Result<bool> {
+        let touched = self.is_touched(column);
+        let target = ColumnRef::new(&model.name, &column.name);
+        let mdl = self.mdl;
+        let upstream = mdl.lineage().upstream_columns(&target);
+        let columns =
+            std::iter::once((target.clone(), column)).chain(upstream.into_iter().filter_map(|c| {
+                let protected = mdl.column(&c.model, &c.column)?;
+                Some((c, protected))
+            }));
+
+        for (protected_column, protected) in columns {
+            let allowed = match column_access_allowed(protected, self.properties) {
+                Ok(allowed) => allowed,
+                Err(err) if touched => return Err(err),
+                Err(_) => false,
+            };
+            if allowed {
+                continue;
+            }
+            let policy = protected
+                .column_level_access_control
+                .as_ref()
+                .map(|p| p.name.clone())
+                .unwrap_or_default();
+            if touched && self.cls_mode == ClsMode::Deny {
+                return Err(Error::AccessControl(format!(
+                    "column `{target}` is protected by column level access control `{policy}`"
+                )));
+            }
+            self.diagnostics
+                .column_level_access_controls
+                .insert(AppliedColumnLevelAccessControl {
+                    column: target,
+                    protected_column,
+                    name: policy,
+                });
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// 当前语句是否用到该列；不在语句上下文中时视为用到
+    fn is_touched(&self, column: &Column) -> bool {
+        self.referenced
+            .last()
+            .map_or(true, |referenced| referenced.contains(&column.name))
+    }
+
+    /// model 的物理列查询（内层），计算列和关系列不在此层
+    ///
+    /// 直接查询和经 relationship 连接都从这里读取 model，行级访问控制在此注入
+    fn base_query(&mut self, model: &Arc<Model>) -> Result<String> {
+        let source = self.model_source(model)?;
+        let items = model
+            .columns
+            .iter()
+            .filter(|c| !c.is_calculated && c.relationship.is_none())
+            .map(|c| {
+                let name = quote_ident(&c.name);
+                let expr = c.expression.clone().unwrap_or_else(|| name.clone());
+                format!("{expr} AS {name}")
+            })
+            .collect::<Vec<_>>();
+
+        if items.is_empty() {
+            return Err(Error::Planning(format!(
+                "model `{}` has no physical columns",
+                model.name
+            )));
+        }
+        self.diagnostics.models.insert(model.name.clone());
+        let base = format!("SELECT {} FROM {source}", items.join(", "));
+        match row_level_filter(model, self.properties)? {
+            Some(filter) => {
+                self.diagnostics
+                    .row_level_access_controls
+                    .extend(
+                        filter
+                            .rules
+                            .iter()
+                            .map(|name| AppliedRowLevelAccessControl {
+                                model: model.name.clone(),
+                                name: name.clone(),
+                            }),
+                    );
+                Ok(format!(
+                    "SELECT * FROM ({base}) AS {} WHERE {filter}",
+                    quote_ident(&model.name)
+                ))
+            }
+            None => Ok(base),
+        }
+    }
+
+    /// model 的数据来源
+    fn model_source(&mut self, model: &Arc<Model>) -> Result<String> {
+        let alias = quote_ident(&model.name);
+        if let Some(table_reference) = &model.table_reference {
+            Ok(table_reference
+                .split('.')
+                .map(quote_ident)
+                .collect::<Vec<_>>()
+                .join("."))
+        } else if let Some(ref_sql) = &model.ref_sql {
+            Ok(format!("({ref_sql}) AS {alias}"))
+        } else if let Some(base_object) = &model.base_object {
+            let base = self.mdl.model(base_object).ok_or_else(|| {
+                Error::Planning(format!("base object `{base_object}` does not exist"))
+            })?;
+            let base = Arc::clone(base);
+            let referenced = ReferencedColumns::from_columns(&model.columns);
+            let query = self.with_referenced(referenced, |planner| planner.model_query(&base))?;
+            Ok(format!("({query}) AS {alias}"))
+        } else {
+            Err(Error::Planning(format!(
+                "model `{}` has no table reference, ref SQL or base object",
+                model.name
+            )))
+        }
+    }
+
+    /// 计算列表达式
+    ///
+    /// `model` 在当前 scope 中的别名为 `alias`。表达式经过一对多 relationship 时，
+    /// 整个表达式在多的一侧预聚合后再连接回来，避免扇出
+    fn calculated_expr(
+        &mut self,
+        scope: &mut JoinScope,
+        model: &Arc<Model>,
+        alias: &str,
+        column: &Column,
+    ) -> Result<Expr> {
+        let expression = column.expression.as_deref().ok_or_else(|| {
+            Error::Planning(format!(
+                "calculated column `{}.{}` has no expression",
+                model.name, column.name
+            ))
+        })?;
+        let expr = parse_expr(expression).map_err(|e| {
+            Error::Planning(format!(
+                "failed to parse expression of `{}.{}`: {e}",
+                model.name, column.name
+            ))
+        })?;
+
+        match self.to_many_chain(model, column, &expr)? {
+            Some(chain) => self.aggregated_expr(scope, model, alias, column, expr, chain),
+            None => self.plan_expr(scope, model, alias, expr),
+        }
+    }
+
+    /// 找出表达式经过的一对多 relationship 链（截止到第一个一对多的 hop）
+    ///
+    /// 表达式中所有列引用都必须经过同一条链
+    fn to_many_chain(
+        &self,
+        model: &Arc<Model>,
+        column: &Column,
+        expr: &Expr,
+    ) -> Result<Option<Vec<RelationshipHop>>> {
+        let mut chains = Vec::new();
+        for path in column_references(expr) {
+            let resolved = self
+                .mdl
+                .resolve_path(model, &path)
+                .map_err(|e| Error::Planning(e.to_string()))?;
+            let chain = resolved
+                .hops
+                .iter()
+                .position(is_to_many)
+                .map(|end| resolved.hops[..=end].to_vec());
+            chains.push((path, chain));
+        }
+
+        let Some(chain) = chains.iter().find_map(|(_, chain)| chain.clone()) else {
+            return Ok(None);
+        };
+        let names = |hops: &[RelationshipHop]| {
+            hops.iter()
+                .map(|h| normalize_name(&h.column.name))
+                .collect::<Vec<_>>()
+        };
+        let expected = names(&chain);
+        for (path, other) in &chains {
+            if other.as_deref().map(names).as_ref() != Some(&expected) {
+                return Err(Error::Planning(format!(
+                    "`{}.{}` aggregates over a to-many relationship, so `{}` must also go through `{}`",
+                    model.name,
+                    column.name,
+                    path.join("."),
+                    expected.join(".")
+                )));
+            }
+        }
+        Ok(Some(chain))
+    }
+
+    /// 在一对多 relationship 的多侧按连接键聚合，再以 LEFT JOIN 连接回当前 scope
+    fn aggregated_expr(
+        &mut self,
+        scope: &mut JoinScope,
+        model: &Arc<Model>,
+        alias: &str,
+        column: &Column,
+        mut expr: Expr,
+        chain: Vec<RelationshipHop>,
+    ) -> Result<Expr> {
+        let Some((many_hop, to_one_hops)) = chain.split_last() else {
+            return Err(Error::Planning("empty relationship chain".to_string()));
+        };
+
+        let mut from_alias = alias.to_string();
+        for hop in to_one_hops {
+            from_alias = self.join_to_one(scope, hop, &from_alias)?;
+        }
+
+        let join_alias = format!("{from_alias}.{}", column.name);
+        if scope.aliases.contains(&join_alias) {
+            return Ok(qualified(&join_alias, &column.name));
+        }
+
+        // 把列路径改写为相对多侧 model 的路径
+        let many = Arc::clone(&many_hop.to);
+        let skip = chain.len();
+        let own_name = normalize_name(&model.name);
+        let _ = visit_expressions_mut(&mut expr, |e| {
+            let idents = match e {
+                Expr::Identifier(ident) => vec![ident.clone()],
+                Expr::CompoundIdentifier(idents) => idents.clone(),
+                _ => return ControlFlow::<()>::Continue(()),
+            };
+            let mut rest = idents.as_slice();
+            if rest.len() > 1
+                && normalize_name(&rest[0].value) == own_name
+                && self.mdl.column(&model.name, &rest[0].value).is_none()
+            {
+                rest = &rest[1..];
+            }
+            let rest = rest[skip.min(rest.len())..].to_vec();
+            *e = match rest.len() {
+                1 => Expr::Identifier(rest[0].clone()),
+                _ => Expr::CompoundIdentifier(rest),
+            };
+            ControlFlow::Continue(())
+        });
+
+        let mut inner_scope = JoinScope::default();
+        let value = self.plan_expr(&mut inner_scope, &many, &many.name, expr)?;
+        let keys = self.join_keys(many_hop)?;
+
+        let key_items = keys
+            .iter()
+            .enumerate()
+            .map(|(i, (_, to))| format!("{} AS {}", qualified(&many.name, to), key_alias(i)))
+            .collect::<Vec<_>>();
+        let group_by = keys
+            .iter()
+            .map(|(_, to)| qualified(&many.name, to).to_string())
+            .collect::<Vec<_>>();
+        let on = keys
+            .iter()
+            .enumerate()
+            .map(|(i, (from, _))| {
+                format!(
+                    "{} = {}.{}",
+                    qualified(&from_alias, from),
+                    quote_ident(&join_alias),
+                    key_alias(i)
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let base = self.base_query(&many)?;
+        self.diagnostics
+            .relationships
+            .insert(many_hop.relationship.name.clone());
+        scope.aliases.insert(join_alias.clone());
+        scope.joins.push(format!(
+            " LEFT JOIN (SELECT {}, {value} AS {} FROM ({base}) AS {}{} GROUP BY {}) AS {} ON {}",
+            key_items.join(", "),
+            quote_ident(&column.name),
+            quote_ident(&many.name),
+            inner_scope.sql(),
+            group_by.join(", "),
+            quote_ident(&join_alias),
+            on.join(" AND ")
+        ));
+        Ok(qualified(&join_alias, &column.name))
+    }
+
+    /// 将表达式中的列引用改写为 scope 内的限定列，必要时添加 join
+    fn plan_expr(
+        &mut self,
+        scope: &mut JoinScope,
+        model: &Arc<Model>,
+        alias: &str,
+        mut expr: Expr,
+    ) -> Result<Expr> {
+        let flow = visit_expressions_mut(&mut expr, |e| {
+            let path = match e {
+                Expr::Identifier(ident) => vec![ident.value.clone()],
+                Expr::CompoundIdentifier(idents) => {
+                    idents.iter().map(|i| i.value.clone()).collect()
+                }
+                _ => return ControlFlow::Continue(()),
+            };
+            match self.column_reference(scope, model, alias, &path) {
+                Ok(replacement) => {
+                    *e = replacement;
+                    ControlFlow::Continue(())
+                }
+                Err(err) => ControlFlow::Break(err),
+            }
+        });
+        match flow {
+            ControlFlow::Break(err) => Err(err),
+            ControlFlow::Continue(()) => Ok(expr),
+        }
+    }
+
+    /// 表达式中对某个列路径的引用；经过的多对一 / 一对一 relationship 会被连接进 scope
+    fn column_reference(
+        &mut self,
+        scope: &mut JoinScope,
+        model: &Arc<Model>,
+        alias: &str,
+        path: &[String],
+    ) -> Result<Expr> {
+        let resolved = self
+            .mdl
+            .resolve_path(model, path)
+            .map_err(|e| Error::Planning(e.to_string()))?;
+
+        let mut current = alias.to_string();
+        for hop in &resolved.hops {
+            if is_to_many(hop) {
+                return Err(Error::Planning(format!(
+                    "`{}` crosses to-many relationship `{}`; reference it from an aggregated calculated column instead",
+                    path.join("."),
+                    hop.relationship.name
+                )));
+            }
+            current = self.join_to_one(scope, hop, &current)?;
+        }
+
+        let column = resolved.column;
+        self.diagnostics
+            .columns
+            .insert(ColumnRef::new(&resolved.model.name, &column.name));
+        if column.is_calculated {
+            let expr = self.calculated_expr(scope, &resolved.model, &current, &column)?;
+            Ok(Expr::Nested(Box::new(expr)))
+        } else if column.relationship.is_some() {
+            Err(Error::Planning(format!(
+                "relationship column `{}.{}` cannot be used as a value",
+                resolved.model.name, column.name
+            )))
+        } else {
+            Ok(qualified(&current, &column.name))
+        }
+    }
+
+    /// 沿多对一 / 一对一 relationship 连接目标 model，返回目标在 scope 中的别名
+    fn join_to_one(
+        &mut self,
+        scope: &mut JoinScope,
+        hop: &RelationshipHop,
+        from_alias: &str,
+    ) -> Result<String> {
+        let to_alias = format!("{from_alias}.{}", hop.column.name);
+        if scope.aliases.contains(&to_alias) {
+            return Ok(to_alias);
+        }
+        self.diagnostics
+            .relationships
+            .insert(hop.relationship.name.clone());
+
+        let on = self
+            .join_keys(hop)?
+            .iter()
+            .map(|(from, to)| {
+                format!(
+                    "{} = {}",
+                    qualified(from_alias, from),
+                    qualified(&to_alias, to)
+                )
+            })
+            .collect::<Vec<_>>();
+        let base = self.base_query(&hop.to)?;
+        scope.aliases.insert(to_alias.clone());
+        scope.joins.push(format!(
+            " LEFT JOIN ({base}) AS {} ON {}",
+            quote_ident(&to_alias),
+            on.join(" AND ")
+        ));
+        Ok(to_alias)
+    }
+
+    /// 从 relationship 条件中提取等值连接键 `(from 列, to 列)`
+    fn join_keys(&self, hop: &RelationshipHop) -> Result<Vec<(String, String)>> {
+        let relationship = &hop.relationship;
+        let from = normalize_name(&hop.from.name);
+        let to = normalize_name(&hop.to.name);
+        if from == to {
+            return Err(Error::Planning(format!(
+                "self-referencing relationship `{}` is not supported",
+                relationship.name
+            )));
+        }
+
+        let condition = parse_expr(&relationship.condition).map_err(|e| {
+            Error::Planning(format!(
+                "failed to parse condition of relationship `{}`: {e}",
+                relationship.name
+            ))
+        })?;
+        let invalid = || {
+            Error::Planning(format!(
+                "condition of relationship `{}` must be equalities between `{}` and `{}` columns joined by AND",
+                relationship.name, hop.from.name, hop.to.name
+            ))
+        };
+
+        let mut pending = vec![&condition];
+        let mut keys = Vec::new();
+        while let Some(expr) = pending.pop() {
+            match expr {
+                Expr::Nested(inner) => pending.push(inner),
+                Expr::BinaryOp {
+                    left,
+                    op: BinaryOperator::And,
+                    right,
+                } => {
+                    pending.push(right);
+                    pending.push(left);
+                }
+                Expr::BinaryOp {
+                    left,
+                    op: BinaryOperator::Eq,
+                    right,
+                } => {
+                    let side = |e: &Expr| match e {
+                        Expr::CompoundIdentifier(idents) if idents.len() == 2 => {
+                            Some((normalize_name(&idents[0].value), idents[1].value.clone()))
+                        }
+                        _ => None,
+                    };
+                    let (Some(l), Some(r)) = (side(left), side(right)) else {
+                        return Err(invalid());
+                    };
+                    let key = if l.0 == from && r.0 == to {
+                        (l.1, r.1)
+                    } else if l.0 == to && r.0 == from {
+                        (r.1, l.1)
+                    } else {
+                        return Err(invalid());
+                    };
+                    keys.push(key);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(keys)
+    }
+}
+
+fn key_alias(index: usize) -> String {
+    quote_ident(&format!("__key_{index}"))
+}
@@ -1,18 +1,1085 @@
 //! SQL 重写器 - 将语义 SQL 转换为实际 SQL
 
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    Expr, Ident, LimitClause, ObjectName, Query, Statement, TableAlias, TableFactor,
+    TableFunctionArgs, Value, Visit, VisitMut, VisitorMut, With,
+};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::Tokenizer;
+
+use crate::engine::access_control::{ClsMode, ReferencedColumns, SessionProperties};
+use crate::engine::diagnostics::PlanDiagnostics;
+use crate::engine::dialect::SqlDialect;
+use crate::engine::planner::{parse_query, quote_ident, RelationPlanner};
+use crate::error::{Error, Result};
+use crate::mdl::analyzed::{normalize_name, AnalyzedMdl};
+use crate::mdl::manifest::DataSource;
+use crate::mdl::utils::table_function_args;
+
+/// 改写结果：目标数据源可执行的 SQL 及规划诊断
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub sql: String,
+    pub diagnostics: PlanDiagnostics,
+}
+
 /// SQL 重写器
 /// 参考 wren-engine 的 Rewriter 类
-pub struct Rewriter;
+#[derive(Debug, Clone, Default)]
+pub struct Rewriter {
+    session_properties: SessionProperties,
+    cls_mode: ClsMode,
+    data_source: Option<DataSource>,
+    default_data_source: DataSource,
+    limit: Option<u64>,
+}
 
 impl Rewriter {
     /// 创建新的重写器
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// 指定目标数据源，覆盖 manifest 中的 `data_source`
+    pub fn with_data_source(mut self, data_source: DataSource) -> Self {
+        self.data_source = Some(data_source);
+        self
+    }
+
+    /// 指定默认数据源，未指定目标数据源且 manifest 也未声明时使用
+    pub fn with_default_data_source(mut self, data_source: DataSource) -> Self {
+        self.default_data_source = data_source;
+        self
+    }
+
+    /// 设置列级访问控制不通过时的处理方式，默认拒绝查询
+    pub fn with_cls_mode(mut self, mode: ClsMode) -> Self {
+        self.cls_mode = mode;
+        self
+    }
+
+    /// 设置会话属性，用于行级和列级访问控制
+    pub fn with_session_properties(mut self, properties: SessionProperties) -> Self {
+        self.session_properties = properties;
+        self
+    }
+
+    /// 限制改写后 SQL 最多返回的行数，已有更小的 LIMIT 时保持不变
+    pub fn with_limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// 将引用 MDL 对象的 SQL 改写为 manifest 数据源可以执行的 SQL
+    ///
+    /// `catalog.schema.model`、`schema.model` 和 `model` 形式的引用都会被替换为
+    /// 由 model / metric / view 定义展开的子查询；除 CTE 外，不是 MDL 对象的表引用
+    /// 和 `roll_up` 以外的表函数都会被拒绝
+    pub fn rewrite(&self, mdl: &AnalyzedMdl, sql: &str) -> Result<String> {
+        self.plan(mdl, sql).map(|plan| plan.sql)
+    }
+
+    /// 与 [`Rewriter::rewrite`] 相同，同时返回规划中用到的 MDL 对象和访问控制
+    pub fn plan(&self, mdl: &AnalyzedMdl, sql: &str) -> Result<Plan> {
+        let mut statements = parse_sql(sql)?;
+        if statements.len() != 1 {
+            return Err(Error::Planning(format!(
+                "expected exactly one statement, found {}",
+                statements.len()
+            )));
+        }
+        let mut statement = statements.remove(0);
+        if !matches!(statement, Statement::Query(_)) {
+            return Err(Error::Planning(
+                "only SELECT statements are supported".to_string(),
+            ));
+        }
+
+        let data_source = self
+            .data_source
+            .or(mdl.manifest().data_source)
+            .unwrap_or(self.default_data_source);
+        let dialect = SqlDialect::new(data_source);
+        let mut planner =
+            RelationPlanner::new(mdl, dialect, &self.session_properties, self.cls_mode);
+        expand_relations(&mut planner, &mut statement)?;
+        if let (Some(limit), Statement::Query(query)) = (self.limit, &mut statement) {
+            apply_limit(query, limit)?;
+        }
+
+        Ok(Plan {
+            sql: dialect.unparse(&statement.to_string())?,
+            diagnostics: planner.into_diagnostics(),
+        })
+    }
+}
+
+/// 把语句中对 MDL 对象的引用展开为子查询，view 的语句也通过这里递归展开
+pub(crate) fn expand_relations<T: Visit + VisitMut>(
+    planner: &mut RelationPlanner<'_>,
+    node: &mut T,
+) -> Result<()> {
+    planner.with_referenced(ReferencedColumns::collect(node), |planner| {
+        let mut expander = ModelExpander {
+            planner,
+            scopes: Vec::new(),
+            withs: Vec::new(),
+        };
+        match VisitMut::visit(node, &mut expander) {
+            ControlFlow::Break(err) => Err(err),
+            ControlFlow::Continue(()) => Ok(()),
+        }
+    })
+}
+
+/// 把对 MDL 对象的表引用替换为展开后的子查询
+struct ModelExpander<'p, 'a> {
+    planner: &'p mut RelationPlanner<'a>,
+    /// 当前位置可见的 CTE 名称，每层查询一个作用域，同名时 CTE 优先于 MDL 对象
+    scopes: Vec<HashSet<String>>,
+    /// 正在遍历的各层查询暂时取出的 WITH 子句，离开查询时放回
+    withs: Vec<Option<With>>,
+}
+
+impl ModelExpander<'_, '_> {
+    fn is_cte(&self, name: &str) -> bool {
+        let name = normalize_name(name);
+        self.scopes.iter().any(|scope| scope.contains(&name))
+    }
+
+    /// 按作用域展开 WITH 子句中的 CTE
+    ///
+    /// 非递归 CTE 只能引用排在它之前的 CTE，其自身名称在定义中仍是普通表引用；
+    /// `WITH RECURSIVE` 中的 CTE 可以引用同一子句中的所有 CTE
+    fn expand_ctes(&mut self, with: &mut With) -> ControlFlow<Error> {
+        let names = with
+            .cte_tables
+            .iter()
+            .map(|cte| normalize_name(&cte.alias.name.value))
+            .collect::<Vec<_>>();
+        for (index, cte) in with.cte_tables.iter_mut().enumerate() {
+            let visible = if with.recursive {
+                &names[..]
+            } else {
+                &names[..index]
+            };
+            self.scopes.push(visible.iter().cloned().collect());
+            let result = VisitMut::visit(cte.query.as_mut(), self);
+            self.scopes.pop();
+            result?;
+        }
+        ControlFlow::Continue(())
     }
+
+    /// 查找表引用对应的 MDL 对象并展开；引用 CTE 时返回 `None`
+    ///
+    /// 其他表引用一律拒绝，否则可以绕过 model 直接查询物理表，跳过行级和列级访问控制
+    fn expand(&mut self, name: &ObjectName) -> Result<Option<Query>> {
+        let parts = object_name_parts(name)?;
+
+        let mdl = self.planner.mdl();
+        let manifest = mdl.manifest();
+        let matches = |a: &str, b: &str| normalize_name(a) == normalize_name(b);
+        let object = match parts.as_slice() {
+            [object] if self.is_cte(object) => return Ok(None),
+            [object] => Some(object),
+            [schema, object] if matches(schema, &manifest.schema) => Some(object),
+            [catalog, schema, object]
+                if matches(catalog, &manifest.catalog) && matches(schema, &manifest.schema) =>
+            {
+                Some(object)
+            }
+            _ => None,
+        };
+
+        let query = match object {
+            Some(object) => self.planner.object_query(object)?,
+            None => None,
+        };
+        query
+            .map(Some)
+            .ok_or_else(|| Error::Planning(format!("`{name}` is not defined in the MDL")))
+    }
+
+    /// 展开 `roll_up(metric, time_grain, unit)` 表函数，返回查询和默认别名
+    fn expand_roll_up(&mut self, args: &TableFunctionArgs) -> Result<(Query, Ident)> {
+        let args = table_function_args(args);
+        let Some([metric, grain, unit]) = args.as_deref() else {
+            return Err(Error::Planning(
+                "roll_up expects (metric, time_grain, unit) arguments".to_string(),
+            ));
+        };
+
+        let query = self
+            .planner
+            .roll_up_query(&metric.value, &grain.value, &unit.value)?;
+        Ok((query, metric.clone()))
+    }
+}
+
+fn object_name_parts(name: &ObjectName) -> Result<Vec<String>> {
+    name.0
+        .iter()
+        .map(|part| {
+            part.as_ident()
+                .map(|i| i.value.clone())
+                .ok_or_else(|| Error::Planning(format!("unsupported table reference `{name}`")))
+        })
+        .collect()
 }
 
-impl Default for Rewriter {
-    fn default() -> Self {
-        Self::new()
+impl VisitorMut for ModelExpander<'_, '_> {
+    type Break = Error;
+
+    // WITH 子句在这里单独展开，随后的默认遍历只经过查询主体，CTE 名称只在主体中可见
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<Error> {
+        let mut with = query.with.take();
+        if let Some(with) = &mut with {
+            self.expand_ctes(with)?;
+        }
+        let names = with
+            .iter()
+            .flat_map(|with| &with.cte_tables)
+            .map(|cte| normalize_name(&cte.alias.name.value))
+            .collect();
+        self.scopes.push(names);
+        self.withs.push(with);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, query: &mut Query) -> ControlFlow<Error> {
+        self.scopes.pop();
+        query.with = self.withs.pop().flatten();
+        ControlFlow::Continue(())
+    }
+
+    // 在 post 阶段替换，避免继续遍历新生成的子查询
+    fn post_visit_table_factor(&mut self, table_factor: &mut TableFactor) -> ControlFlow<Error> {
+        let (name, alias, args) = match table_factor {
+            TableFactor::Table {
+                name, alias, args, ..
+            } => (name, alias, args),
+            // 表函数可以读取任意物理表或文件
+            TableFactor::Function { name, .. } => {
+                return ControlFlow::Break(unsupported_table_function(name));
+            }
+            TableFactor::TableFunction { expr, .. } => {
+                return ControlFlow::Break(Error::Planning(format!(
+                    "table function `{expr}` is not supported"
+                )));
+            }
+            _ => return ControlFlow::Continue(()),
+        };
+
+        let expanded = match args {
+            None => self.expand(name).map(|query| {
+                query.map(|query| {
+                    let default_alias = name.0.last().and_then(|part| part.as_ident()).cloned();
+                    (query, default_alias)
+                })
+            }),
+            Some(args) if is_roll_up(name) => self
+                .expand_roll_up(args)
+                .map(|(query, alias)| Some((query, Some(alias)))),
+            Some(_) => Err(unsupported_table_function(name)),
+        };
+
+        match expanded {
+            Ok(Some((subquery, default_alias))) => {
+                let alias = alias.clone().or_else(|| {
+                    default_alias.map(|name| TableAlias {
+                        name,
+                        columns: vec![],
+                    })
+                });
+                *table_factor = TableFactor::Derived {
+                    lateral: false,
+                    subquery: Box::new(subquery),
+                    alias,
+                };
+                ControlFlow::Continue(())
+            }
+            Ok(None) => ControlFlow::Continue(()),
+            Err(err) => ControlFlow::Break(err),
+        }
+    }
+}
+
+/// 在最外层查询上施加行数上限
+///
+/// 没有 LIMIT 时直接添加，已有常量 LIMIT 时取较小值；
+/// FETCH、非常量 LIMIT 等其他写法包一层子查询后再限制
+fn apply_limit(query: &mut Query, limit: u64) -> Result<()> {
+    let literal = |n: u64| Expr::value(Value::Number(n.to_string(), false));
+    if query.fetch.is_none() {
+        match &mut query.limit_clause {
+            None => {
+                query.limit_clause = Some(LimitClause::LimitOffset {
+                    limit: Some(literal(limit)),
+                    offset: None,
+                    limit_by: vec![],
+                });
+                return Ok(());
+            }
+            Some(LimitClause::LimitOffset {
+                limit: existing,
+                limit_by,
+                ..
+            }) if limit_by.is_empty() => match existing {
+                None => {
+                    *existing = Some(literal(limit));
+                    return Ok(());
+                }
+                Some(Expr::Value(value)) => {
+                    if let Value::Number(n, _) = &value.value {
+                        if let Ok(n) = n.parse::<u64>() {
+                            *existing = Some(literal(n.min(limit)));
+                            return Ok(());
+                        }
+                    }
+                }
+                Some(_) => {}
+            },
+            Some(_) => {}
+        }
+    }
+    *query = parse_query(&format!(
+        "SELECT * FROM ({query}) AS {} LIMIT {limit}",
+        quote_ident("__limited")
+    ))?;
+    Ok(())
+}
+
+/// 解析 SQL，语法错误带上出错位置的行列号
+fn parse_sql(sql: &str) -> Result<Vec<Statement>> {
+    let dialect = GenericDialect;
+    let tokens = Tokenizer::new(&dialect, sql)
+        .tokenize_with_location()
+        .map_err(|e| Error::SqlSyntax {
+            message: e.message,
+            line: Some(e.location.line),
+            column: Some(e.location.column),
+        })?;
+    Parser::new(&dialect)
+        .with_tokens_with_locations(tokens)
+        .parse_statements()
+        .map_err(|e| {
+            let message = match e {
+                ParserError::TokenizerError(message) | ParserError::ParserError(message) => message,
+                ParserError::RecursionLimitExceeded => "recursion limit exceeded".to_string(),
+            };
+            // sqlparser 的解析错误没有结构化的位置，只在信息末尾追加 ` at Line: 1, Column: 8`；
+            // 出错的 token 可能已被解析器消费，也不能从解析器的当前位置得到，
+            // 所以从信息中取出位置，并从返回的信息中去掉
+            match split_location(&message) {
+                Some((text, line, column)) => Error::SqlSyntax {
+                    message: text.to_string(),
+                    line: Some(line),
+                    column: Some(column),
+                },
+                None => Error::SqlSyntax {
+                    message,
+                    line: None,
+                    column: None,
+                },
+            }
+        })
+}
+
+/// 把 `... at Line: 1, Column: 8` 拆成信息和行列号
+fn split_location(message: &str) -> Option<(&str, u64, u64)> {
+    let (text, location) = message.rsplit_once(" at Line: ")?;
+    let (line, column) = location.split_once(", Column: ")?;
+    Some((text, line.parse().ok()?, column.parse().ok()?))
+}
+
+/// 只支持 `roll_up` 表函数
+fn unsupported_table_function(name: &ObjectName) -> Error {
+    Error::Planning(format!("table function `{name}` is not supported"))
+}
+
+fn is_roll_up(name: &ObjectName) -> bool {
+    matches!(name.0.as_slice(), [part] if part.as_ident().is_some_and(|i| normalize_name(&i.value) == "roll_up"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::diagnostics::{
+        AppliedColumnLevelAccessControl, AppliedRowLevelAccessControl,
+    };
+    use crate::mdl::decode_manifest;
+    use crate::mdl::lineage::ColumnRef;
+    use std::collections::BTreeSet;
+    use std::sync::Arc;
+
+    const MANIFEST: &str = r#"{
+        "catalog": "wren",
+        "schema": "public",
+        "dataSource": "POSTGRES",
+        "models": [
+            {
+                "name": "orders",
+                "tableReference": { "schema": "tpch", "table": "orders" },
+                "columns": [
+                    { "name": "o_orderkey", "type": "integer" },
+                    { "name": "o_totalprice", "type": "double", "expression": "o_price * o_qty" },
+                    { "name": "doubled", "type": "double", "isCalculated": true, "expression": "o_totalprice * 2" },
+                    { "name": "quadrupled", "type": "double", "isCalculated": true, "expression": "doubled * 2" }
+                ]
+            },
+            {
+                "name": "big_orders",
+                "refSql": "SELECT * FROM tpch.orders WHERE o_totalprice > 1000",
+                "columns": [{ "name": "o_orderkey", "type": "integer" }]
+            },
+            {
+                "name": "order_keys",
+                "baseObject": "orders",
+                "columns": [{ "name": "key", "type": "integer", "expression": "o_orderkey" }]
+            }
+        ]
+    }"#;
+
+    fn rewrite(manifest: &str, sql: &str) -> Result<String> {
+        let mdl = AnalyzedMdl::analyze(Arc::new(decode_manifest(manifest).unwrap())).unwrap();
+        Rewriter::new().rewrite(&mdl, sql)
+    }
+
+    #[test]
+    fn test_rewrite_table_reference_model() {
+        assert_eq!(
+            rewrite(
+                MANIFEST,
+                "SELECT o_orderkey, quadrupled FROM wren.public.orders"
+            )
+            .unwrap(),
+            r#"SELECT o_orderkey, quadrupled FROM (SELECT "orders"."o_orderkey" AS "o_orderkey", "orders"."o_totalprice" AS "o_totalprice", "orders"."o_totalprice" * 2 AS "doubled", ("orders"."o_totalprice" * 2) * 2 AS "quadrupled" FROM (SELECT "o_orderkey" AS "o_orderkey", o_price * o_qty AS "o_totalprice" FROM "tpch"."orders") AS "orders") AS orders"#
+        );
+    }
+
+    #[test]
+    fn test_rewrite_ref_sql_and_base_object() {
+        assert_eq!(
+            rewrite(MANIFEST, "SELECT * FROM big_orders b").unwrap(),
+            r#"SELECT * FROM (SELECT "big_orders"."o_orderkey" AS "o_orderkey" FROM (SELECT "o_orderkey" AS "o_orderkey" FROM (SELECT * FROM tpch.orders WHERE o_totalprice > 1000) AS "big_orders") AS "big_orders") AS b"#
+        );
+
+        let sql = rewrite(MANIFEST, "SELECT key FROM public.order_keys").unwrap();
+        assert!(sql.starts_with(r#"SELECT key FROM (SELECT "order_keys"."key" AS "key" FROM (SELECT o_orderkey AS "key" FROM (SELECT "orders"."o_orderkey""#));
+        assert!(sql.ends_with(r#"AS "order_keys") AS order_keys"#), "{sql}");
+    }
+
+    #[test]
+    fn test_rewrite_keeps_ctes_and_rejects_unknown_tables() {
+        let sql = "WITH orders AS (SELECT 1 AS o_orderkey) SELECT * FROM orders";
+        assert_eq!(rewrite(MANIFEST, sql).unwrap(), sql);
+
+        for sql in [
+            "WITH orders AS (SELECT 1 AS o_orderkey) SELECT * FROM orders JOIN other.t ON true",
+            "SELECT * FROM customers",
+            "SELECT * FROM tpch.orders",
+            "SELECT * FROM other.public.orders",
+            "SELECT * FROM big_orders WHERE o_orderkey IN (SELECT o_orderkey FROM tpch.orders)",
+            "SELECT * FROM read_csv('/etc/passwd')",
+            "SELECT * FROM orders, LATERAL generate_series(1, 2)",
+        ] {
+            let err = rewrite(MANIFEST, sql).unwrap_err();
+            assert!(matches!(err, Error::Planning(_)), "{sql}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_rewrite_scopes_ctes() {
+        // 非递归 CTE 的定义中，自身名称仍引用 MDL 对象
+        let sql = rewrite(
+            MANIFEST,
+            "WITH orders AS (SELECT * FROM orders) SELECT * FROM orders",
+        )
+        .unwrap();
+        assert!(
+            sql.starts_with(r#"WITH orders AS (SELECT * FROM (SELECT "orders"."o_orderkey""#),
+            "{sql}"
+        );
+        assert!(sql.ends_with(") AS orders) SELECT * FROM orders"), "{sql}");
+
+        // 后面的 CTE 可以引用前面的 CTE，反过来不行
+        let sql = "WITH a AS (SELECT 1 AS x), b AS (SELECT * FROM a) SELECT * FROM b";
+        assert_eq!(rewrite(MANIFEST, sql).unwrap(), sql);
+
+        for sql in [
+            "WITH secret AS (SELECT * FROM secret) SELECT * FROM secret",
+            "WITH a AS (SELECT * FROM b), b AS (SELECT 1 AS x) SELECT * FROM a",
+            // 嵌套查询中的 CTE 不影响外层的同名引用
+            "SELECT * FROM (WITH secret AS (SELECT 1) SELECT * FROM secret) t, secret",
+        ] {
+            let err = rewrite(MANIFEST, sql).unwrap_err();
+            assert!(
+                err.to_string().contains("is not defined in the MDL"),
+                "{sql}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rewrite_mysql_dialect() {
+        let manifest = MANIFEST.replace("POSTGRES", "MYSQL");
+        let sql = rewrite(&manifest, "SELECT o_orderkey FROM big_orders").unwrap();
+        assert!(
+            sql.contains("`big_orders`.`o_orderkey` AS `o_orderkey`"),
+            "{sql}"
+        );
+        assert!(!sql.contains('"'), "{sql}");
+
+        let mdl = AnalyzedMdl::analyze(Arc::new(decode_manifest(MANIFEST).unwrap())).unwrap();
+        let sql = Rewriter::new()
+            .with_data_source(DataSource::MySQL)
+            .rewrite(&mdl, "SELECT o_orderkey FROM big_orders")
+            .unwrap();
+        assert!(!sql.contains('"'), "{sql}");
+
+        // 默认数据源只在请求和 manifest 都未指定时使用
+        let sql = Rewriter::new()
+            .with_default_data_source(DataSource::MySQL)
+            .rewrite(&mdl, "SELECT o_orderkey FROM big_orders")
+            .unwrap();
+        assert!(sql.contains('"'), "{sql}");
+        let undeclared = MANIFEST.replace(r#""dataSource": "POSTGRES","#, "");
+        let mdl = AnalyzedMdl::analyze(Arc::new(decode_manifest(&undeclared).unwrap())).unwrap();
+        let sql = Rewriter::new()
+            .with_default_data_source(DataSource::MySQL)
+            .rewrite(&mdl, "SELECT o_orderkey FROM big_orders")
+            .unwrap();
+        assert!(!sql.contains('"'), "{sql}");
+    }
+
+    const RELATIONSHIPS: &str = r#"{
+        "catalog": "wren",
+        "schema": "public",
+        "models": [
+            {
+                "name": "orders",
+                "tableReference": { "table": "orders" },
+                "columns": [
+                    { "name": "o_orderkey", "type": "integer" },
+                    { "name": "o_custkey", "type": "integer" },
+                    { "name": "customer", "type": "customer", "relationship": "orders_customer" },
+                    { "name": "lineitems", "type": "lineitem", "relationship": "orders_lineitem" },
+                    { "name": "customer_name", "type": "varchar", "isCalculated": true, "expression": "customer.c_name" },
+                    { "name": "nation_name", "type": "varchar", "isCalculated": true, "expression": "upper(customer.nation.n_name)" },
+                    { "name": "revenue", "type": "double", "isCalculated": true, "expression": "sum(lineitems.l_price)" }
+                ]
+            },
+            {
+                "name": "customer",
+                "tableReference": { "table": "customer" },
+                "columns": [
+                    { "name": "c_custkey", "type": "integer" },
+                    { "name": "c_name", "type": "varchar" },
+                    { "name": "c_nationkey", "type": "integer" },
+                    { "name": "nation", "type": "nation", "relationship": "customer_nation" }
+                ]
+            },
+            {
+                "name": "nation",
+                "tableReference": { "table": "nation" },
+                "columns": [
+                    { "name": "n_nationkey", "type": "integer" },
+                    { "name": "n_name", "type": "varchar" }
+                ]
+            },
+            {
+                "name": "lineitem",
+                "tableReference": { "table": "lineitem" },
+                "columns": [
+                    { "name": "l_orderkey", "type": "integer" },
+                    { "name": "l_price", "type": "double" }
+                ]
+            }
+        ],
+        "relationships": [
+            { "name": "orders_customer", "models": ["orders", "customer"], "joinType": "MANY_TO_ONE", "condition": "orders.o_custkey = customer.c_custkey" },
+            { "name": "customer_nation", "models": ["customer", "nation"], "joinType": "MANY_TO_ONE", "condition": "customer.c_nationkey = nation.n_nationkey" },
+            { "name": "orders_lineitem", "models": ["orders", "lineitem"], "joinType": "ONE_TO_MANY", "condition": "orders.o_orderkey = lineitem.l_orderkey" }
+        ]
+    }"#;
+
+    #[test]
+    fn test_rewrite_multi_hop_to_one_relationships() {
+        let manifest = RELATIONSHIPS.replace(
+            r#"{ "name": "revenue", "type": "double", "isCalculated": true, "expression": "sum(lineitems.l_price)" }"#,
+            r#"{ "name": "o_total", "type": "double" }"#,
+        );
+        assert_eq!(
+            rewrite(&manifest, "SELECT customer_name, nation_name FROM orders").unwrap(),
+            concat!(
+                r#"SELECT customer_name, nation_name FROM (SELECT "orders"."o_orderkey" AS "o_orderkey", "orders"."o_custkey" AS "o_custkey", "#,
+                r#""orders.customer"."c_name" AS "customer_name", upper("orders.customer.nation"."n_name") AS "nation_name", "#,
+                r#""orders"."o_total" AS "o_total" "#,
+                r#"FROM (SELECT "o_orderkey" AS "o_orderkey", "o_custkey" AS "o_custkey", "o_total" AS "o_total" FROM "orders") AS "orders" "#,
+                r#"LEFT JOIN (SELECT "c_custkey" AS "c_custkey", "c_name" AS "c_name", "c_nationkey" AS "c_nationkey" FROM "customer") AS "orders.customer" "#,
+                r#"ON "orders"."o_custkey" = "orders.customer"."c_custkey" "#,
+                r#"LEFT JOIN (SELECT "n_nationkey" AS "n_nationkey", "n_name" AS "n_name" FROM "nation") AS "orders.customer.nation" "#,
+                r#"ON "orders.customer"."c_nationkey" = "orders.customer.nation"."n_nationkey") AS orders"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_rewrite_one_to_many_pre_aggregates() {
+        let sql = rewrite(RELATIONSHIPS, "SELECT revenue FROM orders").unwrap();
+        assert!(
+            sql.contains(concat!(
+                r#"LEFT JOIN (SELECT "lineitem"."l_orderkey" AS "__key_0", sum("lineitem"."l_price") AS "revenue" "#,
+                r#"FROM (SELECT "l_orderkey" AS "l_orderkey", "l_price" AS "l_price" FROM "lineitem") AS "lineitem" "#,
+                r#"GROUP BY "lineitem"."l_orderkey") AS "orders.revenue" "#,
+                r#"ON "orders"."o_orderkey" = "orders.revenue"."__key_0""#
+            )),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(r#""orders.revenue"."revenue" AS "revenue""#),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_rejects_mixed_to_many_expression() {
+        let manifest = RELATIONSHIPS.replace(
+            "sum(lineitems.l_price)",
+            "sum(lineitems.l_price) + o_orderkey",
+        );
+        let err = rewrite(&manifest, "SELECT revenue FROM orders").unwrap_err();
+        assert!(
+            err.to_string().contains("must also go through `lineitems`"),
+            "{err}"
+        );
+    }
+
+    const METRICS: &str = r#"{
+        "catalog": "wren",
+        "schema": "public",
+        "dataSource": "POSTGRES",
+        "models": [
+            {
+                "name": "orders",
+                "tableReference": { "table": "orders" },
+                "columns": [
+                    { "name": "o_custkey", "type": "integer" },
+                    { "name": "o_orderdate", "type": "timestamp" },
+                    { "name": "o_totalprice", "type": "double" }
+                ]
+            }
+        ],
+        "metrics": [
+            {
+                "name": "revenue",
+                "baseObject": "orders",
+                "dimension": [{ "name": "customer", "type": "integer", "expression": "o_custkey" }],
+                "measure": [{ "name": "total", "type": "double", "expression": "sum(o_totalprice)" }],
+                "timeGrain": [{ "name": "order_date", "refColumn": "o_orderdate", "dateParts": ["Year", "Month"] }]
+            }
+        ]
+    }"#;
+
+    const ORDERS_RELATION: &str = r#"(SELECT "orders"."o_custkey" AS "o_custkey", "orders"."o_orderdate" AS "o_orderdate", "orders"."o_totalprice" AS "o_totalprice" FROM (SELECT "o_custkey" AS "o_custkey", "o_orderdate" AS "o_orderdate", "o_totalprice" AS "o_totalprice" FROM "orders") AS "orders")"#;
+
+    #[test]
+    fn test_rewrite_metric() {
+        assert_eq!(
+            rewrite(METRICS, "SELECT customer, total FROM revenue").unwrap(),
+            format!(
+                r#"SELECT customer, total FROM (SELECT o_custkey AS "customer", sum(o_totalprice) AS "total" FROM {ORDERS_RELATION} AS "orders" GROUP BY o_custkey) AS revenue"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_rewrite_metric_roll_up() {
+        assert_eq!(
+            rewrite(METRICS, "SELECT * FROM roll_up(revenue, order_date, MONTH)").unwrap(),
+            format!(
+                r#"SELECT * FROM (SELECT DATE_TRUNC('month', "o_orderdate") AS "order_date", o_custkey AS "customer", sum(o_totalprice) AS "total" FROM {ORDERS_RELATION} AS "orders" GROUP BY DATE_TRUNC('month', "o_orderdate"), o_custkey) AS revenue"#
+            )
+        );
+
+        let mysql = METRICS.replace("POSTGRES", "MYSQL");
+        let sql = rewrite(
+            &mysql,
+            "SELECT * FROM roll_up(revenue, order_date, 'year') r",
+        )
+        .unwrap();
+        assert!(
+            sql.contains(
+                "CAST(DATE_FORMAT(`o_orderdate`, '%Y-01-01 00:00:00') AS DATETIME) AS `order_date`"
+            ),
+            "{sql}"
+        );
+        assert!(sql.ends_with(") AS r"), "{sql}");
+
+        let err = rewrite(METRICS, "SELECT * FROM roll_up(revenue, order_date, DAY)").unwrap_err();
+        assert!(err.to_string().contains("does not support Day"), "{err}");
+
+        // view 中的 roll_up 在查询时展开
+        let with_view = METRICS.replace(
+            r#""metrics": ["#,
+            r#""views": [{ "name": "monthly", "statement": "SELECT * FROM roll_up(revenue, order_date, MONTH)" }],
+            "metrics": ["#,
+        );
+        let sql = rewrite(&with_view, "SELECT * FROM monthly").unwrap();
+        assert!(
+            sql.contains(r#"DATE_TRUNC('month', "o_orderdate") AS "order_date""#),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_roll_up_replaces_dimension_on_grain_column() {
+        // dimension 与时间粒度引用同一列时只按截断后的值分组
+        let manifest = METRICS.replace(
+            r#""dimension": [{ "name": "customer", "type": "integer", "expression": "o_custkey" }]"#,
+            r#""dimension": [
+                { "name": "customer", "type": "integer", "expression": "o_custkey" },
+                { "name": "ordered_at", "type": "timestamp", "expression": "orders.O_ORDERDATE" }
+            ]"#,
+        );
+        assert_eq!(
+            rewrite(
+                &manifest,
+                "SELECT * FROM roll_up(revenue, order_date, MONTH)"
+            )
+            .unwrap(),
+            format!(
+                r#"SELECT * FROM (SELECT DATE_TRUNC('month', "o_orderdate") AS "order_date", o_custkey AS "customer", sum(o_totalprice) AS "total" FROM {ORDERS_RELATION} AS "orders" GROUP BY DATE_TRUNC('month', "o_orderdate"), o_custkey) AS revenue"#
+            )
+        );
+        // 不按时间粒度汇总时保留该 dimension
+        let sql = rewrite(&manifest, "SELECT * FROM revenue").unwrap();
+        assert!(
+            sql.contains(r#"GROUP BY o_custkey, orders.O_ORDERDATE"#),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_nested_views() {
+        let manifest = METRICS.replace(
+            r#""metrics": ["#,
+            r#""views": [
+                { "name": "top_customers", "statement": "SELECT customer FROM revenue WHERE total > 100" },
+                { "name": "top_orders", "statement": "WITH top AS (SELECT * FROM top_customers) SELECT o.* FROM orders o JOIN top ON o.o_custkey = top.customer" }
+            ],
+            "metrics": ["#,
+        );
+        assert_eq!(
+            rewrite(&manifest, "SELECT count(*) FROM top_orders").unwrap(),
+            format!(
+                concat!(
+                    r#"SELECT count(*) FROM (WITH top AS (SELECT * FROM (SELECT customer FROM (SELECT o_custkey AS "customer", sum(o_totalprice) AS "total" "#,
+                    r#"FROM {relation} AS "orders" GROUP BY o_custkey) AS revenue WHERE total > 100) AS top_customers) "#,
+                    r#"SELECT o.* FROM {relation} AS o JOIN top ON o.o_custkey = top.customer) AS top_orders"#
+                ),
+                relation = ORDERS_RELATION
+            )
+        );
+    }
+
+    #[test]
+    fn test_rewrite_view_errors() {
+        let manifest = MANIFEST.replace(
+            r#""models": ["#,
+            r#""views": [{ "name": "missing_view", "statement": "SELECT * FROM other_view" }],
+            "models": ["#,
+        );
+        // view 引用了未定义的对象时 manifest 在分析阶段就被拒绝
+        let err = AnalyzedMdl::analyze(Arc::new(decode_manifest(&manifest).unwrap())).unwrap_err();
+        assert!(
+            err.to_string().contains(
+                "[UNKNOWN_OBJECT] views[missing_view].statement: `other_view` is not defined in the MDL"
+            ),
+            "{err}"
+        );
+
+        let cyclic = MANIFEST.replace(
+            r#""models": ["#,
+            r#""views": [
+                { "name": "v1", "statement": "SELECT * FROM v2" },
+                { "name": "v2", "statement": "SELECT * FROM V1" }
+            ],
+            "models": ["#,
+        );
+        let err = AnalyzedMdl::analyze(Arc::new(decode_manifest(&cyclic).unwrap())).unwrap_err();
+        assert!(
+            err.to_string().contains(
+                "[CIRCULAR_REFERENCE] views[v1].statement: circular view reference: v1 -> v2 -> v1"
+            ),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_applies_row_level_access_control() {
+        let manifest = RELATIONSHIPS.replace(
+            r#""tableReference": { "table": "customer" },"#,
+            r#""tableReference": { "table": "customer" },
+                "rowLevelAccessControls": [{
+                    "name": "own_customer",
+                    "requiredProperties": [{ "name": "session_user", "required": true }],
+                    "condition": "customer.c_name = @session_user"
+                }],"#,
+        );
+        let mdl = AnalyzedMdl::analyze(Arc::new(decode_manifest(&manifest).unwrap())).unwrap();
+        let rewriter = Rewriter::new()
+            .with_session_properties([("session_user", "alice")].into_iter().collect());
+
+        // 直接查询和经 relationship 连接都会带上过滤条件
+        let filtered = r#"(SELECT * FROM (SELECT "c_custkey" AS "c_custkey", "c_name" AS "c_name", "c_nationkey" AS "c_nationkey" FROM "customer") AS "customer" WHERE ("c_name" = 'alice'))"#;
+        let sql = rewriter.rewrite(&mdl, "SELECT * FROM customer").unwrap();
+        assert!(
+            sql.contains(&format!("FROM {filtered} AS \"customer\"")),
+            "{sql}"
+        );
+        let sql = rewriter
+            .rewrite(&mdl, "SELECT customer_name FROM orders")
+            .unwrap();
+        assert!(
+            sql.contains(&format!("LEFT JOIN {filtered} AS \"orders.customer\"")),
+            "{sql}"
+        );
+
+        let err = Rewriter::new()
+            .rewrite(&mdl, "SELECT * FROM customer")
+            .unwrap_err();
+        assert!(matches!(err, Error::AccessControl(_)), "{err}");
+
+        // 不能绕过 model 直接查询受保护的物理表
+        for sql in [
+            "SELECT * FROM main.customer",
+            "SELECT * FROM other.public.customer",
+            "SELECT c_name FROM orders JOIN \"tpch\".\"customer\" ON true",
+        ] {
+            let err = rewriter.rewrite(&mdl, sql).unwrap_err();
+            assert!(
+                err.to_string().contains("is not defined in the MDL"),
+                "{sql}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rewrite_applies_column_level_access_control() {
+        let manifest = RELATIONSHIPS.replace(
+            r#"{ "name": "c_name", "type": "varchar" },"#,
+            r#"{ "name": "c_name", "type": "varchar", "columnLevelAccessControl": {
+                    "name": "vip_only",
+                    "requiredProperties": [{ "name": "level", "required": false }],
+                    "operator": "GREATER_THAN",
+                    "threshold": "3"
+               } },"#,
+        );
+        let mdl = AnalyzedMdl::analyze(Arc::new(decode_manifest(&manifest).unwrap())).unwrap();
+        let level =
+            |v: &str| Rewriter::new().with_session_properties([("level", v)].into_iter().collect());
+
+        let sql = level("5")
+            .rewrite(&mdl, "SELECT customer_name FROM orders")
+            .unwrap();
+        assert!(
+            sql.contains(r#""orders.customer"."c_name" AS "customer_name""#),
+            "{sql}"
+        );
+
+        // 通过计算列间接访问同样被拒绝
+        let err = level("2")
+            .rewrite(&mdl, "SELECT customer_name FROM orders")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Access denied: column `orders.customer_name` is protected by column level access control `vip_only`"
+        );
+        assert!(level("2").rewrite(&mdl, "SELECT * FROM customer").is_err());
+
+        // 未用到的受保护列投影为 NULL
+        let sql = level("2")
+            .rewrite(&mdl, "SELECT c_custkey FROM customer")
+            .unwrap();
+        assert!(sql.contains(r#"NULL AS "c_name""#), "{sql}");
+
+        let sql = level("2")
+            .with_cls_mode(ClsMode::Nullify)
+            .rewrite(&mdl, "SELECT customer_name FROM orders")
+            .unwrap();
+        assert!(sql.contains(r#"NULL AS "customer_name""#), "{sql}");
+
+        // 可选属性缺失时策略不生效
+        let sql = Rewriter::new()
+            .rewrite(&mdl, "SELECT c_name FROM customer")
+            .unwrap();
+        assert!(sql.contains(r#""customer"."c_name" AS "c_name""#), "{sql}");
+    }
+
+    #[test]
+    fn test_plan_diagnostics() {
+        let manifest = RELATIONSHIPS
+            .replace(
+                r#""tableReference": { "table": "customer" },"#,
+                r#""tableReference": { "table": "customer" },
+                "rowLevelAccessControls": [{
+                    "name": "own_customer",
+                    "requiredProperties": [{ "name": "session_user", "required": true }],
+                    "condition": "c_name = @session_user"
+                }],"#,
+            )
+            .replace(
+                r#"{ "name": "c_name", "type": "varchar" },"#,
+                r#"{ "name": "c_name", "type": "varchar", "columnLevelAccessControl": {
+                    "name": "vip_only",
+                    "requiredProperties": [{ "name": "level", "required": true }],
+                    "operator": "GREATER_THAN",
+                    "threshold": "3"
+               } },"#,
+            );
+        let mdl = AnalyzedMdl::analyze(Arc::new(decode_manifest(&manifest).unwrap())).unwrap();
+        let rewriter = |level: &str| {
+            Rewriter::new().with_session_properties(
+                [("session_user", "alice"), ("level", level)]
+                    .into_iter()
+                    .collect(),
+            )
+        };
+        let names = |set: &BTreeSet<String>| set.iter().cloned().collect::<Vec<_>>();
+
+        let plan = rewriter("5")
+            .plan(&mdl, "SELECT customer_name, revenue FROM orders")
+            .unwrap();
+        let diagnostics = plan.diagnostics;
+        assert_eq!(
+            names(&diagnostics.models),
+            ["customer", "lineitem", "nation", "orders"]
+        );
+        assert_eq!(
+            names(&diagnostics.relationships),
+            ["customer_nation", "orders_customer", "orders_lineitem"]
+        );
+        assert_eq!(
+            diagnostics
+                .columns
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "customer.c_name",
+                "lineitem.l_price",
+                "nation.n_name",
+                "orders.customer_name",
+                "orders.revenue"
+            ]
+        );
+        assert_eq!(
+            diagnostics
+                .row_level_access_controls
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [AppliedRowLevelAccessControl {
+                model: "customer".to_string(),
+                name: "own_customer".to_string(),
+            }]
+        );
+        assert!(diagnostics.column_level_access_controls.is_empty());
+
+        let plan = rewriter("2")
+            .with_cls_mode(ClsMode::Nullify)
+            .plan(&mdl, "SELECT o_orderkey, customer_name FROM orders")
+            .unwrap();
+        assert_eq!(
+            plan.diagnostics
+                .column_level_access_controls
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [AppliedColumnLevelAccessControl {
+                column: ColumnRef::new("orders", "customer_name"),
+                protected_column: ColumnRef::new("customer", "c_name"),
+                name: "vip_only".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_rewrite_with_limit() {
+        let mdl = AnalyzedMdl::analyze(Arc::new(decode_manifest(MANIFEST).unwrap())).unwrap();
+        let limited = |sql: &str| Rewriter::new().with_limit(10).rewrite(&mdl, sql).unwrap();
+
+        assert!(limited("SELECT o_orderkey FROM orders").ends_with(") AS orders LIMIT 10"));
+        assert_eq!(limited("SELECT 1 LIMIT 5"), "SELECT 1 LIMIT 5");
+        assert_eq!(
+            limited("SELECT 1 ORDER BY 1 LIMIT 50 OFFSET 5"),
+            "SELECT 1 ORDER BY 1 LIMIT 10 OFFSET 5"
+        );
+        assert_eq!(
+            limited("SELECT 1 UNION ALL SELECT 2 OFFSET 1"),
+            "SELECT 1 UNION ALL SELECT 2 LIMIT 10 OFFSET 1"
+        );
+        assert_eq!(
+            limited("SELECT 1 FETCH FIRST 20 ROWS ONLY"),
+            r#"SELECT * FROM (SELECT 1 FETCH FIRST 20 ROWS ONLY) AS "__limited" LIMIT 10"#
+        );
+    }
+
+    #[test]
+    fn test_rewrite_errors() {
+        assert!(matches!(
+            rewrite(MANIFEST, "SELECT * FROM wren.public.missing"),
+            Err(Error::Planning(_))
+        ));
+        assert!(matches!(
+            rewrite(MANIFEST, "DELETE FROM orders"),
+            Err(Error::Planning(_))
+        ));
+        let err = rewrite(MANIFEST, "SELEC 1").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "SQL planning error: failed to parse SQL: Expected: an SQL statement, found: SELEC"
+        );
+        assert!(
+            matches!(
+                err,
+                Error::SqlSyntax {
+                    line: Some(1),
+                    column: Some(1),
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+        // 语法错误的位置来自解析器，跨行时按行列计算
+        let err = rewrite(MANIFEST, "SELECT o_orderkey\nFROM orders\nWHERE )").unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::SqlSyntax {
+                    line: Some(3),
+                    column: Some(7),
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+        let err = rewrite(MANIFEST, "SELECT 'unterminated").unwrap_err();
+        assert!(!err.to_string().contains("Line:"), "{err}");
+        assert!(
+            matches!(
+                err,
+                Error::SqlSyntax {
+                    line: Some(1),
+                    column: Some(8),
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
     }
 }
@@ -1,28 +1,79 @@
 //! Error handling module
 
+mod response;
+
+pub use response::{correlation_id, with_correlation_id, ErrorDetail, ErrorResponse};
+
 use thiserror::Error;
 
+use crate::mdl::validator::ValidationIssue;
+
 /// Main error type for the application
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Configuration error: {0}")]
     Config(String),
 
-    #[error("MDL error: {0}")]
-    Mdl(String),
+    /// MDL 错误；manifest 无法反序列化时带有出错的 JSON 路径和行列号，
+    /// 出错位置在根节点时 `path` 为空
+    #[error("MDL error: {}", mdl_message(message, path, line, column))]
+    Mdl {
+        message: String,
+        path: Option<String>,
+        /// 出错位置的行号（从 1 开始）
+        line: Option<u64>,
+        /// 出错位置的列号（从 1 开始）
+        column: Option<u64>,
+    },
+
+    /// manifest 未通过语义校验，`issues` 为发现的全部问题
+    #[error("Validation error: {message}")]
+    MdlValidation {
+        message: String,
+        issues: Vec<ValidationIssue>,
+    },
 
     #[error("SQL planning error: {0}")]
     Planning(String),
 
+    /// SQL 语法错误
+    #[error("SQL planning error: failed to parse SQL: {message}")]
+    SqlSyntax {
+        message: String,
+        /// 出错位置的行号（从 1 开始）
+        line: Option<u64>,
+        /// 出错位置的列号（从 1 开始）
+        column: Option<u64>,
+    },
+
     #[error("Connector error: {0}")]
     Connector(String),
 
-    #[error("Database error: {0}")]
-    Database(String),
+    /// 数据库返回的错误，保留 SQLSTATE 供调用方按代码处理
+    #[error("Database error: {message}{}", sqlstate.as_ref().map(|s| format!(" (SQLSTATE {s})")).unwrap_or_default())]
+    Database {
+        message: String,
+        sqlstate: Option<String>,
+        /// 出错位置在 SQL 中的字符偏移（从 1 开始）
+        position: Option<u32>,
+    },
 
     #[error("Validation error: {0}")]
     Validation(String),
 
+    #[error("Access denied: {0}")]
+    AccessControl(String),
+
+    /// 查询在完成前被取消，如调用方取消或服务关闭时超过等待时间
+    #[error("Query cancelled: {0}")]
+    Cancelled(String),
+
+    #[error("Query timed out: {0}")]
+    Timeout(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -33,5 +84,33 @@ pub enum Error {
     Http(String),
 }
 
+impl Error {
+    /// 不带位置信息的 MDL 错误
+    pub fn mdl(message: impl Into<String>) -> Self {
+        Error::Mdl {
+            message: message.into(),
+            path: None,
+            line: None,
+            column: None,
+        }
+    }
+}
+
+fn mdl_message(
+    message: &str,
+    path: &Option<String>,
+    line: &Option<u64>,
+    column: &Option<u64>,
+) -> String {
+    let (Some(line), Some(column)) = (line, column) else {
+        return message.to_string();
+    };
+    let path = path
+        .as_ref()
+        .map(|p| format!("`{p}`"))
+        .unwrap_or_else(|| "root".to_string());
+    format!("invalid manifest at {path} (line {line}, column {column}): {message}")
+}
+
 /// Result type alias
 pub type Result<T> = std::result::Result<T, Error>;
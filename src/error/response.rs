@@ -0,0 +1,296 @@
+//! 错误响应 - 将 `Error` 转换为统一结构的 HTTP 响应
+//!
+//! 响应体包含稳定的错误代码、错误信息、可选的定位信息（MDL 路径、SQL 行列号、
+//! SQLSTATE 等）以及请求的关联 ID，关联 ID 由 API 层的中间件在请求范围内设置。
+
+use std::future::Future;
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Json, Response};
+use serde::{Deserialize, Serialize};
+
+use super::Error;
+use crate::mdl::validator::ValidationIssue;
+
+tokio::task_local! {
+    static CORRELATION_ID: String;
+}
+
+/// 当前请求的关联 ID，不在请求范围内时返回 `None`
+pub fn correlation_id() -> Option<String> {
+    CORRELATION_ID.try_with(Clone::clone).ok()
+}
+
+/// 在关联 ID 为 `id` 的范围内执行 `f`
+pub async fn with_correlation_id<F: Future>(id: String, f: F) -> F::Output {
+    CORRELATION_ID.scope(id, f).await
+}
+
+/// 错误响应体
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    /// 稳定的错误代码，如 `PLANNING_ERROR`
+    pub code: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<ErrorDetail>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+/// 错误的定位信息
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorDetail {
+    /// manifest 中出错的 JSON 路径，语义校验失败时为第一个问题的路径
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// 出错位置的行号（从 1 开始）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u64>,
+    /// 出错位置的列号（从 1 开始）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<u64>,
+    /// 数据库返回的 SQLSTATE
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sqlstate: Option<String>,
+    /// 数据库返回的出错位置在 SQL 中的字符偏移（从 1 开始）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<u32>,
+    /// manifest 语义校验发现的全部问题
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ErrorResponse {
+    /// 由错误构造响应体，`correlation_id` 为错误所属请求的关联 ID
+    pub fn new(err: &Error, correlation_id: Option<String>) -> Self {
+        Self {
+            code: err.code().to_string(),
+            message: err.to_string(),
+            detail: err.detail(),
+            correlation_id,
+        }
+    }
+}
+
+impl ErrorDetail {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Error {
+    /// 每个变体对应的稳定错误代码
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Config(_) => "CONFIG_ERROR",
+            Error::Mdl { .. } => "MDL_ERROR",
+            Error::Planning(_) | Error::SqlSyntax { .. } => "PLANNING_ERROR",
+            Error::Connector(_) => "CONNECTOR_ERROR",
+            Error::Database { .. } => "DATABASE_ERROR",
+            Error::Validation(_) | Error::MdlValidation { .. } => "VALIDATION_ERROR",
+            Error::AccessControl(_) => "ACCESS_DENIED",
+            Error::Cancelled(_) => "QUERY_CANCELLED",
+            Error::Timeout(_) => "QUERY_TIMEOUT",
+            Error::NotFound(_) => "NOT_FOUND",
+            Error::Io(_) => "IO_ERROR",
+            Error::Serialization(_) => "SERIALIZATION_ERROR",
+            Error::Http(_) => "HTTP_ERROR",
+        }
+    }
+
+    /// 错误对应的 HTTP 状态码
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Error::Mdl { .. }
+            | Error::MdlValidation { .. }
+            | Error::Validation(_)
+            | Error::Planning(_)
+            | Error::SqlSyntax { .. } => StatusCode::BAD_REQUEST,
+            Error::AccessControl(_) => StatusCode::FORBIDDEN,
+            Error::NotFound(_) => StatusCode::NOT_FOUND,
+            // 22 类（数据异常）和 42 类（语法错误或访问规则）由查询本身引起
+            Error::Database {
+                sqlstate: Some(sqlstate),
+                ..
+            } if sqlstate.starts_with("22") || sqlstate.starts_with("42") => {
+                StatusCode::BAD_REQUEST
+            }
+            Error::Database { .. } | Error::Connector(_) => StatusCode::BAD_GATEWAY,
+            Error::Cancelled(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Error::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            Error::Config(_) | Error::Io(_) | Error::Serialization(_) | Error::Http(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    /// 错误的定位信息，没有可用信息时返回 `None`
+    pub fn detail(&self) -> Option<ErrorDetail> {
+        let detail = match self {
+            Error::Database {
+                sqlstate, position, ..
+            } => ErrorDetail {
+                sqlstate: sqlstate.clone(),
+                position: *position,
+                ..Default::default()
+            },
+            Error::Mdl {
+                path, line, column, ..
+            } => ErrorDetail {
+                path: path.clone(),
+                line: *line,
+                column: *column,
+                ..Default::default()
+            },
+            Error::MdlValidation { issues, .. } => ErrorDetail {
+                path: issues.first().map(|issue| issue.path.clone()),
+                issues: issues.clone(),
+                ..Default::default()
+            },
+            Error::SqlSyntax { line, column, .. } => ErrorDetail {
+                line: *line,
+                column: *column,
+                ..Default::default()
+            },
+            // serde_json 的行号为 0 表示没有位置信息
+            Error::Serialization(e) if e.line() > 0 => ErrorDetail {
+                line: Some(e.line() as u64),
+                column: Some(e.column() as u64),
+                ..Default::default()
+            },
+            _ => return None,
+        };
+        (!detail.is_empty()).then_some(detail)
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        let body = ErrorResponse::new(&self, correlation_id());
+        if status.is_server_error() {
+            tracing::error!(
+                code = body.code,
+                correlation_id = body.correlation_id,
+                "{}",
+                body.message
+            );
+        }
+        (status, Json(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdl::validator::IssueCode;
+    use axum::body::to_bytes;
+
+    async fn body(err: Error) -> (StatusCode, ErrorResponse) {
+        let response = err.into_response();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_error_response_schema() {
+        let (status, response) = with_correlation_id(
+            "req-1".to_string(),
+            body(Error::AccessControl("nope".to_string())),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(
+            response,
+            ErrorResponse {
+                code: "ACCESS_DENIED".to_string(),
+                message: "Access denied: nope".to_string(),
+                detail: None,
+                correlation_id: Some("req-1".to_string()),
+            }
+        );
+
+        let (status, response) = body(Error::Config("bad".to_string())).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.correlation_id, None);
+    }
+
+    #[test]
+    fn test_database_status_and_detail() {
+        let database = |sqlstate: &str| Error::Database {
+            message: "boom".to_string(),
+            sqlstate: Some(sqlstate.to_string()),
+            position: Some(8),
+        };
+        assert_eq!(database("42703").status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(database("22012").status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(database("08006").status_code(), StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            database("42703").detail(),
+            Some(ErrorDetail {
+                sqlstate: Some("42703".to_string()),
+                position: Some(8),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_detail_from_fields() {
+        let err = Error::Mdl {
+            message: "invalid type".to_string(),
+            path: Some("models[0].columns".to_string()),
+            line: Some(3),
+            column: Some(17),
+        };
+        assert_eq!(
+            err.to_string(),
+            "MDL error: invalid manifest at `models[0].columns` (line 3, column 17): invalid type"
+        );
+        assert_eq!(
+            err.detail(),
+            Some(ErrorDetail {
+                path: Some("models[0].columns".to_string()),
+                line: Some(3),
+                column: Some(17),
+                ..Default::default()
+            })
+        );
+
+        let err = Error::SqlSyntax {
+            message: "Expected: end of statement, found: x".to_string(),
+            line: Some(1),
+            column: Some(10),
+        };
+        assert_eq!(err.code(), "PLANNING_ERROR");
+        let detail = err.detail().unwrap();
+        assert_eq!((detail.line, detail.column), (Some(1), Some(10)));
+
+        let issue = ValidationIssue {
+            code: IssueCode::InvalidPrimaryKey,
+            path: "models[orders].primaryKey".to_string(),
+            message: "`id` is not a column of model `orders`".to_string(),
+        };
+        let err = Error::MdlValidation {
+            message: "manifest has 1 problem(s)".to_string(),
+            issues: vec![issue.clone()],
+        };
+        assert_eq!(err.code(), "VALIDATION_ERROR");
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+        let detail = err.detail().unwrap();
+        assert_eq!(detail.path.as_deref(), Some("models[orders].primaryKey"));
+        assert_eq!(detail.issues, vec![issue]);
+
+        let err = Error::from(serde_json::from_str::<serde_json::Value>("{\n  x").unwrap_err());
+        let detail = err.detail().unwrap();
+        assert_eq!((detail.line, detail.column), (Some(2), Some(3)));
+
+        // 信息中的文字不再被当作位置
+        assert_eq!(
+            Error::Planning("invalid manifest at `x` line 3".to_string()).detail(),
+            None
+        );
+    }
+}
@@ -0,0 +1,958 @@
+//! Arrow 格式 - 按列类型把查询结果转换为 Arrow 的 `RecordBatch`
+//!
+//! 列类型使用连接器返回的类型名（`ColumnInfo::data_type`），行值使用连接器输出的 JSON：
+//!
+//! - 整数、浮点数、布尔值映射为对应的 Arrow 基本类型，浮点数的 `NaN` 和无穷大以字符串传入
+//! - `numeric(p,s)` / `decimal(p,s)` 映射为 `Decimal128`，精度超过 38 时为 `Decimal256`；
+//!   未声明精度的 `numeric` 无法用固定小数位表示，映射为字符串
+//! - `timestamp` 映射为微秒精度、无时区的 `Timestamp`，`timestamptz` 的时区为 UTC
+//! - `date`、`time` 映射为 `Date32`、`Time64(Microsecond)`，`bytea` 从 base64 还原为 `Binary`
+//! - `T[]` 映射为元素类型的 `List`，嵌套数组映射为嵌套的 `List`
+//! - 其余类型（文本、json、uuid、interval 等）映射为字符串，非字符串的值序列化为 JSON 文本
+//!
+//! 嵌入式引擎直接产出 `RecordBatch`，`type_name` 和 `batch_rows` 做反向转换，
+//! 得到与其他连接器一致的类型名和 JSON 行。
+
+use std::sync::Arc;
+
+use arrow_array::builder::{
+    ArrayBuilder, BinaryBuilder, BooleanBuilder, Date32Builder, Decimal128Builder,
+    Decimal256Builder, Float32Builder, Float64Builder, Int16Builder, Int32Builder, Int64Builder,
+    Int8Builder, ListBuilder, StringBuilder, Time64MicrosecondBuilder, TimestampMicrosecondBuilder,
+    UInt16Builder, UInt32Builder, UInt64Builder, UInt8Builder,
+};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    Date32Type, Date64Type, Decimal128Type, Decimal256Type, DecimalType, DurationMicrosecondType,
+    DurationMillisecondType, DurationNanosecondType, DurationSecondType, Float16Type, Float32Type,
+    Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, IntervalDayTimeType,
+    IntervalMonthDayNanoType, IntervalYearMonthType, Time32MillisecondType, Time32SecondType,
+    Time64MicrosecondType, Time64NanosecondType, TimestampMicrosecondType,
+    TimestampMillisecondType, TimestampNanosecondType, TimestampSecondType, UInt16Type, UInt32Type,
+    UInt64Type, UInt8Type,
+};
+use arrow_array::{Array, ArrayRef, RecordBatch};
+use arrow_buffer::i256;
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{
+    ArrowError, DataType, Field, FieldRef, IntervalUnit, Schema, SchemaRef, TimeUnit,
+    DECIMAL128_MAX_PRECISION, DECIMAL256_MAX_PRECISION,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use serde_json::Value;
+
+use super::iso8601_duration;
+use crate::error::{Error, Result};
+use crate::model::ColumnInfo;
+
+/// 带时区的时间戳统一转换为 UTC
+const UTC: &str = "UTC";
+
+/// schema 元数据中记录生效的行数上限的键
+pub const MAX_ROWS_METADATA: &str = "max_rows";
+
+/// 按列信息生成 Arrow schema，所有列均可为空
+pub fn schema(columns: &[ColumnInfo]) -> Schema {
+    Schema::new(
+        columns
+            .iter()
+            .map(|column| Field::new(&column.name, data_type(&column.data_type), true))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// 类型名对应的 Arrow 类型，不区分大小写
+pub fn data_type(name: &str) -> DataType {
+    let name = name.trim().to_ascii_lowercase();
+    if let Some(element) = name.strip_suffix("[]") {
+        return DataType::List(list_item(data_type(element)));
+    }
+    let (base, args) = match name.split_once('(') {
+        Some((base, args)) => (base.trim(), args.strip_suffix(')')),
+        None => (name.as_str(), None),
+    };
+    match base {
+        "bool" | "boolean" => DataType::Boolean,
+        // Postgres 的单字节类型 "char"
+        "\"char\"" | "tinyint" => DataType::Int8,
+        "int2" | "smallint" | "year" => DataType::Int16,
+        "int4" | "int" | "integer" | "mediumint" => DataType::Int32,
+        "int8" | "bigint" => DataType::Int64,
+        "tinyint unsigned" => DataType::UInt8,
+        "smallint unsigned" => DataType::UInt16,
+        "oid" | "int unsigned" | "mediumint unsigned" => DataType::UInt32,
+        "bigint unsigned" => DataType::UInt64,
+        // MySQL 的 float 为单精度
+        "float4" | "real" | "float" => DataType::Float32,
+        "float8" | "double" | "double precision" => DataType::Float64,
+        "numeric" | "decimal" => args.and_then(decimal).unwrap_or(DataType::Utf8),
+        "bytea" | "binary" | "varbinary" | "tinyblob" | "blob" | "mediumblob" | "longblob" => {
+            DataType::Binary
+        }
+        // MySQL 的 bit(1) 作为布尔值，更宽的 bit(n) 作为无符号整数
+        "bit" if args == Some("1") => DataType::Boolean,
+        "bit" => DataType::UInt64,
+        "date" => DataType::Date32,
+        "time" => DataType::Time64(TimeUnit::Microsecond),
+        "timestamp" | "datetime" => DataType::Timestamp(TimeUnit::Microsecond, None),
+        "timestamptz" => DataType::Timestamp(TimeUnit::Microsecond, Some(UTC.into())),
+        _ => DataType::Utf8,
+    }
+}
+
+/// 按列信息生成 schema，并在元数据中记录行数上限
+pub fn schema_with_max_rows(columns: &[ColumnInfo], max_rows: Option<u64>) -> SchemaRef {
+    let mut schema = schema(columns);
+    if let Some(max_rows) = max_rows {
+        schema
+            .metadata
+            .insert(MAX_ROWS_METADATA.to_string(), max_rows.to_string());
+    }
+    Arc::new(schema)
+}
+
+/// `p,s` 或 `p` 形式的精度和小数位
+fn decimal(args: &str) -> Option<DataType> {
+    let mut args = args.split(',').map(str::trim);
+    let precision: u8 = args.next()?.parse().ok()?;
+    let scale: i8 = match args.next() {
+        Some(scale) => scale.parse().ok()?,
+        None => 0,
+    };
+    match precision {
+        0 => None,
+        p if p <= DECIMAL128_MAX_PRECISION => Some(DataType::Decimal128(p, scale)),
+        p if p <= DECIMAL256_MAX_PRECISION => Some(DataType::Decimal256(p, scale)),
+        _ => None,
+    }
+}
+
+fn list_item(data_type: DataType) -> FieldRef {
+    Arc::new(Field::new_list_field(data_type, true))
+}
+
+/// 把 JSON 行（与 schema 字段顺序一致的数组）转换为 `RecordBatch`
+pub fn record_batch(schema: &SchemaRef, rows: &[Value]) -> Result<RecordBatch> {
+    let mut builders = schema
+        .fields()
+        .iter()
+        .map(|field| Column::new(field.data_type(), rows.len()))
+        .collect::<Vec<_>>();
+    for row in rows {
+        let values = row
+            .as_array()
+            .filter(|values| values.len() == builders.len())
+            .ok_or_else(|| {
+                Error::Connector(format!(
+                    "expected a row of {} values, got `{row}`",
+                    builders.len()
+                ))
+            })?;
+        for ((builder, value), field) in builders.iter_mut().zip(values).zip(schema.fields()) {
+            builder.append(value).map_err(|e| {
+                Error::Connector(format!(
+                    "failed to convert column `{}` to Arrow `{}`: {e}",
+                    field.name(),
+                    field.data_type()
+                ))
+            })?;
+        }
+    }
+    let columns = builders.iter_mut().map(ArrayBuilder::finish).collect();
+    RecordBatch::try_new(schema.clone(), columns)
+        .map_err(|e| Error::Connector(format!("failed to build Arrow record batch: {e}")))
+}
+
+/// Arrow IPC 流的写入器，每批行编码为一个 `RecordBatch` 消息
+///
+/// 每次写入后返回新编码的字节，调用方可以逐段发出
+pub struct IpcStreamWriter {
+    schema: SchemaRef,
+    writer: StreamWriter<Vec<u8>>,
+}
+
+impl IpcStreamWriter {
+    /// 创建写入器并编码 schema 消息，通过 `take` 取出
+    pub fn try_new(schema: SchemaRef) -> Result<Self> {
+        let writer = StreamWriter::try_new(Vec::new(), &schema).map_err(ipc_error)?;
+        Ok(Self { schema, writer })
+    }
+
+    /// 写入一批行，空批次不产生消息
+    pub fn write(&mut self, rows: &[Value]) -> Result<Vec<u8>> {
+        if !rows.is_empty() {
+            let batch = record_batch(&self.schema, rows)?;
+            self.writer.write(&batch).map_err(ipc_error)?;
+        }
+        Ok(self.take())
+    }
+
+    /// 写入流的结束标记
+    pub fn finish(&mut self) -> Result<Vec<u8>> {
+        self.writer.finish().map_err(ipc_error)?;
+        Ok(self.take())
+    }
+
+    /// 取出已编码但尚未取出的字节
+    pub fn take(&mut self) -> Vec<u8> {
+        std::mem::take(self.writer.get_mut())
+    }
+}
+
+fn ipc_error(err: ArrowError) -> Error {
+    Error::Connector(format!("failed to encode Arrow IPC stream: {err}"))
+}
+
+/// 单列的构建器，列表类型递归包含元素的构建器
+enum Column {
+    Boolean(BooleanBuilder),
+    Int8(Int8Builder),
+    Int16(Int16Builder),
+    Int32(Int32Builder),
+    Int64(Int64Builder),
+    UInt8(UInt8Builder),
+    UInt16(UInt16Builder),
+    UInt32(UInt32Builder),
+    UInt64(UInt64Builder),
+    Float32(Float32Builder),
+    Float64(Float64Builder),
+    Decimal128(Decimal128Builder, u8, i8),
+    Decimal256(Decimal256Builder, u8, i8),
+    Binary(BinaryBuilder),
+    Date32(Date32Builder),
+    Time64(Time64MicrosecondBuilder),
+    Timestamp(TimestampMicrosecondBuilder, bool),
+    List(ListBuilder<Box<dyn ArrayBuilder>>),
+    Utf8(StringBuilder),
+}
+
+type AppendResult = std::result::Result<(), String>;
+
+impl Column {
+    fn new(data_type: &DataType, capacity: usize) -> Self {
+        match data_type {
+            DataType::Boolean => Column::Boolean(BooleanBuilder::with_capacity(capacity)),
+            DataType::Int8 => Column::Int8(Int8Builder::with_capacity(capacity)),
+            DataType::Int16 => Column::Int16(Int16Builder::with_capacity(capacity)),
+            DataType::Int32 => Column::Int32(Int32Builder::with_capacity(capacity)),
+            DataType::Int64 => Column::Int64(Int64Builder::with_capacity(capacity)),
+            DataType::UInt8 => Column::UInt8(UInt8Builder::with_capacity(capacity)),
+            DataType::UInt16 => Column::UInt16(UInt16Builder::with_capacity(capacity)),
+            DataType::UInt32 => Column::UInt32(UInt32Builder::with_capacity(capacity)),
+            DataType::UInt64 => Column::UInt64(UInt64Builder::with_capacity(capacity)),
+            DataType::Float32 => Column::Float32(Float32Builder::with_capacity(capacity)),
+            DataType::Float64 => Column::Float64(Float64Builder::with_capacity(capacity)),
+            DataType::Decimal128(p, s) => Column::Decimal128(
+                Decimal128Builder::with_capacity(capacity).with_data_type(data_type.clone()),
+                *p,
+                *s,
+            ),
+            DataType::Decimal256(p, s) => Column::Decimal256(
+                Decimal256Builder::with_capacity(capacity).with_data_type(data_type.clone()),
+                *p,
+                *s,
+            ),
+            DataType::Binary => Column::Binary(BinaryBuilder::with_capacity(capacity, 0)),
+            DataType::Date32 => Column::Date32(Date32Builder::with_capacity(capacity)),
+            DataType::Time64(_) => {
+                Column::Time64(Time64MicrosecondBuilder::with_capacity(capacity))
+            }
+            DataType::Timestamp(_, tz) => Column::Timestamp(
+                TimestampMicrosecondBuilder::with_capacity(capacity)
+                    .with_data_type(data_type.clone()),
+                tz.is_some(),
+            ),
+            DataType::List(item) => Column::List(
+                ListBuilder::with_capacity(Column::boxed(item.data_type()), capacity)
+                    .with_field(item.clone()),
+            ),
+            _ => Column::Utf8(StringBuilder::with_capacity(capacity, 0)),
+        }
+    }
+
+    fn boxed(data_type: &DataType) -> Box<dyn ArrayBuilder> {
+        Box::new(Column::new(data_type, 0))
+    }
+
+    fn append(&mut self, value: &Value) -> AppendResult {
+        if value.is_null() {
+            self.append_null();
+            return Ok(());
+        }
+        match self {
+            Column::Boolean(b) => b.append_value(value.as_bool().ok_or_else(|| unexpected(value))?),
+            Column::Int8(b) => b.append_value(integer(value)?),
+            Column::Int16(b) => b.append_value(integer(value)?),
+            Column::Int32(b) => b.append_value(integer(value)?),
+            Column::Int64(b) => b.append_value(integer(value)?),
+            Column::UInt8(b) => b.append_value(integer(value)?),
+            Column::UInt16(b) => b.append_value(integer(value)?),
+            Column::UInt32(b) => b.append_value(integer(value)?),
+            Column::UInt64(b) => b.append_value(value.as_u64().ok_or_else(|| unexpected(value))?),
+            Column::Float32(b) => b.append_value(float(value)? as f32),
+            Column::Float64(b) => b.append_value(float(value)?),
+            Column::Decimal128(b, precision, scale) => {
+                let digits = decimal_digits(value, *precision, *scale)?;
+                b.append_value(digits.parse::<i128>().map_err(|e| e.to_string())?)
+            }
+            Column::Decimal256(b, precision, scale) => {
+                let digits = decimal_digits(value, *precision, *scale)?;
+                b.append_value(i256::from_string(&digits).ok_or_else(|| unexpected(value))?)
+            }
+            Column::Binary(b) => {
+                let bytes = STANDARD.decode(string(value)?).map_err(|e| e.to_string())?;
+                b.append_value(bytes)
+            }
+            Column::Date32(b) => {
+                let date = NaiveDate::parse_from_str(string(value)?, "%Y-%m-%d")
+                    .map_err(|e| e.to_string())?;
+                let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).expect("valid date");
+                let days = (date - epoch).num_days();
+                b.append_value(i32::try_from(days).map_err(|e| e.to_string())?)
+            }
+            Column::Time64(b) => {
+                let time = string(value)?
+                    .parse::<NaiveTime>()
+                    .map_err(|e| e.to_string())?;
+                let micros = i64::from(time.num_seconds_from_midnight()) * 1_000_000
+                    + i64::from(time.nanosecond() / 1_000);
+                b.append_value(micros)
+            }
+            Column::Timestamp(b, with_time_zone) => {
+                let text = string(value)?;
+                let micros = if *with_time_zone {
+                    DateTime::parse_from_rfc3339(text)
+                        .map_err(|e| e.to_string())?
+                        .timestamp_micros()
+                } else {
+                    NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+                        .map_err(|e| e.to_string())?
+                        .and_utc()
+                        .timestamp_micros()
+                };
+                b.append_value(micros)
+            }
+            Column::List(b) => {
+                let values = value.as_array().ok_or_else(|| unexpected(value))?;
+                let items = b
+                    .values()
+                    .as_any_mut()
+                    .downcast_mut::<Column>()
+                    .expect("list items are built by `Column`");
+                for value in values {
+                    items.append(value)?;
+                }
+                b.append(true)
+            }
+            Column::Utf8(b) => match value {
+                Value::String(s) => b.append_value(s),
+                other => b.append_value(other.to_string()),
+            },
+        }
+        Ok(())
+    }
+
+    fn append_null(&mut self) {
+        match self {
+            Column::Boolean(b) => b.append_null(),
+            Column::Int8(b) => b.append_null(),
+            Column::Int16(b) => b.append_null(),
+            Column::Int32(b) => b.append_null(),
+            Column::Int64(b) => b.append_null(),
+            Column::UInt8(b) => b.append_null(),
+            Column::UInt16(b) => b.append_null(),
+            Column::UInt32(b) => b.append_null(),
+            Column::UInt64(b) => b.append_null(),
+            Column::Float32(b) => b.append_null(),
+            Column::Float64(b) => b.append_null(),
+            Column::Decimal128(b, ..) => b.append_null(),
+            Column::Decimal256(b, ..) => b.append_null(),
+            Column::Binary(b) => b.append_null(),
+            Column::Date32(b) => b.append_null(),
+            Column::Time64(b) => b.append_null(),
+            Column::Timestamp(b, _) => b.append_null(),
+            Column::List(b) => b.append_null(),
+            Column::Utf8(b) => b.append_null(),
+        }
+    }
+
+    fn builder(&self) -> &dyn ArrayBuilder {
+        match self {
+            Column::Boolean(b) => b,
+            Column::Int8(b) => b,
+            Column::Int16(b) => b,
+            Column::Int32(b) => b,
+            Column::Int64(b) => b,
+            Column::UInt8(b) => b,
+            Column::UInt16(b) => b,
+            Column::UInt32(b) => b,
+            Column::UInt64(b) => b,
+            Column::Float32(b) => b,
+            Column::Float64(b) => b,
+            Column::Decimal128(b, ..) => b,
+            Column::Decimal256(b, ..) => b,
+            Column::Binary(b) => b,
+            Column::Date32(b) => b,
+            Column::Time64(b) => b,
+            Column::Timestamp(b, _) => b,
+            Column::List(b) => b,
+            Column::Utf8(b) => b,
+        }
+    }
+
+    fn builder_mut(&mut self) -> &mut dyn ArrayBuilder {
+        match self {
+            Column::Boolean(b) => b,
+            Column::Int8(b) => b,
+            Column::Int16(b) => b,
+            Column::Int32(b) => b,
+            Column::Int64(b) => b,
+            Column::UInt8(b) => b,
+            Column::UInt16(b) => b,
+            Column::UInt32(b) => b,
+            Column::UInt64(b) => b,
+            Column::Float32(b) => b,
+            Column::Float64(b) => b,
+            Column::Decimal128(b, ..) => b,
+            Column::Decimal256(b, ..) => b,
+            Column::Binary(b) => b,
+            Column::Date32(b) => b,
+            Column::Time64(b) => b,
+            Column::Timestamp(b, _) => b,
+            Column::List(b) => b,
+            Column::Utf8(b) => b,
+        }
+    }
+}
+
+/// 列表构建器以 `Column` 作为元素构建器
+impl ArrayBuilder for Column {
+    fn len(&self) -> usize {
+        self.builder().len()
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        self.builder_mut().finish()
+    }
+
+    fn finish_cloned(&self) -> ArrayRef {
+        self.builder().finish_cloned()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn into_box_any(self: Box<Self>) -> Box<dyn std::any::Any> {
+        self
+    }
+}
+
+fn unexpected(value: &Value) -> String {
+    format!("unexpected value `{value}`")
+}
+
+fn string(value: &Value) -> std::result::Result<&str, String> {
+    value.as_str().ok_or_else(|| unexpected(value))
+}
+
+fn integer<T: TryFrom<i64>>(value: &Value) -> std::result::Result<T, String> {
+    value
+        .as_i64()
+        .and_then(|n| T::try_from(n).ok())
+        .ok_or_else(|| unexpected(value))
+}
+
+/// 浮点数的 `NaN` 和无穷大以字符串表示
+fn float(value: &Value) -> std::result::Result<f64, String> {
+    match value {
+        Value::Number(n) => n.as_f64().ok_or_else(|| unexpected(value)),
+        Value::String(s) => s.parse().map_err(|_| unexpected(value)),
+        _ => Err(unexpected(value)),
+    }
+}
+
+/// 十进制数按小数位 `scale` 缩放后的整数的数字串，超出精度或小数位时报错
+fn decimal_digits(value: &Value, precision: u8, scale: i8) -> std::result::Result<String, String> {
+    let text = match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        _ => return Err(unexpected(value)),
+    };
+    let (negative, unsigned) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(&text)),
+    };
+    let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let valid = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if integer.is_empty() && fraction.is_empty() || !valid(integer) || !valid(fraction) {
+        return Err(unexpected(value));
+    }
+
+    let mut digits = format!("{integer}{fraction}");
+    // 缩放：小数位数与 scale 的差值决定补零或去掉多余的零
+    let shift = i32::from(scale) - fraction.len() as i32;
+    if shift >= 0 {
+        digits.extend(std::iter::repeat('0').take(shift as usize));
+    } else {
+        let keep = digits.len().saturating_sub(shift.unsigned_abs() as usize);
+        if digits[keep..].bytes().any(|b| b != b'0') {
+            return Err(format!("`{text}` has more than {scale} fractional digits"));
+        }
+        digits.truncate(keep);
+    }
+    let digits = digits.trim_start_matches('0');
+    if digits.len() > usize::from(precision) {
+        return Err(format!("`{text}` exceeds precision {precision}"));
+    }
+    Ok(match (negative, digits.is_empty()) {
+        (_, true) => "0".to_string(),
+        (true, false) => format!("-{digits}"),
+        (false, false) => digits.to_string(),
+    })
+}
+
+/// Arrow 类型对应的类型名，是 `data_type` 的逆映射，用于嵌入式引擎返回的列
+///
+/// 无符号整数带 `unsigned` 后缀，字典类型使用值的类型，列表类型为 `T[]`
+pub fn type_name(data_type: &DataType) -> String {
+    let name = match data_type {
+        DataType::Null => "null",
+        DataType::Boolean => "boolean",
+        DataType::Int8 => "tinyint",
+        DataType::Int16 => "smallint",
+        DataType::Int32 => "integer",
+        DataType::Int64 => "bigint",
+        DataType::UInt8 => "tinyint unsigned",
+        DataType::UInt16 => "smallint unsigned",
+        DataType::UInt32 => "int unsigned",
+        DataType::UInt64 => "bigint unsigned",
+        DataType::Float16 | DataType::Float32 => "real",
+        DataType::Float64 => "double",
+        DataType::Decimal128(precision, scale) | DataType::Decimal256(precision, scale) => {
+            return format!("decimal({precision},{scale})")
+        }
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => "varchar",
+        DataType::Binary
+        | DataType::LargeBinary
+        | DataType::BinaryView
+        | DataType::FixedSizeBinary(_) => "blob",
+        DataType::Date32 | DataType::Date64 => "date",
+        DataType::Time32(_) | DataType::Time64(_) => "time",
+        DataType::Timestamp(_, None) => "timestamp",
+        DataType::Timestamp(_, Some(_)) => "timestamptz",
+        DataType::Interval(_) | DataType::Duration(_) => "interval",
+        DataType::List(item) | DataType::LargeList(item) | DataType::FixedSizeList(item, _) => {
+            return format!("{}[]", type_name(item.data_type()))
+        }
+        DataType::Struct(_) => "struct",
+        DataType::Map(..) => "map",
+        DataType::Dictionary(_, value) => return type_name(value),
+        other => return other.to_string().to_ascii_lowercase(),
+    };
+    name.to_string()
+}
+
+/// 把 `RecordBatch` 转换为 JSON 行，值的表示与 Postgres 连接器一致
+///
+/// 十进制数转为字符串以保留精度，二进制转为 base64，带时区的时间戳转为 UTC 的
+/// RFC 3339 时间，时间间隔转为 ISO 8601 时长，struct 和 map 转为 JSON 对象
+pub fn batch_rows(batch: &RecordBatch) -> Result<Vec<Value>> {
+    let mut rows = vec![Vec::with_capacity(batch.num_columns()); batch.num_rows()];
+    let schema = batch.schema();
+    for (array, field) in batch.columns().iter().zip(schema.fields()) {
+        for (index, row) in rows.iter_mut().enumerate() {
+            let value = json_value(array.as_ref(), index).map_err(|e| {
+                Error::Connector(format!(
+                    "failed to convert column `{}` of Arrow type `{}`: {e}",
+                    field.name(),
+                    field.data_type()
+                ))
+            })?;
+            row.push(value);
+        }
+    }
+    Ok(rows.into_iter().map(Value::Array).collect())
+}
+
+fn json_value(array: &dyn Array, index: usize) -> std::result::Result<Value, String> {
+    if array.is_null(index) {
+        return Ok(Value::Null);
+    }
+    let value = match array.data_type() {
+        DataType::Null => Value::Null,
+        DataType::Boolean => Value::Bool(array.as_boolean().value(index)),
+        DataType::Int8 => Value::from(array.as_primitive::<Int8Type>().value(index)),
+        DataType::Int16 => Value::from(array.as_primitive::<Int16Type>().value(index)),
+        DataType::Int32 => Value::from(array.as_primitive::<Int32Type>().value(index)),
+        DataType::Int64 => Value::from(array.as_primitive::<Int64Type>().value(index)),
+        DataType::UInt8 => Value::from(array.as_primitive::<UInt8Type>().value(index)),
+        DataType::UInt16 => Value::from(array.as_primitive::<UInt16Type>().value(index)),
+        DataType::UInt32 => Value::from(array.as_primitive::<UInt32Type>().value(index)),
+        DataType::UInt64 => Value::from(array.as_primitive::<UInt64Type>().value(index)),
+        DataType::Float16 => json_float(array.as_primitive::<Float16Type>().value(index).to_f64()),
+        DataType::Float32 => json_float(array.as_primitive::<Float32Type>().value(index).into()),
+        DataType::Float64 => json_float(array.as_primitive::<Float64Type>().value(index)),
+        DataType::Decimal128(precision, scale) => Value::String(Decimal128Type::format_decimal(
+            array.as_primitive::<Decimal128Type>().value(index),
+            *precision,
+            *scale,
+        )),
+        DataType::Decimal256(precision, scale) => Value::String(Decimal256Type::format_decimal(
+            array.as_primitive::<Decimal256Type>().value(index),
+            *precision,
+            *scale,
+        )),
+        DataType::Utf8 => Value::from(array.as_string::<i32>().value(index)),
+        DataType::LargeUtf8 => Value::from(array.as_string::<i64>().value(index)),
+        DataType::Utf8View => Value::from(array.as_string_view().value(index)),
+        DataType::Binary => base64(array.as_binary::<i32>().value(index)),
+        DataType::LargeBinary => base64(array.as_binary::<i64>().value(index)),
+        DataType::BinaryView => base64(array.as_binary_view().value(index)),
+        DataType::FixedSizeBinary(_) => base64(array.as_fixed_size_binary().value(index)),
+        DataType::Date32 => date(array.as_primitive::<Date32Type>().value_as_date(index))?,
+        DataType::Date64 => date(array.as_primitive::<Date64Type>().value_as_date(index))?,
+        DataType::Time32(TimeUnit::Second) => time(
+            array
+                .as_primitive::<Time32SecondType>()
+                .value_as_time(index),
+        )?,
+        DataType::Time32(_) => time(
+            array
+                .as_primitive::<Time32MillisecondType>()
+                .value_as_time(index),
+        )?,
+        DataType::Time64(TimeUnit::Microsecond) => time(
+            array
+                .as_primitive::<Time64MicrosecondType>()
+                .value_as_time(index),
+        )?,
+        DataType::Time64(_) => time(
+            array
+                .as_primitive::<Time64NanosecondType>()
+                .value_as_time(index),
+        )?,
+        DataType::Timestamp(unit, tz) => {
+            let datetime = match unit {
+                TimeUnit::Second => array
+                    .as_primitive::<TimestampSecondType>()
+                    .value_as_datetime(index),
+                TimeUnit::Millisecond => array
+                    .as_primitive::<TimestampMillisecondType>()
+                    .value_as_datetime(index),
+                TimeUnit::Microsecond => array
+                    .as_primitive::<TimestampMicrosecondType>()
+                    .value_as_datetime(index),
+                TimeUnit::Nanosecond => array
+                    .as_primitive::<TimestampNanosecondType>()
+                    .value_as_datetime(index),
+            }
+            .ok_or("timestamp out of range")?;
+            Value::String(match tz {
+                Some(_) => DateTime::<Utc>::from_naive_utc_and_offset(datetime, Utc).to_rfc3339(),
+                None => datetime.format("%Y-%m-%dT%H:%M:%S%.f").to_string(),
+            })
+        }
+        DataType::Interval(IntervalUnit::YearMonth) => {
+            let months = array.as_primitive::<IntervalYearMonthType>().value(index);
+            Value::String(iso8601_duration(months.into(), 0, 0))
+        }
+        DataType::Interval(IntervalUnit::DayTime) => {
+            let value = array.as_primitive::<IntervalDayTimeType>().value(index);
+            let micros = i64::from(value.milliseconds) * 1_000;
+            Value::String(iso8601_duration(0, value.days.into(), micros))
+        }
+        DataType::Interval(IntervalUnit::MonthDayNano) => {
+            let value = array
+                .as_primitive::<IntervalMonthDayNanoType>()
+                .value(index);
+            let micros = value.nanoseconds / 1_000;
+            Value::String(iso8601_duration(
+                value.months.into(),
+                value.days.into(),
+                micros,
+            ))
+        }
+        DataType::Duration(unit) => {
+            let micros = match unit {
+                TimeUnit::Second => array
+                    .as_primitive::<DurationSecondType>()
+                    .value(index)
+                    .saturating_mul(1_000_000),
+                TimeUnit::Millisecond => array
+                    .as_primitive::<DurationMillisecondType>()
+                    .value(index)
+                    .saturating_mul(1_000),
+                TimeUnit::Microsecond => {
+                    array.as_primitive::<DurationMicrosecondType>().value(index)
+                }
+                TimeUnit::Nanosecond => {
+                    array.as_primitive::<DurationNanosecondType>().value(index) / 1_000
+                }
+            };
+            Value::String(iso8601_duration(0, 0, micros))
+        }
+        DataType::List(_) => json_array(array.as_list::<i32>().value(index).as_ref())?,
+        DataType::LargeList(_) => json_array(array.as_list::<i64>().value(index).as_ref())?,
+        DataType::FixedSizeList(..) => {
+            json_array(array.as_fixed_size_list().value(index).as_ref())?
+        }
+        DataType::Struct(fields) => {
+            let columns = array.as_struct().columns();
+            let mut object = serde_json::Map::with_capacity(fields.len());
+            for (field, column) in fields.iter().zip(columns) {
+                object.insert(field.name().clone(), json_value(column.as_ref(), index)?);
+            }
+            Value::Object(object)
+        }
+        DataType::Map(..) => {
+            let entries = array.as_map().value(index);
+            let mut object = serde_json::Map::with_capacity(entries.len());
+            for entry in 0..entries.len() {
+                let key = match json_value(entries.column(0).as_ref(), entry)? {
+                    Value::String(key) => key,
+                    key => key.to_string(),
+                };
+                object.insert(key, json_value(entries.column(1).as_ref(), entry)?);
+            }
+            Value::Object(object)
+        }
+        DataType::Dictionary(key, _) => {
+            let dictionary = array.as_any_dictionary();
+            let keys = dictionary.keys();
+            let key = match key.as_ref() {
+                DataType::Int8 => {
+                    usize::try_from(keys.as_primitive::<Int8Type>().value(index)).ok()
+                }
+                DataType::Int16 => {
+                    usize::try_from(keys.as_primitive::<Int16Type>().value(index)).ok()
+                }
+                DataType::Int32 => {
+                    usize::try_from(keys.as_primitive::<Int32Type>().value(index)).ok()
+                }
+                DataType::Int64 => {
+                    usize::try_from(keys.as_primitive::<Int64Type>().value(index)).ok()
+                }
+                DataType::UInt8 => Some(keys.as_primitive::<UInt8Type>().value(index).into()),
+                DataType::UInt16 => Some(keys.as_primitive::<UInt16Type>().value(index).into()),
+                DataType::UInt32 => {
+                    usize::try_from(keys.as_primitive::<UInt32Type>().value(index)).ok()
+                }
+                DataType::UInt64 => {
+                    usize::try_from(keys.as_primitive::<UInt64Type>().value(index)).ok()
+                }
+                other => return Err(format!("unsupported dictionary key type `{other}`")),
+            }
+            .ok_or("invalid dictionary key")?;
+            json_value(dictionary.values().as_ref(), key)?
+        }
+        other => return Err(format!("unsupported Arrow type `{other}`")),
+    };
+    Ok(value)
+}
+
+fn json_array(array: &dyn Array) -> std::result::Result<Value, String> {
+    (0..array.len())
+        .map(|index| json_value(array, index))
+        .collect::<std::result::Result<_, _>>()
+        .map(Value::Array)
+}
+
+/// NaN 和无穷大无法表示为 JSON 数字，转为字符串
+fn json_float(value: f64) -> Value {
+    serde_json::Number::from_f64(value)
+        .map(Value::Number)
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+fn base64(bytes: &[u8]) -> Value {
+    Value::String(STANDARD.encode(bytes))
+}
+
+fn date(date: Option<NaiveDate>) -> std::result::Result<Value, String> {
+    date.map(|date| Value::String(date.to_string()))
+        .ok_or_else(|| "date out of range".to_string())
+}
+
+fn time(time: Option<NaiveTime>) -> std::result::Result<Value, String> {
+    time.map(|time| Value::String(time.to_string()))
+        .ok_or_else(|| "time out of range".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn column(name: &str, data_type: &str) -> ColumnInfo {
+        ColumnInfo {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_data_type_mapping() {
+        assert_eq!(data_type("int4"), DataType::Int32);
+        assert_eq!(data_type("BIGINT"), DataType::Int64);
+        assert_eq!(data_type("numeric(10,2)"), DataType::Decimal128(10, 2));
+        assert_eq!(data_type("numeric(50, 5)"), DataType::Decimal256(50, 5));
+        assert_eq!(data_type("numeric"), DataType::Utf8);
+        assert_eq!(
+            data_type("timestamptz"),
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+        );
+        assert_eq!(
+            data_type("int4[][]"),
+            DataType::List(list_item(DataType::List(list_item(DataType::Int32))))
+        );
+        assert_eq!(data_type("jsonb"), DataType::Utf8);
+        assert_eq!(data_type("\"char\""), DataType::Int8);
+        assert_eq!(data_type("char"), DataType::Utf8);
+        assert_eq!(data_type("bigint unsigned"), DataType::UInt64);
+        assert_eq!(data_type("decimal(65,30)"), DataType::Decimal256(65, 30));
+        assert_eq!(data_type("bit(1)"), DataType::Boolean);
+        assert_eq!(data_type("bit(12)"), DataType::UInt64);
+        assert_eq!(data_type("varbinary"), DataType::Binary);
+        assert_eq!(
+            data_type("datetime"),
+            DataType::Timestamp(TimeUnit::Microsecond, None)
+        );
+    }
+
+    #[test]
+    fn test_batch_rows_round_trip() {
+        let columns = [
+            column("id", "bigint unsigned"),
+            column("amount", "decimal(10,2)"),
+            column("at", "timestamptz"),
+            column("day", "date"),
+            column("payload", "blob"),
+            column("tags", "varchar[]"),
+        ];
+        let rows = vec![
+            json!([
+                u64::MAX,
+                "-12.50",
+                "2024-01-02T03:04:05.123+00:00",
+                "2024-01-02",
+                "AP8=",
+                ["a", null]
+            ]),
+            json!([null, null, null, null, null, null]),
+        ];
+        let schema = schema_with_max_rows(&columns, None);
+        let batch = record_batch(&schema, &rows).unwrap();
+        assert_eq!(batch_rows(&batch).unwrap(), rows);
+        let names = schema
+            .fields()
+            .iter()
+            .map(|field| type_name(field.data_type()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "bigint unsigned",
+                "decimal(10,2)",
+                "timestamptz",
+                "date",
+                "blob",
+                "varchar[]"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_batch_rows_nested_and_interval() {
+        use arrow_array::{DictionaryArray, IntervalMonthDayNanoArray, StructArray};
+        use arrow_buffer::IntervalMonthDayNano;
+
+        let dictionary: DictionaryArray<Int8Type> = vec!["x", "y", "x"].into_iter().collect();
+        let interval = IntervalMonthDayNanoArray::from(vec![
+            IntervalMonthDayNano::new(14, 3, 5_500_000_000),
+            IntervalMonthDayNano::new(0, 0, 0),
+            IntervalMonthDayNano::new(0, -1, 0),
+        ]);
+        let point = StructArray::from(vec![(
+            Arc::new(Field::new("x", DataType::Int32, true)),
+            Arc::new(arrow_array::Int32Array::from(vec![1, 2, 3])) as ArrayRef,
+        )]);
+        let batch = RecordBatch::try_from_iter([
+            ("kind", Arc::new(dictionary) as ArrayRef),
+            ("span", Arc::new(interval) as ArrayRef),
+            ("point", Arc::new(point) as ArrayRef),
+        ])
+        .unwrap();
+        assert_eq!(
+            batch_rows(&batch).unwrap(),
+            vec![
+                json!(["x", "P1Y2M3DT5.5S", {"x": 1}]),
+                json!(["y", "PT0S", {"x": 2}]),
+                json!(["x", "P-1D", {"x": 3}]),
+            ]
+        );
+        assert_eq!(type_name(batch.schema().field(0).data_type()), "varchar");
+    }
+
+    #[test]
+    fn test_record_batch() {
+        let schema = Arc::new(schema(&[
+            column("id", "int4"),
+            column("amount", "numeric(10,2)"),
+            column("created_at", "timestamptz"),
+            column("tags", "int4[]"),
+            column("payload", "jsonb"),
+        ]));
+        let rows = vec![
+            json!([1, "12.5", "2024-01-02T03:04:05.000006+00:00", [1, null, 3], {"a": 1}]),
+            json!([null, "-0.01", null, null, "text"]),
+        ];
+        let batch = record_batch(&schema, &rows).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+
+        let ids = batch.column(0).as_primitive::<Int32Type>();
+        assert_eq!(ids.value(0), 1);
+        assert!(ids.is_null(1));
+
+        let amounts = batch.column(1).as_primitive::<Decimal128Type>();
+        assert_eq!(amounts.value(0), 1250);
+        assert_eq!(amounts.value(1), -1);
+
+        let created = batch.column(2).as_primitive::<TimestampMicrosecondType>();
+        assert_eq!(created.value(0), 1_704_164_645_000_006);
+
+        let tags = batch.column(3).as_list::<i32>();
+        let first = tags.value(0);
+        let first = first.as_primitive::<Int32Type>();
+        assert_eq!(first.len(), 3);
+        assert!(first.is_null(1));
+        assert!(tags.is_null(1));
+
+        let payload = batch.column(4).as_string::<i32>();
+        assert_eq!(payload.value(0), r#"{"a":1}"#);
+        assert_eq!(payload.value(1), "text");
+    }
+
+    #[test]
+    fn test_decimal_digits() {
+        assert_eq!(decimal_digits(&json!("1.5"), 10, 2).unwrap(), "150");
+        assert_eq!(decimal_digits(&json!("-0.00"), 10, 2).unwrap(), "0");
+        assert_eq!(decimal_digits(&json!(7), 10, 0).unwrap(), "7");
+        assert_eq!(decimal_digits(&json!("1200"), 10, -2).unwrap(), "12");
+        assert!(decimal_digits(&json!("1.234"), 10, 2).is_err());
+        assert!(decimal_digits(&json!("123456"), 4, 0).is_err());
+        assert!(decimal_digits(&json!("NaN"), 10, 2).is_err());
+    }
+
+    #[test]
+    fn test_conversion_error_names_column() {
+        let schema = Arc::new(schema(&[column("id", "int2")]));
+        let err = record_batch(&schema, &[json!([100000])]).unwrap_err();
+        assert!(err.to_string().contains("`id`"), "{err}");
+    }
+}
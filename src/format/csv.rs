@@ -0,0 +1,119 @@
+//! CSV 格式 - 按 RFC 4180 输出查询结果
+//!
+//! 记录以 CRLF 结尾；包含分隔符、双引号或换行的字段用双引号括起，字段中的双引号写两次。
+//! NULL 按 `CsvOptions::null` 输出，与之相同的字符串加引号以便区分。
+//! 字符串原样输出，数字和布尔值输出字面量，数组和对象输出 JSON 文本。
+
+use serde_json::Value;
+
+use crate::model::{ColumnInfo, CsvOptions};
+
+/// CSV 写入器
+pub struct CsvWriter {
+    options: CsvOptions,
+}
+
+impl CsvWriter {
+    pub fn new(options: CsvOptions) -> Self {
+        Self { options }
+    }
+
+    /// 列名组成的首行，未启用 `header` 时为空
+    pub fn header(&self, columns: &[ColumnInfo]) -> Vec<u8> {
+        let mut buf = String::new();
+        if self.options.header {
+            let names = columns.iter().map(|c| Value::String(c.name.clone()));
+            self.write_record(&mut buf, names);
+        }
+        buf.into_bytes()
+    }
+
+    /// 写入一批行，每行是与列顺序一致的数组
+    pub fn write(&self, rows: &[Value]) -> Vec<u8> {
+        let mut buf = String::new();
+        for row in rows {
+            let fields = row
+                .as_array()
+                .map_or(std::slice::from_ref(row), Vec::as_slice);
+            self.write_record(&mut buf, fields.iter().cloned());
+        }
+        buf.into_bytes()
+    }
+
+    fn write_record(&self, buf: &mut String, fields: impl Iterator<Item = Value>) {
+        for (index, field) in fields.enumerate() {
+            if index > 0 {
+                buf.push(self.options.delimiter);
+            }
+            match field {
+                Value::Null => buf.push_str(&self.options.null),
+                Value::String(s) => self.write_field(buf, &s),
+                Value::Number(_) | Value::Bool(_) => buf.push_str(&field.to_string()),
+                other => self.write_field(buf, &other.to_string()),
+            }
+        }
+        buf.push_str("\r\n");
+    }
+
+    fn write_field(&self, buf: &mut String, field: &str) {
+        let quote = field == self.options.null
+            || field
+                .chars()
+                .any(|c| c == self.options.delimiter || matches!(c, '"' | '\r' | '\n'));
+        if quote {
+            buf.push('"');
+            buf.push_str(&field.replace('"', "\"\""));
+            buf.push('"');
+        } else {
+            buf.push_str(field);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn columns() -> Vec<ColumnInfo> {
+        ["id", "name"]
+            .into_iter()
+            .map(|name| ColumnInfo {
+                name: name.to_string(),
+                data_type: "text".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rfc4180_quoting() {
+        let writer = CsvWriter::new(CsvOptions::default());
+        assert_eq!(writer.header(&columns()), b"id,name\r\n");
+        let rows = [
+            json!([1, "plain"]),
+            json!([2, "a,b"]),
+            json!([3, "say \"hi\"\nbye"]),
+            json!([null, ""]),
+            json!([true, {"k": [1]}]),
+        ];
+        assert_eq!(
+            String::from_utf8(writer.write(&rows)).unwrap(),
+            "1,plain\r\n2,\"a,b\"\r\n3,\"say \"\"hi\"\"\nbye\"\r\n,\"\"\r\ntrue,\"{\"\"k\"\":[1]}\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_options() {
+        let writer = CsvWriter::new(CsvOptions {
+            delimiter: ';',
+            header: false,
+            null: "NULL".to_string(),
+        });
+        assert!(writer.header(&columns()).is_empty());
+        let rows = [json!([null, "a,b;c"]), json!(["NULL", ""])];
+        assert_eq!(
+            String::from_utf8(writer.write(&rows)).unwrap(),
+            "NULL;\"a,b;c\"\r\n\"NULL\";\r\n"
+        );
+    }
+}
@@ -0,0 +1,47 @@
+//! 结果格式 - 查询结果在 JSON 以外的输出格式
+
+pub mod arrow;
+pub mod csv;
+pub mod parquet;
+
+/// 时间间隔的 ISO 8601 时长表示，如 `P1Y2M3DT4H5M6.5S`，零时长为 `PT0S`
+pub fn iso8601_duration(months: i64, days: i64, micros: i64) -> String {
+    let mut result = "P".to_string();
+    let (years, months) = (months / 12, months % 12);
+    for (value, unit) in [(years, 'Y'), (months, 'M'), (days, 'D')] {
+        if value != 0 {
+            result.push_str(&format!("{value}{unit}"));
+        }
+    }
+
+    if micros != 0 {
+        result.push('T');
+        let hours = micros / 3_600_000_000;
+        let minutes = micros % 3_600_000_000 / 60_000_000;
+        let seconds = micros % 60_000_000;
+        if hours != 0 {
+            result.push_str(&format!("{hours}H"));
+        }
+        if minutes != 0 {
+            result.push_str(&format!("{minutes}M"));
+        }
+        if seconds != 0 {
+            let whole = seconds / 1_000_000;
+            let fraction = (seconds % 1_000_000).abs();
+            if fraction == 0 {
+                result.push_str(&format!("{whole}S"));
+            } else {
+                let sign = if seconds < 0 && whole == 0 { "-" } else { "" };
+                let fraction = format!("{fraction:06}");
+                result.push_str(&format!(
+                    "{sign}{whole}.{}S",
+                    fraction.trim_end_matches('0')
+                ));
+            }
+        }
+    }
+    if result == "P" {
+        result.push_str("T0S");
+    }
+    result
+}
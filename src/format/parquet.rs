@@ -0,0 +1,107 @@
+//! Parquet 格式 - 按 `format::arrow` 的类型映射把查询结果写为 Parquet 文件
+//!
+//! 行按行组缓冲，行组写满后即可发出对应的字节；文件的元数据在末尾写入，
+//! 其中的 `truncated` 表示结果是否因行数上限被截断。
+
+use arrow_schema::SchemaRef;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::metadata::KeyValue;
+use parquet::file::properties::WriterProperties;
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+use crate::format::arrow::record_batch;
+
+/// 每个行组的最大行数，决定写入时缓冲的数据量
+const ROW_GROUP_SIZE: usize = 128 * 1024;
+
+/// 文件元数据中标记结果被截断的键
+pub const TRUNCATED_METADATA: &str = "truncated";
+
+/// Parquet 写入器
+///
+/// 每次写入后返回新编码的字节，调用方可以逐段发出
+pub struct ParquetWriter {
+    schema: SchemaRef,
+    writer: ArrowWriter<Vec<u8>>,
+}
+
+impl ParquetWriter {
+    pub fn try_new(schema: SchemaRef) -> Result<Self> {
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_size(ROW_GROUP_SIZE)
+            .build();
+        let writer = ArrowWriter::try_new(Vec::new(), schema.clone(), Some(properties))
+            .map_err(parquet_error)?;
+        Ok(Self { schema, writer })
+    }
+
+    /// 写入一批行，行组未写满时不产生字节
+    pub fn write(&mut self, rows: &[Value]) -> Result<Vec<u8>> {
+        if !rows.is_empty() {
+            let batch = record_batch(&self.schema, rows)?;
+            self.writer.write(&batch).map_err(parquet_error)?;
+        }
+        Ok(self.take())
+    }
+
+    /// 写入剩余的行和文件元数据
+    pub fn finish(&mut self, truncated: bool) -> Result<Vec<u8>> {
+        self.writer.append_key_value_metadata(KeyValue::new(
+            TRUNCATED_METADATA.to_string(),
+            truncated.to_string(),
+        ));
+        self.writer.finish().map_err(parquet_error)?;
+        Ok(self.take())
+    }
+
+    fn take(&mut self) -> Vec<u8> {
+        std::mem::take(self.writer.inner_mut())
+    }
+}
+
+fn parquet_error(err: ParquetError) -> Error {
+    Error::Connector(format!("failed to encode Parquet file: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::arrow::schema_with_max_rows;
+    use crate::model::ColumnInfo;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::Decimal128Type;
+    use arrow_array::Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use serde_json::json;
+
+    #[test]
+    fn test_parquet_round_trip() {
+        let columns = vec![ColumnInfo {
+            name: "amount".to_string(),
+            data_type: "numeric(10,2)".to_string(),
+        }];
+        let mut writer = ParquetWriter::try_new(schema_with_max_rows(&columns, Some(2))).unwrap();
+        let mut file = writer.write(&[json!(["1.50"]), json!([null])]).unwrap();
+        file.extend(writer.finish(true).unwrap());
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(file)).unwrap();
+        let metadata = builder
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .unwrap();
+        assert!(metadata
+            .iter()
+            .any(|kv| kv.key == TRUNCATED_METADATA && kv.value.as_deref() == Some("true")));
+        assert_eq!(builder.schema().metadata()["max_rows"], "2");
+
+        let batch = builder.build().unwrap().next().unwrap().unwrap();
+        let amounts = batch.column(0).as_primitive::<Decimal128Type>();
+        assert_eq!(amounts.value(0), 150);
+        assert!(amounts.is_null(1));
+    }
+}
@@ -8,6 +8,7 @@ pub mod config;
 pub mod connector;
 pub mod engine;
 pub mod error;
+pub mod format;
 pub mod mdl;
 pub mod model;
 
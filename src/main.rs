@@ -2,32 +2,40 @@
 //!
 //! A semantic layer engine service built with Rust and Axum.
 
-use mimir_well_engine::api::router;
-use mimir_well_engine::config::Settings;
+use clap::Parser;
+use mimir_well_engine::api::{router, serve, shutdown_signal, AppState};
+use mimir_well_engine::config::{CliArgs, LogFormat, Settings};
 use std::net::SocketAddr;
 use tracing::info;
+use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-        .init();
+    // Load configuration: defaults -> config file -> environment -> CLI
+    let settings = Settings::load(&CliArgs::parse())?;
 
-    // Load configuration
-    let settings = Settings::default();
-    let addr = SocketAddr::from(([0, 0, 0, 0], settings.server.port));
+    // Initialize tracing, RUST_LOG takes precedence over the configured level
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(&settings.logging.level));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    match settings.logging.format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+
+    let addr = SocketAddr::new(settings.server.host, settings.server.port);
 
     info!("Starting Mimir Well Engine server on {}", addr);
 
     // Build application with routes
-    let app = router();
+    let state = AppState::from_settings(settings);
+    let app = router(state.clone());
 
-    // Start server
+    // Start server, draining in-flight requests on SIGINT / SIGTERM
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     info!("Server listening on {}", addr);
 
-    axum::serve(listener, app).await?;
+    serve(listener, app, state, shutdown_signal()).await?;
 
     Ok(())
 }
@@ -0,0 +1,304 @@
+//! 分析后的 MDL
+//!
+//! 参考 wren-engine 的 `AnalyzedWrenMDL`：在原始 manifest 之上建立按规范化名称
+//! （小写）索引的 model / column / relationship，并计算列级血缘。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::mdl::lineage::Lineage;
+use crate::mdl::manifest::{Column, Manifest, Metric, Model, Relationship, View};
+use crate::mdl::validator::validate_manifest;
+
+/// 规范化对象名称（大小写不敏感）
+pub fn normalize_name(name: &str) -> String {
+    name.to_lowercase()
+}
+
+/// 分析后的 MDL，引擎各组件共享
+#[derive(Debug)]
+pub struct AnalyzedMdl {
+    manifest: Arc<Manifest>,
+    models: HashMap<String, Arc<Model>>,
+    columns: HashMap<String, HashMap<String, Arc<Column>>>,
+    relationships: HashMap<String, Arc<Relationship>>,
+    metrics: HashMap<String, Arc<Metric>>,
+    views: HashMap<String, Arc<View>>,
+    lineage: Arc<Lineage>,
+}
+
+/// 经过 relationship 的一跳
+#[derive(Debug, Clone)]
+pub struct RelationshipHop {
+    /// 出发 model
+    pub from: Arc<Model>,
+    /// 出发 model 上的关系列
+    pub column: Arc<Column>,
+    pub relationship: Arc<Relationship>,
+    /// 目标 model
+    pub to: Arc<Model>,
+}
+
+/// 解析后的列路径，如 `customer.nation.name`
+#[derive(Debug, Clone)]
+pub struct ResolvedPath {
+    /// 依次经过的 relationship
+    pub hops: Vec<RelationshipHop>,
+    /// 最终列所在的 model
+    pub model: Arc<Model>,
+    /// 最终引用的列
+    pub column: Arc<Column>,
+}
+
+impl AnalyzedMdl {
+    /// 校验并分析 manifest
+    pub fn analyze(manifest: Arc<Manifest>) -> Result<Self> {
+        validate_manifest(&manifest)?;
+
+        let models = manifest
+            .models
+            .iter()
+            .map(|m| (normalize_name(&m.name), Arc::clone(m)))
+            .collect();
+        let columns = manifest
+            .models
+            .iter()
+            .map(|m| {
+                let columns = m
+                    .columns
+                    .iter()
+                    .map(|c| (normalize_name(&c.name), Arc::clone(c)))
+                    .collect();
+                (normalize_name(&m.name), columns)
+            })
+            .collect();
+        let relationships = manifest
+            .relationships
+            .iter()
+            .map(|r| (normalize_name(&r.name), Arc::clone(r)))
+            .collect();
+        let metrics = manifest
+            .metrics
+            .iter()
+            .map(|m| (normalize_name(&m.name), Arc::clone(m)))
+            .collect();
+        let views = manifest
+            .views
+            .iter()
+            .map(|v| (normalize_name(&v.name), Arc::clone(v)))
+            .collect();
+
+        let mut analyzed = Self {
+            manifest,
+            models,
+            columns,
+            relationships,
+            metrics,
+            views,
+            lineage: Arc::default(),
+        };
+        analyzed.lineage = Arc::new(Lineage::build(&analyzed)?);
+        Ok(analyzed)
+    }
+
+    /// 原始 manifest
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// 列级血缘
+    pub fn lineage(&self) -> &Arc<Lineage> {
+        &self.lineage
+    }
+
+    pub fn model(&self, name: &str) -> Option<&Arc<Model>> {
+        self.models.get(&normalize_name(name))
+    }
+
+    pub fn column(&self, model: &str, column: &str) -> Option<&Arc<Column>> {
+        self.columns
+            .get(&normalize_name(model))
+            .and_then(|columns| columns.get(&normalize_name(column)))
+    }
+
+    pub fn relationship(&self, name: &str) -> Option<&Arc<Relationship>> {
+        self.relationships.get(&normalize_name(name))
+    }
+
+    pub fn metric(&self, name: &str) -> Option<&Arc<Metric>> {
+        self.metrics.get(&normalize_name(name))
+    }
+
+    pub fn view(&self, name: &str) -> Option<&Arc<View>> {
+        self.views.get(&normalize_name(name))
+    }
+
+    /// 关系列指向的 relationship 和目标 model
+    pub fn relationship_target(
+        &self,
+        model: &Model,
+        column: &Column,
+    ) -> Option<(Arc<Relationship>, Arc<Model>)> {
+        let relationship = self.relationship(column.relationship.as_deref()?)?;
+        // 关系列的 type 即目标 model；自关联时目标和自身相同
+        let target = self.model(&column.r#type).or_else(|| {
+            relationship
+                .models
+                .iter()
+                .find(|m| normalize_name(m) != normalize_name(&model.name))
+                .and_then(|m| self.model(m))
+        })?;
+        Some((Arc::clone(relationship), Arc::clone(target)))
+    }
+
+    /// 从 `model` 出发解析列路径
+    ///
+    /// 路径中除最后一段外都必须是关系列；允许用 model 自身名称限定列（`orders.o_orderkey`）
+    pub fn resolve_path(&self, model: &Arc<Model>, path: &[String]) -> Result<ResolvedPath> {
+        let mut parts = path;
+        if parts.len() > 1
+            && normalize_name(&parts[0]) == normalize_name(&model.name)
+            && self.column(&model.name, &parts[0]).is_none()
+        {
+            parts = &parts[1..];
+        }
+
+        let mut hops = Vec::new();
+        let mut current = Arc::clone(model);
+        for (index, part) in parts.iter().enumerate() {
+            let column = self.column(&current.name, part).ok_or_else(|| {
+                Error::mdl(format!(
+                    "column `{part}` does not exist in model `{}`",
+                    current.name
+                ))
+            })?;
+
+            if index == parts.len() - 1 {
+                return Ok(ResolvedPath {
+                    hops,
+                    model: current,
+                    column: Arc::clone(column),
+                });
+            }
+
+            let (relationship, target) =
+                self.relationship_target(&current, column).ok_or_else(|| {
+                    Error::mdl(format!(
+                        "column `{}.{part}` is not a relationship column",
+                        current.name
+                    ))
+                })?;
+            hops.push(RelationshipHop {
+                from: Arc::clone(&current),
+                column: Arc::clone(column),
+                relationship,
+                to: Arc::clone(&target),
+            });
+            current = target;
+        }
+
+        Err(Error::mdl("empty column path".to_string()))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::mdl::decode_manifest;
+
+    pub(crate) const TPCH: &str = r#"{
+        "catalog": "wren",
+        "schema": "public",
+        "models": [
+            {
+                "name": "Orders",
+                "tableReference": { "schema": "tpch", "table": "orders" },
+                "primaryKey": "o_orderkey",
+                "columns": [
+                    { "name": "o_orderkey", "type": "integer" },
+                    { "name": "o_custkey", "type": "integer" },
+                    { "name": "customer", "type": "Customer", "relationship": "orders_customer" },
+                    { "name": "customer_name", "type": "varchar", "isCalculated": true, "expression": "customer.c_name" },
+                    { "name": "nation_name", "type": "varchar", "isCalculated": true, "expression": "customer.nation.n_name" }
+                ]
+            },
+            {
+                "name": "Customer",
+                "tableReference": { "schema": "tpch", "table": "customer" },
+                "primaryKey": "c_custkey",
+                "columns": [
+                    { "name": "c_custkey", "type": "integer" },
+                    { "name": "c_name", "type": "varchar" },
+                    { "name": "c_nationkey", "type": "integer" },
+                    { "name": "nation", "type": "nation", "relationship": "customer_nation" }
+                ]
+            },
+            {
+                "name": "nation",
+                "tableReference": { "schema": "tpch", "table": "nation" },
+                "columns": [
+                    { "name": "n_nationkey", "type": "integer" },
+                    { "name": "n_name", "type": "varchar" }
+                ]
+            }
+        ],
+        "relationships": [
+            { "name": "orders_customer", "models": ["Orders", "Customer"], "joinType": "MANY_TO_ONE", "condition": "Orders.o_custkey = Customer.c_custkey" },
+            { "name": "customer_nation", "models": ["Customer", "nation"], "joinType": "MANY_TO_ONE", "condition": "Customer.c_nationkey = nation.n_nationkey" }
+        ]
+    }"#;
+
+    fn analyzed() -> AnalyzedMdl {
+        AnalyzedMdl::analyze(Arc::new(decode_manifest(TPCH).unwrap())).unwrap()
+    }
+
+    #[test]
+    fn test_lookup_by_normalized_name() {
+        let mdl = analyzed();
+        assert_eq!(mdl.model("orders").unwrap().name, "Orders");
+        assert_eq!(mdl.model("ORDERS").unwrap().name, "Orders");
+        assert_eq!(mdl.column("orders", "O_CUSTKEY").unwrap().name, "o_custkey");
+        assert!(mdl.relationship("Orders_Customer").is_some());
+        assert!(mdl.column("orders", "missing").is_none());
+    }
+
+    #[test]
+    fn test_resolve_multi_hop_path() {
+        let mdl = analyzed();
+        let orders = mdl.model("orders").unwrap();
+        let path = ["customer", "nation", "n_name"].map(String::from);
+        let resolved = mdl.resolve_path(orders, &path).unwrap();
+
+        assert_eq!(resolved.model.name, "nation");
+        assert_eq!(resolved.column.name, "n_name");
+        let hops = resolved
+            .hops
+            .iter()
+            .map(|h| h.relationship.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(hops, vec!["orders_customer", "customer_nation"]);
+    }
+
+    #[test]
+    fn test_resolve_path_errors() {
+        let mdl = analyzed();
+        let orders = mdl.model("orders").unwrap();
+
+        let qualified = ["orders", "o_orderkey"].map(String::from);
+        assert_eq!(
+            mdl.resolve_path(orders, &qualified).unwrap().column.name,
+            "o_orderkey"
+        );
+
+        let not_relationship = ["o_custkey", "c_name"].map(String::from);
+        assert!(mdl.resolve_path(orders, &not_relationship).is_err());
+    }
+
+    #[test]
+    fn test_analyze_rejects_invalid_manifest() {
+        let json = TPCH.replace(r#""primaryKey": "c_custkey""#, r#""primaryKey": "id""#);
+        let err = AnalyzedMdl::analyze(Arc::new(decode_manifest(&json).unwrap())).unwrap_err();
+        assert!(matches!(err, Error::MdlValidation { .. }));
+    }
+}
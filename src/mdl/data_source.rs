@@ -0,0 +1,64 @@
+//! DataSource 相关实现
+//!
+//! 路径参数和配置中的数据源名称大小写不敏感，如 `postgres`、`POSTGRES`
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::mdl::manifest::DataSource;
+
+impl DataSource {
+    /// 小写的数据源名称，与 API 路径中的写法一致
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataSource::MySQL => "mysql",
+            DataSource::Datafusion => "datafusion",
+            DataSource::Postgres => "postgres",
+            DataSource::DuckDB => "duckdb",
+            DataSource::SQLite => "sqlite",
+        }
+    }
+}
+
+impl Display for DataSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DataSource {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mysql" => Ok(DataSource::MySQL),
+            "datafusion" => Ok(DataSource::Datafusion),
+            "postgres" | "postgresql" => Ok(DataSource::Postgres),
+            "duckdb" => Ok(DataSource::DuckDB),
+            "sqlite" | "sqlite3" => Ok(DataSource::SQLite),
+            _ => Err(Error::Validation(format!("unknown data source `{s}`"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_data_source() {
+        assert_eq!(
+            "POSTGRES".parse::<DataSource>().unwrap(),
+            DataSource::Postgres
+        );
+        assert_eq!(
+            "postgresql".parse::<DataSource>().unwrap(),
+            DataSource::Postgres
+        );
+        assert_eq!("DuckDB".parse::<DataSource>().unwrap(), DataSource::DuckDB);
+        assert_eq!("sqlite3".parse::<DataSource>().unwrap(), DataSource::SQLite);
+        assert!("oracle".parse::<DataSource>().is_err());
+        assert_eq!(DataSource::MySQL.to_string(), "mysql");
+    }
+}
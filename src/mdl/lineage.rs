@@ -0,0 +1,343 @@
+//! 列级血缘
+//!
+//! 参考 wren-engine 的 `Lineage`：记录每个计算列、关系列直接依赖哪些列，
+//! 并在构建时检测循环依赖。
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::mdl::analyzed::AnalyzedMdl;
+use crate::mdl::manifest::{Column, Model};
+use crate::mdl::utils::{column_references, parse_expr};
+
+/// 限定列名 `model.column`，使用 manifest 中声明的原始名称
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ColumnRef {
+    pub model: String,
+    pub column: String,
+}
+
+impl ColumnRef {
+    pub fn new(model: impl Into<String>, column: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            column: column.into(),
+        }
+    }
+}
+
+impl Display for ColumnRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.model, self.column)
+    }
+}
+
+/// 列级血缘图
+#[derive(Debug, Default)]
+pub struct Lineage {
+    /// 派生列 -> 直接依赖的列
+    source_columns: BTreeMap<ColumnRef, BTreeSet<ColumnRef>>,
+    /// 列 -> 直接依赖它的派生列
+    dependents: BTreeMap<ColumnRef, BTreeSet<ColumnRef>>,
+    /// 派生列 -> 计算时需要经过的 relationship
+    required_relationships: BTreeMap<ColumnRef, BTreeSet<String>>,
+}
+
+impl Lineage {
+    pub(crate) fn build(mdl: &AnalyzedMdl) -> Result<Self> {
+        let mut lineage = Self::default();
+
+        for model in &mdl.manifest().models {
+            for column in &model.columns {
+                let target = ColumnRef::new(&model.name, &column.name);
+                if column.is_calculated {
+                    lineage.add_calculated_column(mdl, model, column, &target)?;
+                } else if column.relationship.is_some() {
+                    lineage.add_relationship_column(mdl, model, column, &target)?;
+                }
+            }
+        }
+
+        lineage.check_cycles()?;
+        Ok(lineage)
+    }
+
+    fn add_calculated_column(
+        &mut self,
+        mdl: &AnalyzedMdl,
+        model: &Arc<Model>,
+        column: &Column,
+        target: &ColumnRef,
+    ) -> Result<()> {
+        let Some(expression) = &column.expression else {
+            return Ok(());
+        };
+        let expr = parse_expr(expression)
+            .map_err(|e| Error::mdl(format!("failed to parse expression of `{target}`: {e}")))?;
+
+        for path in column_references(&expr) {
+            let resolved = mdl
+                .resolve_path(model, &path)
+                .map_err(|e| Error::mdl(format!("invalid expression of `{target}`: {e}")))?;
+            for hop in &resolved.hops {
+                self.add_edge(target, ColumnRef::new(&hop.from.name, &hop.column.name));
+                self.required_relationships
+                    .entry(target.clone())
+                    .or_default()
+                    .insert(hop.relationship.name.clone());
+            }
+            self.add_edge(
+                target,
+                ColumnRef::new(&resolved.model.name, &resolved.column.name),
+            );
+        }
+        Ok(())
+    }
+
+    /// 关系列依赖 relationship 条件两侧的连接键
+    fn add_relationship_column(
+        &mut self,
+        mdl: &AnalyzedMdl,
+        model: &Model,
+        column: &Column,
+        target: &ColumnRef,
+    ) -> Result<()> {
+        let Some((relationship, _)) = mdl.relationship_target(model, column) else {
+            return Ok(());
+        };
+        let condition = parse_expr(&relationship.condition).map_err(|e| {
+            Error::mdl(format!(
+                "failed to parse condition of relationship `{}`: {e}",
+                relationship.name
+            ))
+        })?;
+
+        for path in column_references(&condition) {
+            if let [qualifier, name] = path.as_slice() {
+                if let (Some(m), Some(c)) = (mdl.model(qualifier), mdl.column(qualifier, name)) {
+                    self.add_edge(target, ColumnRef::new(&m.name, &c.name));
+                }
+            }
+        }
+        self.required_relationships
+            .entry(target.clone())
+            .or_default()
+            .insert(relationship.name.clone());
+        Ok(())
+    }
+
+    fn add_edge(&mut self, target: &ColumnRef, source: ColumnRef) {
+        self.dependents
+            .entry(source.clone())
+            .or_default()
+            .insert(target.clone());
+        self.source_columns
+            .entry(target.clone())
+            .or_default()
+            .insert(source);
+    }
+
+    /// 深度优先检测循环依赖，错误信息中列出完整的环
+    fn check_cycles(&self) -> Result<()> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            Visiting,
+            Done,
+        }
+
+        fn visit<'a>(
+            lineage: &'a Lineage,
+            column: &'a ColumnRef,
+            states: &mut BTreeMap<&'a ColumnRef, State>,
+            stack: &mut Vec<&'a ColumnRef>,
+        ) -> Result<()> {
+            match states.get(column) {
+                Some(State::Done) => return Ok(()),
+                Some(State::Visiting) => {
+                    let start = stack.iter().position(|c| *c == column).unwrap_or(0);
+                    let cycle = stack[start..]
+                        .iter()
+                        .chain(std::iter::once(&column))
+                        .map(|c| c.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" -> ");
+                    return Err(Error::mdl(format!("circular column dependency: {cycle}")));
+                }
+                None => {}
+            }
+
+            states.insert(column, State::Visiting);
+            stack.push(column);
+            for source in lineage.source_columns.get(column).into_iter().flatten() {
+                visit(lineage, source, states, stack)?;
+            }
+            stack.pop();
+            states.insert(column, State::Done);
+            Ok(())
+        }
+
+        let mut states = BTreeMap::new();
+        let mut stack = Vec::new();
+        for column in self.source_columns.keys() {
+            visit(self, column, &mut states, &mut stack)?;
+        }
+        Ok(())
+    }
+
+    /// 直接依赖的列；非派生列返回 `None`
+    pub fn source_columns(&self, column: &ColumnRef) -> Option<&BTreeSet<ColumnRef>> {
+        self.source_columns.get(column)
+    }
+
+    /// 直接依赖该列的派生列
+    pub fn dependents(&self, column: &ColumnRef) -> Option<&BTreeSet<ColumnRef>> {
+        self.dependents.get(column)
+    }
+
+    /// 计算该列需要经过的 relationship
+    pub fn required_relationships(&self, column: &ColumnRef) -> Option<&BTreeSet<String>> {
+        self.required_relationships.get(column)
+    }
+
+    /// 递归展开后最终依赖的物理列（自身没有依赖的列）
+    pub fn required_source_columns(&self, column: &ColumnRef) -> BTreeSet<ColumnRef> {
+        let mut result = BTreeSet::new();
+        let mut pending = vec![column];
+        let mut seen = BTreeSet::new();
+        while let Some(current) = pending.pop() {
+            if !seen.insert(current) {
+                continue;
+            }
+            match self.source_columns.get(current) {
+                Some(sources) => pending.extend(sources),
+                None if current != column => {
+                    result.insert(current.clone());
+                }
+                None => {}
+            }
+        }
+        result
+    }
+
+    /// 递归展开后依赖的所有列，包括中间的派生列和关系列
+    pub fn upstream_columns(&self, column: &ColumnRef) -> BTreeSet<ColumnRef> {
+        let mut result = BTreeSet::new();
+        let mut pending = vec![column];
+        while let Some(current) = pending.pop() {
+            for source in self.source_columns.get(current).into_iter().flatten() {
+                if result.insert(source.clone()) {
+                    pending.push(source);
+                }
+            }
+        }
+        result
+    }
+
+    /// 所有派生列
+    pub fn derived_columns(&self) -> impl Iterator<Item = &ColumnRef> {
+        self.source_columns.keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdl::analyzed::tests::TPCH;
+    use crate::mdl::decode_manifest;
+
+    fn analyze(json: &str) -> Result<AnalyzedMdl> {
+        AnalyzedMdl::analyze(Arc::new(decode_manifest(json).unwrap()))
+    }
+
+    fn refs(columns: &[(&str, &str)]) -> BTreeSet<ColumnRef> {
+        columns
+            .iter()
+            .map(|(m, c)| ColumnRef::new(*m, *c))
+            .collect()
+    }
+
+    #[test]
+    fn test_relationship_column_lineage() {
+        let mdl = analyze(TPCH).unwrap();
+        let lineage = mdl.lineage();
+
+        assert_eq!(
+            lineage.source_columns(&ColumnRef::new("Orders", "customer")),
+            Some(&refs(&[("Orders", "o_custkey"), ("Customer", "c_custkey")]))
+        );
+        assert_eq!(
+            lineage.source_columns(&ColumnRef::new("Orders", "customer_name")),
+            Some(&refs(&[("Orders", "customer"), ("Customer", "c_name")]))
+        );
+        assert!(lineage
+            .source_columns(&ColumnRef::new("Orders", "o_orderkey"))
+            .is_none());
+    }
+
+    #[test]
+    fn test_multi_hop_lineage() {
+        let mdl = analyze(TPCH).unwrap();
+        let lineage = mdl.lineage();
+        let nation_name = ColumnRef::new("Orders", "nation_name");
+
+        assert_eq!(
+            lineage.required_source_columns(&nation_name),
+            refs(&[
+                ("Orders", "o_custkey"),
+                ("Customer", "c_custkey"),
+                ("Customer", "c_nationkey"),
+                ("nation", "n_nationkey"),
+                ("nation", "n_name"),
+            ])
+        );
+        assert_eq!(
+            lineage
+                .required_relationships(&nation_name)
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec!["customer_nation", "orders_customer"]
+        );
+        assert!(lineage
+            .dependents(&ColumnRef::new("nation", "n_name"))
+            .unwrap()
+            .contains(&nation_name));
+        assert_eq!(
+            lineage.upstream_columns(&nation_name).len(),
+            7,
+            "leaf columns plus Orders.customer and Customer.nation"
+        );
+    }
+
+    #[test]
+    fn test_cycle_detection_names_full_cycle() {
+        let json = TPCH.replace(
+            r#"{ "name": "o_orderkey", "type": "integer" },"#,
+            r#"{ "name": "o_orderkey", "type": "integer" },
+               { "name": "a", "type": "integer", "isCalculated": true, "expression": "b + 1" },
+               { "name": "b", "type": "integer", "isCalculated": true, "expression": "c * 2" },
+               { "name": "c", "type": "integer", "isCalculated": true, "expression": "a" },"#,
+        );
+        let Err(Error::Mdl { message, .. }) = analyze(&json) else {
+            panic!("expected MDL error");
+        };
+        assert_eq!(
+            message,
+            "circular column dependency: Orders.a -> Orders.b -> Orders.c -> Orders.a"
+        );
+    }
+
+    #[test]
+    fn test_unknown_column_in_expression() {
+        let json = TPCH.replace("customer.c_name", "customer.missing");
+        let Err(Error::Mdl { message, .. }) = analyze(&json) else {
+            panic!("expected MDL error");
+        };
+        assert!(message.contains("`Orders.customer_name`"), "{message}");
+        assert!(message.contains("`missing`"), "{message}");
+    }
+}
@@ -0,0 +1,178 @@
+//! MDL 加载器 - 将请求中的 manifest 字符串解码为 `Manifest`
+//!
+//! 支持的输入格式：
+//! - 标准 base64 / URL-safe base64（带或不带填充）
+//! - 解码后可选 gzip 或 zstd 压缩（通过魔数自动识别）
+//! - 直接传入的原始 JSON（以 `{` 开头）
+
+use std::io::Read;
+
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use base64::Engine;
+
+use crate::error::{Error, Result};
+use crate::mdl::manifest::Manifest;
+
+/// 解压后 manifest 的最大字节数，防止压缩炸弹
+const MAX_MANIFEST_SIZE: u64 = 64 * 1024 * 1024;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// 解码请求中的 manifest 字符串
+///
+/// 失败时返回 `Error::Mdl`，JSON 错误带有出错的字段路径和行列号
+pub fn decode_manifest(manifest_str: &str) -> Result<Manifest> {
+    let input = manifest_str.trim();
+    if input.is_empty() {
+        return Err(Error::mdl("manifest is empty".to_string()));
+    }
+
+    if input.starts_with('{') {
+        return parse_manifest_json(input.as_bytes());
+    }
+
+    let decoded = decode_base64(input)?;
+    let bytes = decompress(decoded)?;
+    parse_manifest_json(&bytes)
+}
+
+/// 按顺序尝试各种 base64 变体
+fn decode_base64(input: &str) -> Result<Vec<u8>> {
+    // 允许 manifest 中夹带换行（如 `base64` 命令行工具的输出）
+    let compact: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+
+    [&STANDARD, &STANDARD_NO_PAD, &URL_SAFE, &URL_SAFE_NO_PAD]
+        .iter()
+        .find_map(|engine| engine.decode(&compact).ok())
+        .ok_or_else(|| Error::mdl("manifest is neither valid base64 nor raw JSON".to_string()))
+}
+
+/// 根据魔数识别并解压 gzip / zstd，未压缩的数据原样返回
+fn decompress(bytes: Vec<u8>) -> Result<Vec<u8>> {
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let decoder = flate2::read::GzDecoder::new(bytes.as_slice());
+        read_bounded(decoder, "gzip")
+    } else if bytes.starts_with(&ZSTD_MAGIC) {
+        let decoder = zstd::stream::read::Decoder::new(bytes.as_slice())
+            .map_err(|e| Error::mdl(format!("failed to decompress zstd manifest: {e}")))?;
+        read_bounded(decoder, "zstd")
+    } else {
+        Ok(bytes)
+    }
+}
+
+fn read_bounded(reader: impl Read, format: &str) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    reader
+        .take(MAX_MANIFEST_SIZE + 1)
+        .read_to_end(&mut buf)
+        .map_err(|e| Error::mdl(format!("failed to decompress {format} manifest: {e}")))?;
+
+    if buf.len() as u64 > MAX_MANIFEST_SIZE {
+        return Err(Error::mdl(format!(
+            "decompressed manifest exceeds {MAX_MANIFEST_SIZE} bytes"
+        )));
+    }
+    Ok(buf)
+}
+
+/// 反序列化 manifest JSON，并在错误信息中附带 JSON 路径和行列号
+fn parse_manifest_json(bytes: &[u8]) -> Result<Manifest> {
+    let de = &mut serde_json::Deserializer::from_slice(bytes);
+    serde_path_to_error::deserialize(de).map_err(|e| {
+        let path = e.path().to_string();
+        let inner = e.inner();
+        Error::Mdl {
+            message: inner.to_string(),
+            path: (path != ".").then_some(path),
+            line: Some(inner.line() as u64),
+            column: Some(inner.column() as u64),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const MANIFEST: &str = r#"{
+        "catalog": "wren",
+        "schema": "public",
+        "models": [
+            {
+                "name": "orders",
+                "tableReference": { "schema": "public", "table": "orders" },
+                "columns": [
+                    { "name": "o_orderkey", "type": "integer" }
+                ]
+            }
+        ]
+    }"#;
+
+    fn assert_manifest(manifest: &Manifest) {
+        assert_eq!(manifest.catalog, "wren");
+        assert_eq!(manifest.models.len(), 1);
+        assert_eq!(
+            manifest.models[0].table_reference.as_deref(),
+            Some("public.orders")
+        );
+    }
+
+    #[test]
+    fn test_decode_raw_json() {
+        assert_manifest(&decode_manifest(MANIFEST).unwrap());
+    }
+
+    #[test]
+    fn test_decode_base64_variants() {
+        for engine in [&STANDARD, &STANDARD_NO_PAD, &URL_SAFE, &URL_SAFE_NO_PAD] {
+            let encoded = engine.encode(MANIFEST);
+            assert_manifest(&decode_manifest(&encoded).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_decode_gzip() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(MANIFEST.as_bytes()).unwrap();
+        let encoded = STANDARD.encode(encoder.finish().unwrap());
+        assert_manifest(&decode_manifest(&encoded).unwrap());
+    }
+
+    #[test]
+    fn test_decode_zstd() {
+        let compressed = zstd::encode_all(MANIFEST.as_bytes(), 0).unwrap();
+        let encoded = URL_SAFE_NO_PAD.encode(compressed);
+        assert_manifest(&decode_manifest(&encoded).unwrap());
+    }
+
+    #[test]
+    fn test_decode_invalid_base64() {
+        let err = decode_manifest("not base64 !!").unwrap_err();
+        assert!(matches!(err, Error::Mdl { .. }));
+    }
+
+    #[test]
+    fn test_error_reports_json_path() {
+        let json = r#"{"catalog": "wren", "schema": "public", "models": [{"name": "orders", "columns": [{"name": "id"}]}]}"#;
+        let err = decode_manifest(&STANDARD.encode(json)).unwrap_err();
+        let Error::Mdl {
+            message,
+            path,
+            line,
+            ..
+        } = &err
+        else {
+            panic!("expected MDL error");
+        };
+        assert_eq!(path.as_deref(), Some("models[0].columns[0]"));
+        assert_eq!(*line, Some(1));
+        assert!(message.contains("missing field `type`"), "{message}");
+        assert!(
+            err.to_string().contains("`models[0].columns[0]` (line 1"),
+            "{err}"
+        );
+    }
+}
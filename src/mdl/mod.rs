@@ -1,4 +1,14 @@
 //! MDL 模块 - Model Definition Language 处理
+pub mod analyzed;
 pub mod cls;
+pub mod data_source;
+pub mod lineage;
+pub mod loader;
 pub mod manifest;
-mod utils;
+pub(crate) mod utils;
+pub mod validator;
+
+pub use analyzed::AnalyzedMdl;
+pub use lineage::{ColumnRef, Lineage};
+pub use loader::decode_manifest;
+pub use validator::validate_manifest;
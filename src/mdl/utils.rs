@@ -1,4 +1,15 @@
-use sqlparser::{ast::Ident, dialect::GenericDialect, parser::Parser, parser::ParserError};
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+
+use sqlparser::{
+    ast::{
+        visit_expressions, Expr, FunctionArg, FunctionArgExpr, Ident, Query, Statement,
+        TableFactor, TableFunctionArgs, Visit, Visitor,
+    },
+    dialect::GenericDialect,
+    parser::Parser,
+    parser::ParserError,
+};
 
 /// 解析 SQL 多部分标识符
 pub(crate) fn parse_identifiers(s: &str) -> Result<Vec<Ident>, ParserError> {
@@ -23,6 +34,128 @@ pub(crate) fn parse_identifiers_normalized(
     })
 }
 
+/// 解析单个 SQL 表达式（如 relationship 的 condition、计算列的 expression）
+pub(crate) fn parse_expr(s: &str) -> Result<Expr, ParserError> {
+    let dialect = GenericDialect;
+    let mut parser = Parser::new(&dialect).try_with_sql(s)?;
+    let expr = parser.parse_expr()?;
+    parser.expect_token(&sqlparser::tokenizer::Token::EOF)?;
+    Ok(expr)
+}
+
+/// 解析单条 SQL 语句（如 view 的 statement）
+pub(crate) fn parse_statement(s: &str) -> Result<Statement, ParserError> {
+    let dialect = GenericDialect;
+    let mut statements = Parser::parse_sql(&dialect, s)?;
+    match statements.len() {
+        1 => Ok(statements.remove(0)),
+        n => Err(ParserError::ParserError(format!(
+            "expected exactly one statement, found {n}"
+        ))),
+    }
+}
+
+/// 收集表达式中引用的所有列路径，`a.b.c` 返回 `["a", "b", "c"]`
+pub(crate) fn column_references(expr: &Expr) -> Vec<Vec<String>> {
+    let mut references = Vec::new();
+    let _ = visit_expressions(expr, |e| {
+        match e {
+            Expr::Identifier(ident) => references.push(vec![ident.value.clone()]),
+            Expr::CompoundIdentifier(idents) => {
+                references.push(idents.iter().map(|i| i.value.clone()).collect())
+            }
+            _ => {}
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    references
+}
+
+/// 语句中的表引用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TableReference {
+    /// 表名的各部分，`a.b.c` 为 `["a", "b", "c"]`
+    pub name: Vec<String>,
+    /// 表函数的参数，普通表为 `None`
+    pub args: Option<Vec<Ident>>,
+}
+
+/// 表函数的参数，只接受不带名称的标识符或字符串，其他写法返回 `None`
+pub(crate) fn table_function_args(args: &TableFunctionArgs) -> Option<Vec<Ident>> {
+    args.args
+        .iter()
+        .map(|arg| match arg {
+            FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => match expr {
+                Expr::Identifier(ident) => Some(ident.clone()),
+                Expr::Value(value) => value.value.clone().into_string().map(Ident::new),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// 收集语句中引用的所有表和表函数，语句内 CTE 定义的名称不计入
+pub(crate) fn table_references(statement: &Statement) -> Vec<TableReference> {
+    struct CteCollector(HashSet<String>);
+
+    impl Visitor for CteCollector {
+        type Break = ();
+
+        fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+            if let Some(with) = &query.with {
+                self.0.extend(
+                    with.cte_tables
+                        .iter()
+                        .map(|cte| cte.alias.name.value.to_lowercase()),
+                );
+            }
+            ControlFlow::Continue(())
+        }
+    }
+
+    let mut ctes = CteCollector(HashSet::new());
+    let _ = statement.visit(&mut ctes);
+
+    struct ReferenceCollector {
+        ctes: HashSet<String>,
+        references: Vec<TableReference>,
+    }
+
+    impl Visitor for ReferenceCollector {
+        type Break = ();
+
+        fn pre_visit_table_factor(&mut self, table_factor: &TableFactor) -> ControlFlow<()> {
+            let TableFactor::Table { name, args, .. } = table_factor else {
+                return ControlFlow::Continue(());
+            };
+            let parts = name
+                .0
+                .iter()
+                .filter_map(|part| part.as_ident().map(|i| i.value.clone()))
+                .collect::<Vec<_>>();
+            let is_cte = args.is_none()
+                && matches!(parts.as_slice(), [name] if self.ctes.contains(&name.to_lowercase()));
+            if !is_cte && !parts.is_empty() {
+                self.references.push(TableReference {
+                    name: parts,
+                    args: args
+                        .as_ref()
+                        .map(|args| table_function_args(args).unwrap_or_default()),
+                });
+            }
+            ControlFlow::Continue(())
+        }
+    }
+
+    let mut collector = ReferenceCollector {
+        ctes: ctes.0,
+        references: Vec::new(),
+    };
+    let _ = statement.visit(&mut collector);
+    collector.references
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,944 @@
+//! MDL 语义校验 - 检查 manifest 的引用完整性
+//!
+//! 反序列化只保证 JSON 结构正确，这里进一步检查 model / column / relationship /
+//! metric / view 之间的引用关系，并一次性收集所有问题而不是遇到第一个就返回。
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::ops::ControlFlow;
+
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{visit_expressions, Expr, Ident, Statement};
+
+use crate::error::{Error, Result};
+use crate::mdl::analyzed::normalize_name;
+use crate::mdl::manifest::{Manifest, Model, Relationship};
+use crate::mdl::utils::{parse_expr, parse_statement, table_references};
+
+/// 校验问题代码，字符串形式保持稳定，供调用方按代码处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum IssueCode {
+    /// catalog 或 schema 为空
+    EmptyNamespace,
+    /// 对象名称为空
+    EmptyName,
+    /// 同名对象重复定义
+    DuplicateName,
+    /// model 没有声明数据来源
+    MissingSource,
+    /// model 同时声明了多个数据来源
+    AmbiguousSource,
+    /// 引用了不存在的 model
+    UnknownModel,
+    /// 引用了不存在的 relationship
+    UnknownRelationship,
+    /// 引用了不存在的列
+    UnknownColumn,
+    /// primary key 不是 model 的列
+    InvalidPrimaryKey,
+    /// 计算列缺少表达式
+    MissingExpression,
+    /// 表达式或条件无法解析
+    InvalidExpression,
+    /// relationship 的 models 数量不是 2
+    InvalidRelationshipModels,
+    /// 列引用的 relationship 未关联该列所在的 model
+    RelationshipNotLinked,
+    /// metric 的 base object 不存在
+    UnknownBaseObject,
+    /// view 的语句无法解析或不是查询
+    InvalidViewStatement,
+    /// view 引用了 MDL 命名空间中不存在的对象
+    UnknownObject,
+    /// view 之间循环引用
+    CircularReference,
+}
+
+impl IssueCode {
+    /// 稳定的机器可读代码
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IssueCode::EmptyNamespace => "EMPTY_NAMESPACE",
+            IssueCode::EmptyName => "EMPTY_NAME",
+            IssueCode::DuplicateName => "DUPLICATE_NAME",
+            IssueCode::MissingSource => "MISSING_SOURCE",
+            IssueCode::AmbiguousSource => "AMBIGUOUS_SOURCE",
+            IssueCode::UnknownModel => "UNKNOWN_MODEL",
+            IssueCode::UnknownRelationship => "UNKNOWN_RELATIONSHIP",
+            IssueCode::UnknownColumn => "UNKNOWN_COLUMN",
+            IssueCode::InvalidPrimaryKey => "INVALID_PRIMARY_KEY",
+            IssueCode::MissingExpression => "MISSING_EXPRESSION",
+            IssueCode::InvalidExpression => "INVALID_EXPRESSION",
+            IssueCode::InvalidRelationshipModels => "INVALID_RELATIONSHIP_MODELS",
+            IssueCode::RelationshipNotLinked => "RELATIONSHIP_NOT_LINKED",
+            IssueCode::UnknownBaseObject => "UNKNOWN_BASE_OBJECT",
+            IssueCode::InvalidViewStatement => "INVALID_VIEW_STATEMENT",
+            IssueCode::UnknownObject => "UNKNOWN_OBJECT",
+            IssueCode::CircularReference => "CIRCULAR_REFERENCE",
+        }
+    }
+}
+
+impl Display for IssueCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 单个校验问题
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    pub code: IssueCode,
+    /// 出问题的对象路径，如 `models[orders].columns[customer]`
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}: {}", self.code, self.path, self.message)
+    }
+}
+
+/// 校验 manifest，有任何问题时返回包含全部问题的 `Error::MdlValidation`
+pub fn validate_manifest(manifest: &Manifest) -> Result<()> {
+    let issues = check_manifest(manifest);
+    if issues.is_empty() {
+        return Ok(());
+    }
+
+    let details = issues
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ");
+    Err(Error::MdlValidation {
+        message: format!("manifest has {} problem(s): {details}", issues.len()),
+        issues,
+    })
+}
+
+/// 校验 manifest 并返回所有问题
+pub fn check_manifest(manifest: &Manifest) -> Vec<ValidationIssue> {
+    let mut validator = Validator::new(manifest);
+    validator.check();
+    validator.issues
+}
+
+struct Validator<'a> {
+    manifest: &'a Manifest,
+    /// 以下索引均以规范化名称为键，与 `AnalyzedMdl` 一致
+    models: HashMap<String, &'a Model>,
+    relationships: HashMap<String, &'a Relationship>,
+    metrics: HashSet<String>,
+    issues: Vec<ValidationIssue>,
+}
+
+impl<'a> Validator<'a> {
+    fn new(manifest: &'a Manifest) -> Self {
+        Self {
+            manifest,
+            models: manifest
+                .models
+                .iter()
+                .map(|m| (normalize_name(&m.name), m.as_ref()))
+                .collect(),
+            relationships: manifest
+                .relationships
+                .iter()
+                .map(|r| (normalize_name(&r.name), r.as_ref()))
+                .collect(),
+            metrics: manifest
+                .metrics
+                .iter()
+                .map(|m| normalize_name(&m.name))
+                .collect(),
+            issues: Vec::new(),
+        }
+    }
+
+    fn report(&mut self, code: IssueCode, path: impl Into<String>, message: impl Into<String>) {
+        self.issues.push(ValidationIssue {
+            code,
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+
+    fn check(&mut self) {
+        if self.manifest.catalog.is_empty() {
+            self.report(IssueCode::EmptyNamespace, "catalog", "catalog is empty");
+        }
+        if self.manifest.schema.is_empty() {
+            self.report(IssueCode::EmptyNamespace, "schema", "schema is empty");
+        }
+
+        self.check_object_names();
+        for model in &self.manifest.models {
+            self.check_model(model);
+        }
+        for relationship in &self.manifest.relationships {
+            self.check_relationship(relationship);
+        }
+        self.check_metrics();
+        self.check_views();
+    }
+
+    /// model / metric / view 共享同一命名空间，relationship 单独一个命名空间
+    fn check_object_names(&mut self) {
+        let manifest = self.manifest;
+        let objects = manifest
+            .models
+            .iter()
+            .enumerate()
+            .map(|(i, m)| ("models", i, m.name.as_str()))
+            .chain(
+                manifest
+                    .metrics
+                    .iter()
+                    .enumerate()
+                    .map(|(i, m)| ("metrics", i, m.name.as_str())),
+            )
+            .chain(
+                manifest
+                    .views
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| ("views", i, v.name.as_str())),
+            );
+        self.check_unique(objects);
+
+        let relationships = manifest
+            .relationships
+            .iter()
+            .enumerate()
+            .map(|(i, r)| ("relationships", i, r.name.as_str()));
+        self.check_unique(relationships);
+    }
+
+    fn check_unique<'b>(&mut self, names: impl Iterator<Item = (&'b str, usize, &'b str)>) {
+        let mut seen: HashMap<String, String> = HashMap::new();
+        for (kind, index, name) in names {
+            let path = object_path(kind, index, name);
+            if name.is_empty() {
+                self.report(IssueCode::EmptyName, path, "name is empty");
+                continue;
+            }
+            match seen.entry(normalize_name(name)) {
+                Entry::Occupied(first) => {
+                    let message = format!("`{name}` is already defined at {}", first.get());
+                    self.report(IssueCode::DuplicateName, path, message);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(path);
+                }
+            }
+        }
+    }
+
+    fn check_model(&mut self, model: &'a Model) {
+        let model_path = format!("models[{}]", model.name);
+
+        let sources = [
+            model.table_reference.is_some(),
+            model.ref_sql.is_some(),
+            model.base_object.is_some(),
+        ]
+        .into_iter()
+        .filter(|s| *s)
+        .count();
+        match sources {
+            0 => self.report(
+                IssueCode::MissingSource,
+                &model_path,
+                "one of tableReference, refSql or baseObject is required",
+            ),
+            1 => {}
+            _ => self.report(
+                IssueCode::AmbiguousSource,
+                &model_path,
+                "only one of tableReference, refSql or baseObject may be set",
+            ),
+        }
+
+        if let Some(base_object) = &model.base_object {
+            if !self.models.contains_key(&normalize_name(base_object)) {
+                self.report(
+                    IssueCode::UnknownBaseObject,
+                    format!("{model_path}.baseObject"),
+                    format!("model `{base_object}` does not exist"),
+                );
+            }
+        }
+
+        if let Some(ref_sql) = &model.ref_sql {
+            if let Err(e) = parse_statement(ref_sql) {
+                self.report(
+                    IssueCode::InvalidExpression,
+                    format!("{model_path}.refSql"),
+                    format!("failed to parse refSql: {e}"),
+                );
+            }
+        }
+
+        for rule in &model.row_level_access_controls {
+            if let Err(e) = parse_expr(&rule.condition) {
+                self.report(
+                    IssueCode::InvalidExpression,
+                    format!(
+                        "{model_path}.rowLevelAccessControls[{}].condition",
+                        rule.name
+                    ),
+                    format!("failed to parse condition: {e}"),
+                );
+            }
+        }
+
+        let columns_path = format!("{model_path}.columns");
+        let column_names = model
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (columns_path.as_str(), i, c.name.as_str()));
+        self.check_unique(column_names);
+
+        if let Some(primary_key) = &model.primary_key {
+            if !has_column(model, primary_key) {
+                self.report(
+                    IssueCode::InvalidPrimaryKey,
+                    format!("{model_path}.primaryKey"),
+                    format!("`{primary_key}` is not a column of model `{}`", model.name),
+                );
+            }
+        }
+
+        for column in &model.columns {
+            let column_path = format!("{model_path}.columns[{}]", column.name);
+
+            if column.is_calculated && column.expression.is_none() {
+                self.report(
+                    IssueCode::MissingExpression,
+                    &column_path,
+                    "calculated column requires an expression",
+                );
+            }
+            if let Some(expression) = &column.expression {
+                if let Err(e) = parse_expr(expression) {
+                    self.report(
+                        IssueCode::InvalidExpression,
+                        format!("{column_path}.expression"),
+                        format!("failed to parse expression: {e}"),
+                    );
+                }
+            }
+
+            let Some(relationship_name) = &column.relationship else {
+                continue;
+            };
+            let Some(relationship) = self.relationships.get(&normalize_name(relationship_name))
+            else {
+                self.report(
+                    IssueCode::UnknownRelationship,
+                    format!("{column_path}.relationship"),
+                    format!("relationship `{relationship_name}` does not exist"),
+                );
+                continue;
+            };
+            if !contains_name(&relationship.models, &model.name) {
+                self.report(
+                    IssueCode::RelationshipNotLinked,
+                    format!("{column_path}.relationship"),
+                    format!(
+                        "relationship `{relationship_name}` does not involve model `{}`",
+                        model.name
+                    ),
+                );
+            }
+            // 关系列的 type 是目标 model 的名称
+            if !self.models.contains_key(&normalize_name(&column.r#type)) {
+                self.report(
+                    IssueCode::UnknownModel,
+                    format!("{column_path}.type"),
+                    format!(
+                        "relationship column type `{}` is not a model",
+                        column.r#type
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_relationship(&mut self, relationship: &'a Relationship) {
+        let path = format!("relationships[{}]", relationship.name);
+
+        if relationship.models.len() != 2 {
+            self.report(
+                IssueCode::InvalidRelationshipModels,
+                format!("{path}.models"),
+                format!(
+                    "relationship must link exactly 2 models, found {}",
+                    relationship.models.len()
+                ),
+            );
+        }
+        for (index, model) in relationship.models.iter().enumerate() {
+            if !self.models.contains_key(&normalize_name(model)) {
+                self.report(
+                    IssueCode::UnknownModel,
+                    format!("{path}.models[{index}]"),
+                    format!("model `{model}` does not exist"),
+                );
+            }
+        }
+
+        let condition = match parse_expr(&relationship.condition) {
+            Ok(condition) => condition,
+            Err(e) => {
+                self.report(
+                    IssueCode::InvalidExpression,
+                    format!("{path}.condition"),
+                    format!("failed to parse condition: {e}"),
+                );
+                return;
+            }
+        };
+
+        // 条件中的限定列必须形如 `model.column`，且 model 属于该 relationship
+        let mut references = Vec::new();
+        let _ = visit_expressions(&condition, |expr| {
+            if let Expr::CompoundIdentifier(idents) = expr {
+                if let [qualifier, column] = idents.as_slice() {
+                    references.push((qualifier.value.clone(), column.value.clone()));
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        for (qualifier, column) in references {
+            if !contains_name(&relationship.models, &qualifier) {
+                self.report(
+                    IssueCode::UnknownModel,
+                    format!("{path}.condition"),
+                    format!("`{qualifier}` is not one of the related models"),
+                );
+                continue;
+            }
+            let Some(model) = self.models.get(&normalize_name(&qualifier)) else {
+                continue;
+            };
+            if !has_column(model, &column) {
+                self.report(
+                    IssueCode::UnknownColumn,
+                    format!("{path}.condition"),
+                    format!("model `{qualifier}` has no column `{column}`"),
+                );
+            }
+        }
+    }
+
+    fn check_metrics(&mut self) {
+        for metric in &self.manifest.metrics {
+            let path = format!("metrics[{}]", metric.name);
+
+            let base_object = normalize_name(&metric.base_object);
+            let base_model = match self.models.get(&base_object) {
+                Some(model) => Some(*model),
+                None if self.metrics.contains(&base_object) => None,
+                None => {
+                    self.report(
+                        IssueCode::UnknownBaseObject,
+                        format!("{path}.baseObject"),
+                        format!(
+                            "base object `{}` is neither a model nor a metric",
+                            metric.base_object
+                        ),
+                    );
+                    None
+                }
+            };
+
+            for (kind, columns) in [
+                ("dimension", &metric.dimension),
+                ("measure", &metric.measure),
+            ] {
+                for column in columns {
+                    if let Some(expression) = &column.expression {
+                        if let Err(e) = parse_expr(expression) {
+                            self.report(
+                                IssueCode::InvalidExpression,
+                                format!("{path}.{kind}[{}].expression", column.name),
+                                format!("failed to parse expression: {e}"),
+                            );
+                        }
+                    }
+                }
+            }
+
+            for time_grain in &metric.time_grain {
+                let ref_column = time_grain.ref_column.as_str();
+                let is_dimension = metric
+                    .dimension
+                    .iter()
+                    .any(|c| normalize_name(&c.name) == normalize_name(ref_column));
+                let is_base_column = base_model.is_some_and(|model| has_column(model, ref_column));
+                if !is_dimension && !is_base_column {
+                    self.report(
+                        IssueCode::UnknownColumn,
+                        format!("{path}.timeGrain[{}].refColumn", time_grain.name),
+                        format!(
+                            "`{ref_column}` is neither a dimension of the metric nor a column of `{}`",
+                            metric.base_object
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    /// 校验 view 语句：必须是单条查询，未限定或限定在 MDL 命名空间下的引用必须存在，
+    /// 且 view 之间不能循环引用
+    fn check_views(&mut self) {
+        let manifest = self.manifest;
+        let views = manifest
+            .views
+            .iter()
+            .map(|v| (normalize_name(&v.name), v.name.as_str()))
+            .collect::<HashMap<_, _>>();
+        let objects = manifest
+            .models
+            .iter()
+            .map(|m| normalize_name(&m.name))
+            .chain(manifest.metrics.iter().map(|m| normalize_name(&m.name)))
+            .chain(views.keys().cloned())
+            .collect::<HashSet<_>>();
+        let in_namespace = |a: &str, b: &str| normalize_name(a) == normalize_name(b);
+
+        let mut dependencies = BTreeMap::<String, Vec<String>>::new();
+        for view in &manifest.views {
+            let path = format!("views[{}].statement", view.name);
+            let statement = match parse_statement(&view.statement) {
+                Ok(statement @ Statement::Query(_)) => statement,
+                Ok(_) => {
+                    self.report(
+                        IssueCode::InvalidViewStatement,
+                        path,
+                        "view statement must be a SELECT query",
+                    );
+                    continue;
+                }
+                Err(e) => {
+                    self.report(
+                        IssueCode::InvalidViewStatement,
+                        path,
+                        format!("failed to parse view statement: {e}"),
+                    );
+                    continue;
+                }
+            };
+
+            let referenced = dependencies.entry(normalize_name(&view.name)).or_default();
+            for reference in table_references(&statement) {
+                if let Some(args) = &reference.args {
+                    self.check_table_function(&path, &reference.name, args);
+                    continue;
+                }
+                let object = match reference.name.as_slice() {
+                    [object] => object,
+                    [schema, object] if in_namespace(schema, &manifest.schema) => object,
+                    [catalog, schema, object]
+                        if in_namespace(catalog, &manifest.catalog)
+                            && in_namespace(schema, &manifest.schema) =>
+                    {
+                        object
+                    }
+                    _ => continue,
+                };
+                let normalized = normalize_name(object);
+                if views.contains_key(&normalized) {
+                    referenced.push(normalized);
+                } else if !objects.contains(&normalized) {
+                    self.report(
+                        IssueCode::UnknownObject,
+                        path.clone(),
+                        format!("`{}` is not defined in the MDL", reference.name.join(".")),
+                    );
+                }
+            }
+        }
+
+        for cycle in view_cycles(&dependencies) {
+            let names = cycle
+                .iter()
+                .map(|v| views.get(v).copied().unwrap_or(v.as_str()))
+                .collect::<Vec<_>>();
+            self.report(
+                IssueCode::CircularReference,
+                format!("views[{}].statement", names[0]),
+                format!("circular view reference: {}", names.join(" -> ")),
+            );
+        }
+    }
+
+    /// view 中只能使用 `roll_up(metric, time_grain, unit)` 表函数，且 metric 必须存在
+    fn check_table_function(&mut self, path: &str, name: &[String], args: &[Ident]) {
+        if !matches!(name, [function] if normalize_name(function) == "roll_up") {
+            self.report(
+                IssueCode::InvalidViewStatement,
+                path,
+                format!("table function `{}` is not supported", name.join(".")),
+            );
+            return;
+        }
+        let [metric, _, _] = args else {
+            self.report(
+                IssueCode::InvalidViewStatement,
+                path,
+                "roll_up expects (metric, time_grain, unit) arguments",
+            );
+            return;
+        };
+        if !self.metrics.contains(&normalize_name(&metric.value)) {
+            self.report(
+                IssueCode::UnknownObject,
+                path,
+                format!("metric `{}` is not defined in the MDL", metric.value),
+            );
+        }
+    }
+}
+
+/// 深度优先查找 view 依赖图中的环，每个环只报告一次，首尾为同一个 view
+fn view_cycles(dependencies: &BTreeMap<String, Vec<String>>) -> Vec<Vec<String>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        Visiting,
+        Done,
+    }
+
+    fn visit<'a>(
+        dependencies: &'a BTreeMap<String, Vec<String>>,
+        view: &'a String,
+        states: &mut HashMap<&'a String, State>,
+        stack: &mut Vec<&'a String>,
+        cycles: &mut Vec<Vec<String>>,
+    ) {
+        match states.get(view) {
+            Some(State::Done) => return,
+            Some(State::Visiting) => {
+                let start = stack.iter().position(|v| *v == view).unwrap_or(0);
+                cycles.push(
+                    stack[start..]
+                        .iter()
+                        .chain(std::iter::once(&view))
+                        .map(|v| v.to_string())
+                        .collect(),
+                );
+                return;
+            }
+            None => {}
+        }
+
+        states.insert(view, State::Visiting);
+        stack.push(view);
+        for next in dependencies.get(view).into_iter().flatten() {
+            visit(dependencies, next, states, stack, cycles);
+        }
+        stack.pop();
+        states.insert(view, State::Done);
+    }
+
+    let mut states = HashMap::new();
+    let mut stack = Vec::new();
+    let mut cycles = Vec::new();
+    for view in dependencies.keys() {
+        visit(dependencies, view, &mut states, &mut stack, &mut cycles);
+    }
+    cycles
+}
+
+/// 名称列表中是否包含 `name`（大小写不敏感）
+fn contains_name(names: &[String], name: &str) -> bool {
+    let name = normalize_name(name);
+    names.iter().any(|n| normalize_name(n) == name)
+}
+
+/// model 是否有名为 `column` 的列（大小写不敏感）
+fn has_column(model: &Model, column: &str) -> bool {
+    let column = normalize_name(column);
+    model
+        .columns
+        .iter()
+        .any(|c| normalize_name(&c.name) == column)
+}
+
+fn object_path(kind: &str, index: usize, name: &str) -> String {
+    if name.is_empty() {
+        format!("{kind}[{index}]")
+    } else {
+        format!("{kind}[{name}]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdl::decode_manifest;
+
+    fn manifest(json: &str) -> Manifest {
+        decode_manifest(json).unwrap()
+    }
+
+    fn codes(issues: &[ValidationIssue]) -> Vec<(&'static str, &str)> {
+        issues
+            .iter()
+            .map(|i| (i.code.as_str(), i.path.as_str()))
+            .collect()
+    }
+
+    const VALID: &str = r#"{
+        "catalog": "wren",
+        "schema": "public",
+        "models": [
+            {
+                "name": "orders",
+                "tableReference": { "table": "orders" },
+                "primaryKey": "o_orderkey",
+                "columns": [
+                    { "name": "o_orderkey", "type": "integer" },
+                    { "name": "o_custkey", "type": "integer" },
+                    { "name": "o_orderdate", "type": "date" },
+                    { "name": "customer", "type": "customer", "relationship": "orders_customer" },
+                    { "name": "customer_name", "type": "varchar", "isCalculated": true, "expression": "customer.c_name" }
+                ]
+            },
+            {
+                "name": "customer",
+                "tableReference": { "table": "customer" },
+                "primaryKey": "c_custkey",
+                "columns": [
+                    { "name": "c_custkey", "type": "integer" },
+                    { "name": "c_name", "type": "varchar" }
+                ]
+            }
+        ],
+        "relationships": [
+            {
+                "name": "orders_customer",
+                "models": ["orders", "customer"],
+                "joinType": "MANY_TO_ONE",
+                "condition": "orders.o_custkey = customer.c_custkey"
+            }
+        ],
+        "metrics": [
+            {
+                "name": "order_count",
+                "baseObject": "orders",
+                "dimension": [{ "name": "o_custkey", "type": "integer" }],
+                "measure": [{ "name": "cnt", "type": "bigint", "expression": "count(*)" }],
+                "timeGrain": [{ "name": "order_date", "refColumn": "o_orderdate", "dateParts": ["Month"] }]
+            }
+        ],
+        "views": [
+            { "name": "recent_orders", "statement": "SELECT * FROM orders" }
+        ]
+    }"#;
+
+    #[test]
+    fn test_valid_manifest() {
+        let manifest = manifest(VALID);
+        assert_eq!(check_manifest(&manifest), vec![]);
+        assert!(validate_manifest(&manifest).is_ok());
+    }
+
+    #[test]
+    fn test_reports_all_dangling_references() {
+        let json = VALID
+            .replace(
+                r#""models": ["orders", "customer"]"#,
+                r#""models": ["orders", "client"]"#,
+            )
+            .replace(
+                r#""relationship": "orders_customer""#,
+                r#""relationship": "orders_client""#,
+            )
+            .replace(r#""primaryKey": "o_orderkey""#, r#""primaryKey": "id""#)
+            .replace(r#""baseObject": "orders""#, r#""baseObject": "purchases""#)
+            .replace(
+                r#""refColumn": "o_orderdate""#,
+                r#""refColumn": "created_at""#,
+            );
+        let issues = check_manifest(&manifest(&json));
+
+        assert_eq!(
+            codes(&issues),
+            vec![
+                ("INVALID_PRIMARY_KEY", "models[orders].primaryKey"),
+                (
+                    "UNKNOWN_RELATIONSHIP",
+                    "models[orders].columns[customer].relationship"
+                ),
+                ("UNKNOWN_MODEL", "relationships[orders_customer].models[1]"),
+                ("UNKNOWN_MODEL", "relationships[orders_customer].condition"),
+                ("UNKNOWN_BASE_OBJECT", "metrics[order_count].baseObject"),
+                (
+                    "UNKNOWN_COLUMN",
+                    "metrics[order_count].timeGrain[order_date].refColumn"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_names_are_case_insensitive() {
+        let json = VALID
+            .replace(
+                r#""models": ["orders", "customer"]"#,
+                r#""models": ["Orders", "CUSTOMER"]"#,
+            )
+            .replace(
+                r#""relationship": "orders_customer""#,
+                r#""relationship": "Orders_Customer""#,
+            )
+            .replace(
+                r#""condition": "orders.o_custkey = customer.c_custkey""#,
+                r#""condition": "ORDERS.O_CUSTKEY = Customer.C_CustKey""#,
+            )
+            .replace(
+                r#""primaryKey": "o_orderkey""#,
+                r#""primaryKey": "O_OrderKey""#,
+            )
+            .replace(r#""type": "customer""#, r#""type": "Customer""#)
+            .replace(r#""baseObject": "orders""#, r#""baseObject": "ORDERS""#)
+            .replace(
+                r#""refColumn": "o_orderdate""#,
+                r#""refColumn": "O_ORDERDATE""#,
+            );
+        assert_eq!(check_manifest(&manifest(&json)), vec![]);
+
+        let json = VALID.replace(
+            r#"{ "name": "recent_orders", "statement": "SELECT * FROM orders" }"#,
+            r#"{ "name": "Customer", "statement": "SELECT * FROM orders" }"#,
+        );
+        assert_eq!(
+            codes(&check_manifest(&manifest(&json))),
+            vec![("DUPLICATE_NAME", "views[Customer]")]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_names_and_missing_source() {
+        let json = r#"{
+            "catalog": "wren",
+            "schema": "public",
+            "models": [
+                { "name": "orders", "columns": [
+                    { "name": "id", "type": "integer" },
+                    { "name": "id", "type": "integer" },
+                    { "name": "total", "type": "integer", "isCalculated": true }
+                ] }
+            ],
+            "views": [{ "name": "orders", "statement": "SELEC 1" }]
+        }"#;
+        let issues = check_manifest(&manifest(json));
+
+        assert_eq!(
+            codes(&issues),
+            vec![
+                ("DUPLICATE_NAME", "views[orders]"),
+                ("MISSING_SOURCE", "models[orders]"),
+                ("DUPLICATE_NAME", "models[orders].columns[id]"),
+                ("MISSING_EXPRESSION", "models[orders].columns[total]"),
+                ("INVALID_VIEW_STATEMENT", "views[orders].statement"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_view_references_and_cycles() {
+        let json = VALID.replace(
+            r#"{ "name": "recent_orders", "statement": "SELECT * FROM orders" }"#,
+            r#"{ "name": "a", "statement": "SELECT * FROM b JOIN external.t ON true" },
+               { "name": "b", "statement": "WITH a AS (SELECT 1) SELECT * FROM a, public.C" },
+               { "name": "c", "statement": "SELECT * FROM wren.public.a" },
+               { "name": "d", "statement": "SELECT * FROM public.missing" },
+               { "name": "e", "statement": "DELETE FROM orders" },
+               { "name": "f", "statement": "SELECT * FROM Orders JOIN lineitem ON true" }"#,
+        );
+        let issues = check_manifest(&manifest(&json));
+
+        assert_eq!(
+            codes(&issues),
+            vec![
+                ("UNKNOWN_OBJECT", "views[d].statement"),
+                ("INVALID_VIEW_STATEMENT", "views[e].statement"),
+                ("UNKNOWN_OBJECT", "views[f].statement"),
+                ("CIRCULAR_REFERENCE", "views[a].statement"),
+            ]
+        );
+        assert_eq!(issues[2].message, "`lineitem` is not defined in the MDL");
+        assert_eq!(
+            issues[3].message,
+            "circular view reference: a -> b -> c -> a"
+        );
+    }
+
+    #[test]
+    fn test_view_table_functions() {
+        let json = VALID.replace(
+            r#"{ "name": "recent_orders", "statement": "SELECT * FROM orders" }"#,
+            r#"{ "name": "monthly", "statement": "SELECT * FROM roll_up(Order_Count, order_date, Month)" },
+               { "name": "missing", "statement": "SELECT * FROM roll_up(revenue, order_date, Month)" },
+               { "name": "partial", "statement": "SELECT * FROM roll_up(order_count)" },
+               { "name": "csv", "statement": "SELECT * FROM read_csv('/etc/passwd')" }"#,
+        );
+        let issues = check_manifest(&manifest(&json));
+
+        assert_eq!(
+            codes(&issues),
+            vec![
+                ("UNKNOWN_OBJECT", "views[missing].statement"),
+                ("INVALID_VIEW_STATEMENT", "views[partial].statement"),
+                ("INVALID_VIEW_STATEMENT", "views[csv].statement"),
+            ]
+        );
+        assert_eq!(
+            issues[0].message,
+            "metric `revenue` is not defined in the MDL"
+        );
+    }
+
+    #[test]
+    fn test_validate_manifest_error() {
+        let json = VALID
+            .replace(r#""primaryKey": "c_custkey""#, r#""primaryKey": "id""#)
+            .replace(r#""baseObject": "orders""#, r#""baseObject": "purchases""#);
+        let err = validate_manifest(&manifest(&json)).unwrap_err();
+        let Error::MdlValidation { issues, .. } = &err else {
+            panic!("expected validation error");
+        };
+        assert_eq!(
+            codes(issues),
+            vec![
+                ("INVALID_PRIMARY_KEY", "models[customer].primaryKey"),
+                ("UNKNOWN_BASE_OBJECT", "metrics[order_count].baseObject"),
+                (
+                    "UNKNOWN_COLUMN",
+                    "metrics[order_count].timeGrain[order_date].refColumn"
+                ),
+            ]
+        );
+
+        let detail = serde_json::to_value(err.detail().unwrap()).unwrap();
+        assert_eq!(detail["path"], "models[customer].primaryKey");
+        assert_eq!(
+            detail["issues"][0],
+            serde_json::json!({
+                "code": "INVALID_PRIMARY_KEY",
+                "path": "models[customer].primaryKey",
+                "message": "`id` is not a column of model `customer`"
+            })
+        );
+        assert_eq!(detail["issues"].as_array().unwrap().len(), 3);
+    }
+}
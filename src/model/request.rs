@@ -1,7 +1,11 @@
 //! 请求模型 (DTO)
 
+use std::collections::{BTreeMap, HashMap};
+
 use serde::{Deserialize, Serialize};
 
+use crate::error::{Error, Result};
+
 /// 查询请求
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryRequest {
@@ -11,6 +15,59 @@ pub struct QueryRequest {
     pub manifest_str: String,
     /// 连接信息
     pub connection_info: ConnectionInfo,
+    /// 会话属性，用于行级 / 列级访问控制
+    #[serde(default)]
+    pub session_properties: HashMap<String, String>,
+    /// 查询 ID，用于通过 `DELETE /v3/queries/{id}` 取消查询；未指定时自动生成
+    #[serde(default)]
+    pub query_id: Option<String>,
+    /// 超时时间（秒），不能超过服务端配置的上限
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// 最多返回的行数，不能超过服务端配置的上限
+    #[serde(default)]
+    pub limit: Option<u64>,
+    /// 以分块传输的 JSON 流式返回结果；`Accept` 指定 NDJSON、Arrow IPC 流、CSV 或 Parquet 时以该格式返回
+    #[serde(default)]
+    pub stream: bool,
+    /// `Accept: text/csv` 时的 CSV 输出选项
+    #[serde(default)]
+    pub csv: CsvOptions,
+}
+
+/// CSV 输出选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CsvOptions {
+    /// 字段分隔符
+    pub delimiter: char,
+    /// 是否以列名作为首行
+    pub header: bool,
+    /// NULL 的表示，默认为空字段
+    pub null: String,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            header: true,
+            null: String::new(),
+        }
+    }
+}
+
+impl CsvOptions {
+    /// 分隔符不能是双引号或换行符
+    pub fn validate(&self) -> Result<()> {
+        if matches!(self.delimiter, '"' | '\r' | '\n') {
+            return Err(Error::Validation(format!(
+                "`csv.delimiter` cannot be {:?}",
+                self.delimiter
+            )));
+        }
+        Ok(())
+    }
 }
 
 /// 规划请求（不执行查询）
@@ -22,15 +79,58 @@ pub struct DryPlanRequest {
     pub manifest_str: String,
     /// 连接信息（可选）
     pub connection_info: Option<ConnectionInfo>,
+    /// 会话属性，用于行级 / 列级访问控制
+    #[serde(default)]
+    pub session_properties: HashMap<String, String>,
+    /// 是否在响应中附带规划诊断
+    #[serde(default)]
+    pub extended: bool,
 }
 
-/// 数据库连接信息
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 数据库连接信息，同时作为连接池的键
+///
+/// 嵌入式数据源（如 DuckDB）不需要主机、端口和账号，`database` 为数据库文件路径，
+/// 为空或 `:memory:` 时使用内存数据库
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ConnectionInfo {
+    #[serde(default)]
     pub host: String,
+    #[serde(default)]
     pub port: u16,
+    #[serde(default)]
     pub database: String,
+    #[serde(default)]
     pub user: String,
+    #[serde(default)]
     pub password: String,
     pub schema: Option<String>,
+    /// TLS 模式，默认不加密
+    #[serde(default)]
+    pub ssl_mode: SslMode,
+    /// 校验服务端证书使用的 CA 证书（PEM 文件路径，须位于 `files.allowed_roots` 中），
+    /// 未指定时使用内置的根证书
+    #[serde(default)]
+    pub ssl_ca: Option<String>,
+    /// 嵌入式数据源中作为表注册的本地文件：表名（`table` 或 `schema.table`，与 model 的
+    /// `table_reference` 对应）到文件路径
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+    /// 连接器特定的选项，供注册的插件连接器读取，内置连接器忽略
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+}
+
+/// 连接的 TLS 模式，含义与 libpq 的 `sslmode` 一致
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SslMode {
+    /// 不使用 TLS
+    #[default]
+    Disable,
+    /// 使用 TLS 加密，不校验证书
+    Require,
+    /// 校验证书由受信任的 CA 签发，不校验主机名
+    VerifyCa,
+    /// 校验证书和主机名
+    VerifyFull,
 }
@@ -2,6 +2,9 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::connector::Capabilities;
+use crate::engine::PlanDiagnostics;
+
 /// 查询响应
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryResponse {
@@ -9,6 +12,9 @@ pub struct QueryResponse {
     pub data: Vec<serde_json::Value>,
     /// 列元数据
     pub columns: Vec<ColumnInfo>,
+    /// 结果是否因行数上限被截断
+    #[serde(default)]
+    pub truncated: bool,
 }
 
 /// 列信息
@@ -23,4 +29,24 @@ pub struct ColumnInfo {
 pub struct DryPlanResponse {
     /// 规划后的 SQL
     pub sql: String,
+    /// 规划诊断，请求 `extended` 时返回
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<PlanDiagnostics>,
+}
+
+/// 连接器列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectorsResponse {
+    /// 按名称排序的连接器
+    pub connectors: Vec<ConnectorResponse>,
+}
+
+/// 已注册的连接器
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectorResponse {
+    /// 路径 `/v3/connector/{data_source}` 中使用的名称
+    pub name: String,
+    /// 改写 SQL 使用的方言
+    pub dialect: String,
+    pub capabilities: Capabilities,
 }